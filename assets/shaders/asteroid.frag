@@ -0,0 +1,21 @@
+#version 330 core
+
+#include "include/lighting.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in float shade;
+
+out vec4 color;
+
+uniform vec3 tint;
+uniform vec3 light_position;
+uniform vec3 light_color;
+
+const float AMBIENT = 0.02;
+
+void main() {
+    vec3 to_light = normalize(light_position - world_position);
+    vec3 albedo = tint * shade;
+    color = vec4(albedo * (lambert(normalize(world_normal), to_light) * light_color + AMBIENT), 1.0);
+}
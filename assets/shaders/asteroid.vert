@@ -0,0 +1,42 @@
+#version 330 core
+
+in vec3 position;
+in vec3 normal;
+
+in vec3 instance_position;
+in float instance_radius;
+in vec3 spin_axis;
+in vec2 spin;
+in float brightness;
+
+out vec3 world_position;
+out vec3 world_normal;
+out float shade;
+
+uniform mat4 view;
+uniform mat4 perspective;
+uniform vec3 camera_position;
+// Half the smallest apparent size of a rock, in radians.
+uniform float min_radius_angle;
+uniform float time;
+
+// Rotates `v` by `angle` around the unit vector `axis` (Rodrigues' formula).
+vec3 rotate(vec3 v, vec3 axis, float angle) {
+    float c = cos(angle);
+    float s = sin(angle);
+    return v * c + cross(axis, v) * s + axis * dot(axis, v) * (1.0 - c);
+}
+
+void main() {
+    float angle = spin.y + spin.x * time;
+    world_normal = rotate(normal, spin_axis, angle);
+    shade = brightness;
+
+    // Rocks far away are inflated so they cover at least a pixel instead of flickering.
+    float distance_to_camera = length(instance_position - camera_position);
+    float radius = max(instance_radius, distance_to_camera * min_radius_angle);
+
+    vec3 world = instance_position + rotate(position, spin_axis, angle) * radius;
+    world_position = world;
+    gl_Position = perspective * view * vec4(world, 1.0);
+}
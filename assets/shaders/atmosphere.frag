@@ -0,0 +1,100 @@
+#version 330 core
+
+// Single scattering through an exponential atmosphere. Everything is done in the frame of the
+// body, scaled so that its surface has radius 1.
+
+in vec3 world_position;
+
+out vec4 color;
+
+uniform vec3 center;
+uniform float radius;
+uniform vec3 camera_position;
+uniform vec3 light_position;
+
+uniform float height;
+uniform float rayleigh_scale_height;
+uniform float mie_scale_height;
+uniform vec3 rayleigh;
+uniform float mie;
+uniform float mie_g;
+uniform float sun_intensity;
+
+const int VIEW_SAMPLES = 16;
+const int LIGHT_SAMPLES = 8;
+const float PI = 3.14159265;
+
+// Distances along the ray to the two intersections with a sphere around the origin, or a
+// negative far distance if the ray misses it.
+vec2 intersect_sphere(vec3 origin, vec3 direction, float sphere_radius) {
+    float b = dot(origin, direction);
+    float c = dot(origin, origin) - sphere_radius * sphere_radius;
+    float discriminant = b * b - c;
+    if (discriminant < 0.0) {
+        return vec2(1.0, -1.0);
+    }
+    float root = sqrt(discriminant);
+    return vec2(-b - root, -b + root);
+}
+
+// Optical depth of gas (x) and haze (y) between `origin` and the top of the atmosphere.
+vec2 optical_depth_to_sun(vec3 origin, vec3 to_sun, float top) {
+    float distance_to_top = intersect_sphere(origin, to_sun, top).y;
+    float step_length = distance_to_top / float(LIGHT_SAMPLES);
+    vec2 depth = vec2(0.0);
+    for (int i = 0; i < LIGHT_SAMPLES; i++) {
+        vec3 point = origin + to_sun * (float(i) + 0.5) * step_length;
+        float altitude = length(point) - 1.0;
+        depth += exp(-altitude / vec2(rayleigh_scale_height, mie_scale_height)) * step_length;
+    }
+    return depth;
+}
+
+void main() {
+    float top = 1.0 + height;
+    vec3 origin = (camera_position - center) / radius;
+    vec3 direction = normalize(world_position - camera_position);
+    vec3 to_sun = normalize(light_position - center);
+
+    vec2 shell = intersect_sphere(origin, direction, top);
+    if (shell.y < 0.0) {
+        discard;
+    }
+    float start = max(shell.x, 0.0);
+    float end = shell.y;
+    vec2 ground = intersect_sphere(origin, direction, 1.0);
+    if (ground.x > 0.0) {
+        end = min(end, ground.x);
+    }
+
+    float step_length = (end - start) / float(VIEW_SAMPLES);
+    vec2 view_depth = vec2(0.0);
+    vec3 rayleigh_sum = vec3(0.0);
+    vec3 mie_sum = vec3(0.0);
+    for (int i = 0; i < VIEW_SAMPLES; i++) {
+        vec3 point = origin + direction * (start + (float(i) + 0.5) * step_length);
+        float altitude = length(point) - 1.0;
+        vec2 density = exp(-altitude / vec2(rayleigh_scale_height, mie_scale_height)) * step_length;
+        view_depth += density;
+
+        // Points in the planet's shadow receive no sunlight.
+        vec2 blocker = intersect_sphere(point, to_sun, 1.0);
+        if (blocker.x > 0.0) {
+            continue;
+        }
+        vec2 depth = view_depth + optical_depth_to_sun(point, to_sun, top);
+        vec3 transmittance = exp(-(rayleigh * depth.x + vec3(mie * 1.1 * depth.y)));
+        rayleigh_sum += transmittance * density.x;
+        mie_sum += transmittance * density.y;
+    }
+
+    float mu = dot(direction, to_sun);
+    float rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    float g2 = mie_g * mie_g;
+    float mie_phase = 3.0 / (8.0 * PI) * ((1.0 - g2) * (1.0 + mu * mu))
+        / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * mie_g * mu, 1.5));
+
+    vec3 scattered = sun_intensity
+        * (rayleigh_sum * rayleigh * rayleigh_phase + mie_sum * mie * mie_phase);
+    color = vec4(scattered, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec3 position;
+
+out vec3 world_position;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    vec4 world = model * vec4(position, 1.0);
+    world_position = world.xyz;
+    gl_Position = perspective * view * world;
+}
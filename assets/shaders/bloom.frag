@@ -0,0 +1,52 @@
+#version 330 core
+
+// The passes of the dual Kawase bloom, selected by a define from the program description.
+
+in vec2 uv;
+
+out vec4 color;
+
+uniform sampler2D source;
+uniform vec2 texel_size;
+
+#ifdef BLOOM_PREFILTER
+uniform float threshold;
+
+void main() {
+    vec3 scene = texture(source, uv).rgb;
+    float brightness = max(scene.r, max(scene.g, scene.b));
+    // Quadratic soft knee so bloom fades in instead of switching on at the threshold.
+    float knee = threshold * 0.5;
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-4);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 1e-4);
+    color = vec4(scene * contribution, 1.0);
+}
+#endif
+
+#ifdef BLOOM_DOWNSAMPLE
+void main() {
+    vec2 offset = texel_size * 0.5;
+    vec3 sum = texture(source, uv).rgb * 4.0;
+    sum += texture(source, uv - offset).rgb;
+    sum += texture(source, uv + offset).rgb;
+    sum += texture(source, uv + vec2(offset.x, -offset.y)).rgb;
+    sum += texture(source, uv - vec2(offset.x, -offset.y)).rgb;
+    color = vec4(sum / 8.0, 1.0);
+}
+#endif
+
+#ifdef BLOOM_UPSAMPLE
+void main() {
+    vec2 offset = texel_size * 0.5;
+    vec3 sum = texture(source, uv + vec2(-offset.x * 2.0, 0.0)).rgb;
+    sum += texture(source, uv + vec2(-offset.x, offset.y)).rgb * 2.0;
+    sum += texture(source, uv + vec2(0.0, offset.y * 2.0)).rgb;
+    sum += texture(source, uv + vec2(offset.x, offset.y)).rgb * 2.0;
+    sum += texture(source, uv + vec2(offset.x * 2.0, 0.0)).rgb;
+    sum += texture(source, uv + vec2(offset.x, -offset.y)).rgb * 2.0;
+    sum += texture(source, uv + vec2(0.0, -offset.y * 2.0)).rgb;
+    sum += texture(source, uv + vec2(-offset.x, -offset.y)).rgb * 2.0;
+    color = vec4(sum / 12.0, 1.0);
+}
+#endif
@@ -0,0 +1,19 @@
+#version 330 core
+
+out vec4 color;
+
+uniform sampler2D albedo_map;
+uniform vec3 tint;
+uniform vec3 light_color;
+// Lit fraction of the disc seen from the camera.
+uniform float phase;
+
+void main() {
+    vec2 offset = gl_PointCoord * 2.0 - 1.0;
+    if (dot(offset, offset) > 1.0) {
+        discard;
+    }
+    // The smallest mip level is the average color of the whole surface.
+    vec3 albedo = textureLod(albedo_map, vec2(0.5), 20.0).rgb * tint;
+    color = vec4(albedo * light_color * phase, 1.0);
+}
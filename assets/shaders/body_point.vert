@@ -0,0 +1,15 @@
+#version 330 core
+
+// A single point for bodies smaller than a pixel or two.
+
+in vec2 corner;
+
+uniform vec3 center;
+uniform mat4 view;
+uniform mat4 perspective;
+uniform float point_size;
+
+void main() {
+    gl_PointSize = point_size;
+    gl_Position = perspective * view * vec4(center, 1.0);
+}
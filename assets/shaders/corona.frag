@@ -0,0 +1,28 @@
+#version 330 core
+
+#include "include/noise.glsl"
+
+in vec2 offset;
+
+out vec4 color;
+
+uniform vec3 star_color;
+uniform float intensity;
+uniform float time;
+// Distance from the center to the quad edge in star radii.
+uniform float extent;
+
+const float FALLOFF = 3.0;
+const int STREAMERS = 12;
+
+void main() {
+    float r = length(offset);
+    float height = max(r - 1.0, 0.0);
+    float angle = atan(offset.y, offset.x);
+    // Faint streamers that slowly change shape.
+    float streamers = fbm(vec3(cos(angle), sin(angle), time * 0.03) * float(STREAMERS) * 0.5, 3);
+    float glow = exp(-height * FALLOFF) * (0.6 + 0.8 * streamers) + exp(-height * 12.0);
+    // Fade out before the quad edge.
+    glow *= 1.0 - smoothstep(0.6 * extent, extent, r);
+    color = vec4(star_color * intensity * glow, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+// Camera-facing quad around `center`, `size` units from the center to each edge.
+
+in vec2 corner;
+
+out vec2 offset;
+
+uniform vec3 center;
+uniform float size;
+uniform float radius;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    // Measured in star radii for the fragment shader.
+    offset = corner * size / radius;
+    vec4 view_center = view * vec4(center, 1.0);
+    gl_Position = perspective * (view_center + vec4(corner * size, 0.0, 0.0));
+}
@@ -0,0 +1,40 @@
+#version 330 core
+
+#include "include/lighting.glsl"
+
+// Reconstructs the visible hemisphere of a body on a billboard and shades it like planet.frag
+// without the detail maps, which are invisible at this size anyway.
+
+in vec2 offset;
+
+out vec4 color;
+
+uniform sampler2D albedo_map;
+uniform vec3 tint;
+uniform vec3 center;
+uniform float radius;
+uniform mat4 view;
+// Orientation of the body, to find texture coordinates matching the sphere mesh.
+uniform mat3 rotation;
+uniform vec3 light_position;
+uniform vec3 light_color;
+
+const float AMBIENT = 0.02;
+const float PI = 3.14159265;
+
+void main() {
+    float r2 = dot(offset, offset);
+    if (r2 > 1.0) {
+        discard;
+    }
+    vec3 view_normal = vec3(offset, sqrt(1.0 - r2));
+    vec3 normal = transpose(mat3(view)) * view_normal;
+    vec3 local = transpose(rotation) * normal;
+    vec2 uv = vec2(
+        fract(atan(-local.z, local.x) / (2.0 * PI)),
+        asin(clamp(local.y, -1.0, 1.0)) / PI + 0.5);
+
+    vec3 to_light = normalize(light_position - (center + normal * radius));
+    vec3 albedo = texture(albedo_map, uv).rgb * tint;
+    color = vec4(albedo * (lambert(normal, to_light) * light_color + AMBIENT), 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330 core
+
+// Camera-facing quad standing in for a small sphere.
+
+in vec2 corner;
+
+out vec2 offset;
+
+uniform vec3 center;
+uniform float radius;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    offset = corner;
+    vec4 view_center = view * vec4(center, 1.0);
+    gl_Position = perspective * (view_center + vec4(corner * radius, 0.0, 0.0));
+}
@@ -0,0 +1,14 @@
+// The scene is rendered in linear light into an HDR target and only the tone mapping pass encodes
+// to sRGB. Colors authored in sRGB are decoded with `srgb_to_linear` first.
+
+vec3 linear_to_srgb(vec3 linear) {
+    vec3 low = linear * 12.92;
+    vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, step(vec3(0.0031308), linear));
+}
+
+vec3 srgb_to_linear(vec3 srgb) {
+    vec3 low = srgb / 12.92;
+    vec3 high = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(low, high, step(vec3(0.04045), srgb));
+}
@@ -0,0 +1,7 @@
+// Depth helpers shared between shaders.
+
+// Moves a clip-space position onto the far plane (depth 1.0 after the perspective divide).
+// Geometry drawn this way ends up behind everything else, however far away the far plane is.
+vec4 at_far_plane(vec4 clip_position) {
+    return clip_position.xyww;
+}
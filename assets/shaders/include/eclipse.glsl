@@ -0,0 +1,49 @@
+// Shadows of spheres from a spherical light, matching src/eclipse.rs.
+
+const float ECLIPSE_PI = 3.14159265;
+
+// Fraction of a disc of angular radius `light` hidden by a disc of angular radius `occluder`
+// whose center is `separation` away.
+float disc_overlap_fraction(float light, float occluder, float separation) {
+    if (separation >= light + occluder) {
+        return 0.0;
+    }
+    if (separation <= abs(light - occluder)) {
+        return occluder >= light ? 1.0 : (occluder * occluder) / (light * light);
+    }
+    float a = light;
+    float b = occluder;
+    float d = separation;
+    float alpha = acos(clamp((d * d + a * a - b * b) / (2.0 * d * a), -1.0, 1.0));
+    float beta = acos(clamp((d * d + b * b - a * a) / (2.0 * d * b), -1.0, 1.0));
+    float kite = sqrt(max((-d + a + b) * (d + a - b) * (d - a + b) * (d + a + b), 0.0));
+    float area = a * a * alpha + b * b * beta - 0.5 * kite;
+    return clamp(area / (ECLIPSE_PI * a * a), 0.0, 1.0);
+}
+
+float angular_radius(float radius, float distance) {
+    return distance <= radius ? ECLIPSE_PI / 2.0 : asin(radius / distance);
+}
+
+// Fraction of the star at `light_center` hidden from `point` by the occluding sphere.
+float occluded_fraction(
+    vec3 point,
+    vec3 light_center,
+    float light_radius,
+    vec3 occluder_center,
+    float occluder_radius
+) {
+    vec3 to_light = light_center - point;
+    vec3 to_occluder = occluder_center - point;
+    float light_distance = length(to_light);
+    float occluder_distance = length(to_occluder);
+    if (occluder_distance >= light_distance || dot(to_occluder, to_light) <= 0.0) {
+        return 0.0;
+    }
+    float separation = acos(clamp(
+        dot(to_light, to_occluder) / (light_distance * occluder_distance), -1.0, 1.0));
+    return disc_overlap_fraction(
+        angular_radius(light_radius, light_distance),
+        angular_radius(occluder_radius, occluder_distance),
+        separation);
+}
@@ -0,0 +1,43 @@
+// Lighting terms shared by the body shaders. All vectors are normalized and in world space.
+
+float lambert(vec3 normal, vec3 to_light) {
+    return max(dot(normal, to_light), 0.0);
+}
+
+float blinn_phong(vec3 normal, vec3 to_light, vec3 to_camera, float shininess) {
+    vec3 halfway = normalize(to_light + to_camera);
+    return pow(max(dot(normal, halfway), 0.0), shininess) * step(0.0, dot(normal, to_light));
+}
+
+// 1 on the night side, 0 in daylight, with a soft transition across the terminator.
+float night_factor(vec3 normal, vec3 to_light) {
+    return smoothstep(0.1, -0.15, dot(normal, to_light));
+}
+
+// Transmittance of a ring in the plane through `ring_center` with normal `ring_normal` between
+// `ring_inner` and `ring_outer`, along the ray from `point` towards the light.
+float ring_transmittance(
+    vec3 point,
+    vec3 to_light,
+    vec3 ring_center,
+    vec3 ring_normal,
+    float ring_inner,
+    float ring_outer,
+    sampler2D ring_map,
+    float opacity
+) {
+    float denominator = dot(to_light, ring_normal);
+    if (abs(denominator) < 1e-4) {
+        return 1.0;
+    }
+    float t = dot(ring_center - point, ring_normal) / denominator;
+    if (t <= 0.0) {
+        return 1.0;
+    }
+    float distance_from_center = length(point + to_light * t - ring_center);
+    if (distance_from_center < ring_inner || distance_from_center > ring_outer) {
+        return 1.0;
+    }
+    float radial = (distance_from_center - ring_inner) / (ring_outer - ring_inner);
+    return 1.0 - texture(ring_map, vec2(radial, 0.5)).a * opacity;
+}
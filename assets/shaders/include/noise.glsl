@@ -0,0 +1,34 @@
+// Hash-based value noise, good enough for procedural surface detail.
+
+float hash(vec3 p) {
+    p = fract(p * 0.3183099 + 0.1);
+    p *= 17.0;
+    return fract(p.x * p.y * p.z * (p.x + p.y + p.z));
+}
+
+// Smoothly interpolated noise in [0, 1].
+float value_noise(vec3 p) {
+    vec3 cell = floor(p);
+    vec3 f = fract(p);
+    vec3 u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(mix(hash(cell + vec3(0, 0, 0)), hash(cell + vec3(1, 0, 0)), u.x),
+            mix(hash(cell + vec3(0, 1, 0)), hash(cell + vec3(1, 1, 0)), u.x), u.y),
+        mix(mix(hash(cell + vec3(0, 0, 1)), hash(cell + vec3(1, 0, 1)), u.x),
+            mix(hash(cell + vec3(0, 1, 1)), hash(cell + vec3(1, 1, 1)), u.x), u.y),
+        u.z);
+}
+
+// Fractal sum of `octaves` noise layers, in [0, 1].
+float fbm(vec3 p, int octaves) {
+    float sum = 0.0;
+    float amplitude = 0.5;
+    float total = 0.0;
+    for (int i = 0; i < octaves; i++) {
+        sum += value_noise(p) * amplitude;
+        total += amplitude;
+        p *= 2.03;
+        amplitude *= 0.5;
+    }
+    return sum / total;
+}
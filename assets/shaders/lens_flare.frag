@@ -0,0 +1,17 @@
+#version 330 core
+
+in vec2 offset;
+in vec3 element_color;
+
+out vec4 color;
+
+uniform vec3 star_color;
+uniform float intensity;
+
+void main() {
+    float r = length(offset);
+    // Ghosts are soft discs with a slightly brighter rim, like out-of-focus aperture images.
+    float disc = 1.0 - smoothstep(0.8, 1.0, r);
+    float rim = smoothstep(0.6, 0.9, r) * disc;
+    color = vec4(star_color * element_color * intensity * (disc * 0.6 + rim * 0.4), 1.0);
+}
@@ -0,0 +1,22 @@
+#version 330 core
+
+// Flare elements are placed along the line from the sun through the screen center.
+
+in vec2 corner;
+// 0 at the sun, 1 at the sun's position mirrored through the center.
+in float along;
+in float size;
+in vec3 tint;
+
+out vec2 offset;
+out vec3 element_color;
+
+uniform vec2 sun_position;
+uniform float aspect_ratio;
+
+void main() {
+    offset = corner;
+    element_color = tint;
+    vec2 center = mix(sun_position, -sun_position, along);
+    gl_Position = vec4(center + corner * size * vec2(1.0 / aspect_ratio, 1.0), 0.0, 1.0);
+}
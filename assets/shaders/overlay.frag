@@ -0,0 +1,11 @@
+#version 330 core
+
+#include "include/color.glsl"
+
+in vec4 line_color;
+
+out vec4 color;
+
+void main() {
+    color = vec4(srgb_to_linear(line_color.rgb), line_color.a);
+}
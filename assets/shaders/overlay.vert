@@ -0,0 +1,19 @@
+#version 330 core
+
+#include "include/depth.glsl"
+
+in vec3 position;
+in vec4 color;
+
+out vec4 line_color;
+
+uniform mat4 view;
+uniform mat4 perspective;
+// Geometry on the celestial sphere is pushed to the far plane like the skybox.
+uniform bool on_sky;
+
+void main() {
+    line_color = color;
+    vec4 clip_position = perspective * view * vec4(position, 1.0);
+    gl_Position = on_sky ? at_far_plane(clip_position) : clip_position;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+in vec2 offset;
+in vec3 tint;
+
+out vec4 color;
+
+void main() {
+    float falloff = exp(-4.0 * dot(offset, offset));
+    color = vec4(tint * falloff, 1.0);
+}
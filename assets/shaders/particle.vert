@@ -0,0 +1,26 @@
+#version 330 core
+
+// Camera-facing quad per particle instance.
+
+in vec2 corner;
+
+in vec3 particle_position;
+in vec3 particle_color;
+in float particle_size;
+
+out vec2 offset;
+out vec3 tint;
+
+uniform mat4 view;
+uniform mat4 perspective;
+// Half the smallest apparent size of a particle, in radians.
+uniform float min_size_angle;
+
+void main() {
+    vec4 view_center = view * vec4(particle_position, 1.0);
+    float size = max(particle_size, -view_center.z * min_size_angle);
+    // Particles inflated to stay visible keep their total brightness.
+    tint = particle_color * pow(particle_size / size, 2.0);
+    offset = corner;
+    gl_Position = perspective * (view_center + vec4(corner * size, 0.0, 0.0));
+}
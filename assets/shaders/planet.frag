@@ -0,0 +1,70 @@
+#version 330 core
+
+#include "include/eclipse.glsl"
+#include "include/lighting.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec3 world_tangent;
+in vec2 uv;
+
+out vec4 color;
+
+uniform sampler2D albedo_map;
+uniform sampler2D normal_map;
+uniform sampler2D specular_map;
+uniform sampler2D night_map;
+uniform sampler2D ring_map;
+
+uniform vec3 tint;
+uniform vec3 light_position;
+uniform vec3 light_color;
+uniform float light_radius;
+
+// Nearby bodies that may eclipse this one: center in xyz, radius in w.
+const int MAX_OCCLUDERS = 4;
+uniform vec4 occluders[MAX_OCCLUDERS];
+uniform int occluder_count;
+uniform vec3 camera_position;
+
+uniform bool has_rings;
+uniform vec3 center;
+uniform vec3 ring_normal;
+uniform float ring_inner;
+uniform float ring_outer;
+uniform float ring_opacity;
+
+const float AMBIENT = 0.02;
+const float SHININESS = 40.0;
+const float SPECULAR_STRENGTH = 0.6;
+
+void main() {
+    vec3 albedo = texture(albedo_map, uv).rgb * tint;
+
+    vec3 geometric_normal = normalize(world_normal);
+    vec3 tangent = normalize(world_tangent - geometric_normal * dot(world_tangent, geometric_normal));
+    vec3 bitangent = cross(geometric_normal, tangent);
+    vec3 surface_normal = texture(normal_map, uv).xyz * 2.0 - 1.0;
+    vec3 normal = normalize(mat3(tangent, bitangent, geometric_normal) * surface_normal);
+
+    vec3 to_light = normalize(light_position - world_position);
+    vec3 to_camera = normalize(camera_position - world_position);
+
+    // Self-shadowing by the sphere itself uses the geometric normal so bumps do not light up
+    // the night side.
+    float daylight = lambert(geometric_normal, to_light) > 0.0 ? 1.0 : 0.0;
+    if (has_rings) {
+        daylight *= ring_transmittance(world_position, to_light, center, ring_normal,
+            ring_inner, ring_outer, ring_map, ring_opacity);
+    }
+    for (int i = 0; i < occluder_count; i++) {
+        daylight *= 1.0 - occluded_fraction(world_position, light_position, light_radius,
+            occluders[i].xyz, occluders[i].w);
+    }
+    vec3 diffuse = albedo * (lambert(normal, to_light) * daylight * light_color + AMBIENT);
+    vec3 specular = light_color * blinn_phong(normal, to_light, to_camera, SHININESS)
+        * texture(specular_map, uv).r * SPECULAR_STRENGTH * daylight;
+    vec3 night = texture(night_map, uv).rgb * night_factor(geometric_normal, to_light);
+
+    color = vec4(diffuse + specular + night, 1.0);
+}
@@ -0,0 +1,27 @@
+#version 330 core
+
+in vec3 position;
+in vec3 normal;
+in vec3 tangent;
+in vec2 tex_coord;
+
+out vec3 world_position;
+out vec3 world_normal;
+out vec3 world_tangent;
+out vec2 uv;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    // Bodies are scaled uniformly, so the rotation part of the model matrix transforms normals.
+    mat3 rotation = mat3(model);
+    world_normal = normalize(rotation * normal);
+    world_tangent = normalize(rotation * tangent);
+    uv = tex_coord;
+
+    vec4 world = model * vec4(position, 1.0);
+    world_position = world.xyz;
+    gl_Position = perspective * view * world;
+}
@@ -0,0 +1,39 @@
+#version 330 core
+
+#include "include/eclipse.glsl"
+#include "include/lighting.glsl"
+
+in vec3 world_position;
+in float ring_coordinate;
+
+out vec4 color;
+
+uniform sampler2D ring_map;
+uniform vec3 tint;
+uniform float opacity;
+
+uniform vec3 center;
+uniform float radius;
+uniform vec3 ring_normal;
+uniform vec3 light_position;
+uniform vec3 light_color;
+uniform float light_radius;
+uniform vec3 camera_position;
+
+const float AMBIENT = 0.02;
+// Fraction of the light that passes through a fully transparent part to the unlit side.
+const float TRANSMISSION = 0.6;
+
+void main() {
+    vec4 ring = texture(ring_map, vec2(ring_coordinate, 0.5));
+    float alpha = ring.a * opacity;
+    vec3 to_light = normalize(light_position - world_position);
+    vec3 to_camera = normalize(camera_position - world_position);
+
+    float incidence = abs(dot(ring_normal, to_light));
+    bool lit_side = dot(ring_normal, to_light) * dot(ring_normal, to_camera) > 0.0;
+    float light = lit_side ? incidence : incidence * (1.0 - alpha) * TRANSMISSION;
+    light *= 1.0 - occluded_fraction(world_position, light_position, light_radius, center, radius);
+
+    color = vec4(ring.rgb * tint * (light * light_color + AMBIENT), alpha);
+}
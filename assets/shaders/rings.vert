@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec3 position;
+in float radial;
+
+out vec3 world_position;
+out float ring_coordinate;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    ring_coordinate = radial;
+    vec4 world = model * vec4(position, 1.0);
+    world_position = world.xyz;
+    gl_Position = perspective * view * world;
+}
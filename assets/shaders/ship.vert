@@ -0,0 +1,21 @@
+#version 330 core
+
+in vec3 position;
+in vec3 normal;
+
+out vec3 world_position;
+out vec3 world_normal;
+out float shade;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 perspective;
+
+void main() {
+    // The hull is scaled uniformly, so the rotation part of the model matrix transforms normals.
+    world_normal = normalize(mat3(model) * normal);
+    shade = 1.0;
+    vec4 world = model * vec4(position, 1.0);
+    world_position = world.xyz;
+    gl_Position = perspective * view * world;
+}
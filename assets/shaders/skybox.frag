@@ -1,5 +1,7 @@
 #version 330 core
 
+#include "include/color.glsl"
+
 uniform vec3 camera_position;
 uniform samplerCube skybox;
 
@@ -8,6 +10,5 @@ in vec3 tex_coord;
 out vec4 color;
 
 void main() {
-    // vec3 coord = vec3(tex_coord.x, -tex_coord.y, tex_coord.z);
-    color = texture(skybox, tex_coord);
+    color = vec4(srgb_to_linear(texture(skybox, tex_coord).rgb), 1.0);
 }
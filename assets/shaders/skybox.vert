@@ -1,15 +1,17 @@
 #version 330 core
 
+#include "include/depth.glsl"
+
 layout (location = 0) in vec3 position;
 
 out vec3 tex_coord;
 
+// Camera rotation only. The skybox is infinitely far away, so camera translation is ignored.
 uniform mat4 view;
 uniform mat4 perspective;
 
 
 void main() {
     tex_coord = position;
-    gl_Position = perspective * view * vec4(position, 1.0);
+    gl_Position = at_far_plane(perspective * view * vec4(position, 1.0));
 }
-
@@ -0,0 +1,37 @@
+#version 330 core
+
+#include "include/noise.glsl"
+
+// Surface of a star: granulation cells that slowly churn, darkened and reddened towards the limb.
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec3 world_tangent;
+in vec2 uv;
+
+out vec4 color;
+
+uniform vec3 star_color;
+uniform float intensity;
+uniform float time;
+uniform vec3 camera_position;
+
+const float GRANULATION_SCALE = 40.0;
+const float GRANULATION_CONTRAST = 0.35;
+// Linear limb darkening coefficients, strongest in blue like the real sun.
+const vec3 LIMB_DARKENING = vec3(0.5, 0.6, 0.75);
+
+void main() {
+    vec3 normal = normalize(world_normal);
+    vec3 to_camera = normalize(camera_position - world_position);
+    float mu = max(dot(normal, to_camera), 0.0);
+    vec3 limb = 1.0 - LIMB_DARKENING * (1.0 - mu);
+
+    vec3 p = normal * GRANULATION_SCALE;
+    // Warp the domain with a second noise layer so cells drift instead of sliding.
+    vec3 warp = vec3(fbm(p + time * 0.05, 2), fbm(p + 7.3 - time * 0.04, 2), fbm(p + 3.1, 2));
+    float cells = fbm(p + warp * 2.0 + vec3(0.0, time * 0.02, 0.0), 4);
+    float granulation = 1.0 + (cells - 0.5) * 2.0 * GRANULATION_CONTRAST;
+
+    color = vec4(star_color * intensity * limb * granulation, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec3 star_color;
+
+out vec4 color;
+
+void main() {
+    // Soft round sprite instead of the square point.
+    vec2 offset = gl_PointCoord * 2.0 - 1.0;
+    float falloff = exp(-4.0 * dot(offset, offset));
+    color = vec4(star_color * falloff, 1.0);
+}
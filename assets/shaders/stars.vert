@@ -0,0 +1,20 @@
+#version 330 core
+
+#include "include/depth.glsl"
+
+in vec3 direction;
+in vec3 color;
+in float size;
+
+out vec3 star_color;
+
+// Camera rotation only, like the skybox.
+uniform mat4 view;
+uniform mat4 perspective;
+uniform float size_scale;
+
+void main() {
+    star_color = color;
+    gl_PointSize = size * size_scale;
+    gl_Position = at_far_plane(perspective * view * vec4(direction, 1.0));
+}
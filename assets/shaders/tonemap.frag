@@ -0,0 +1,47 @@
+#version 330 core
+
+#include "include/color.glsl"
+
+in vec2 uv;
+
+out vec4 color;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+uniform float bloom_strength;
+uniform float exposure;
+// 0 = Reinhard, 1 = ACES, 2 = filmic (Hable).
+uniform int operator;
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+// Narkowicz's fit of the ACES reference rendering transform.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 hable_curve(vec3 x) {
+    const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 filmic(vec3 x) {
+    const float WHITE = 11.2;
+    return hable_curve(x * 2.0) / hable_curve(vec3(WHITE));
+}
+
+void main() {
+    vec3 hdr = texture(scene, uv).rgb + texture(bloom, uv).rgb * bloom_strength;
+    hdr *= exposure;
+    vec3 mapped;
+    if (operator == 0) {
+        mapped = reinhard(hdr);
+    } else if (operator == 1) {
+        mapped = aces(hdr);
+    } else {
+        mapped = filmic(hdr);
+    }
+    color = vec4(linear_to_srgb(mapped), 1.0);
+}
@@ -0,0 +1,85 @@
+//! Times direct summation against the Barnes-Hut tree for growing numbers of bodies.
+//!
+//! Run with `cargo bench`. Direct summation is skipped where it would take minutes.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use glam::DVec3;
+use solar_system::{NBody, direct_accelerations, tree_accelerations};
+
+const COUNTS: [usize; 4] = [1000, 5000, 20_000, 50_000];
+const THETAS: [f64; 3] = [0.3, 0.5, 1.0];
+/// Largest body count direct summation is timed for.
+const MAX_DIRECT: usize = 20_000;
+const REPETITIONS: u32 = 5;
+
+/// Bodies of equal mass spread uniformly through a sphere of 10 AU.
+fn cloud(count: usize) -> Vec<NBody> {
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = || {
+        // xorshift64*, enough to scatter points.
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    };
+    let mut bodies = Vec::with_capacity(count);
+    while bodies.len() < count {
+        let position = DVec3::new(next(), next(), next()) * 2.0 - 1.0;
+        if position.length_squared() > 1.0 {
+            continue;
+        }
+        bodies.push(NBody {
+            position: position * 10.0,
+            velocity: DVec3::ZERO,
+            gm: 1e-8,
+            radius: 1e-3,
+        });
+    }
+    bodies
+}
+
+/// Fastest of a few runs of `run`.
+fn time(mut run: impl FnMut() -> Vec<DVec3>) -> Duration {
+    (0..REPETITIONS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(run());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>12} {}",
+        "bodies",
+        "direct",
+        THETAS
+            .map(|theta| format!("{:>12}", format!("θ = {theta}")))
+            .join("")
+    );
+    for count in COUNTS {
+        let bodies = cloud(count);
+        let direct = if count <= MAX_DIRECT {
+            format!(
+                "{:>9.2} ms",
+                time(|| direct_accelerations(&bodies)).as_secs_f64() * 1000.0
+            )
+        } else {
+            format!("{:>12}", "-")
+        };
+        let tree: String = THETAS
+            .iter()
+            .map(|&theta| {
+                let elapsed = time(|| tree_accelerations(&bodies, theta));
+                format!("{:>9.2} ms", elapsed.as_secs_f64() * 1000.0)
+            })
+            .collect();
+        println!("{count:>8} {direct} {tree}");
+    }
+}
@@ -0,0 +1,431 @@
+//! Background asset decoding.
+//!
+//! Decoding large PNGs or glTF files takes long enough to freeze the window, so the
+//! [`AssetLoader`] hands that work to a small pool of worker threads. GPU objects can only be
+//! created on the main thread, so the loader never touches glium: each frame the app calls
+//! [`AssetLoader::update`] to collect finished assets, and the owners of pending [`AssetId`]s
+//! [`take`](AssetLoader::take) their data and upload it themselves. Owners that stop waiting for
+//! an id [`discard`](AssetLoader::discard) it so its result does not stay in the loader.
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, mpsc},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{Context, Result, anyhow};
+use image::RgbaImage;
+use log::{debug, info};
+
+/// Handle to an asset requested from the [`AssetLoader`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId(usize);
+
+/// CPU-side mesh extracted from a glTF file, ready to be put into vertex/index buffers.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+/// A decoded asset.
+pub enum AssetData {
+    Image(RgbaImage),
+    Mesh(MeshData),
+}
+
+type Decoder = fn(&Path) -> Result<AssetData>;
+
+struct Job {
+    id: AssetId,
+    path: PathBuf,
+    decode: Decoder,
+}
+
+pub struct AssetLoader {
+    jobs: Option<mpsc::Sender<Job>>,
+    results: mpsc::Receiver<(AssetId, Result<AssetData>)>,
+    workers: Vec<JoinHandle<()>>,
+    finished: HashMap<AssetId, Result<AssetData>>,
+    /// Requested assets the workers have not returned yet.
+    in_flight: HashSet<AssetId>,
+    /// In-flight assets nobody waits for any more, dropped as soon as they arrive.
+    discarded: HashSet<AssetId>,
+    next_id: usize,
+    /// Assets requested since the loader was last idle, used for the progress bar.
+    requested: usize,
+    completed: usize,
+}
+
+impl AssetLoader {
+    pub fn new() -> Self {
+        let worker_count = thread::available_parallelism().map_or(2, |n| n.get());
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..worker_count)
+            .map(|i| {
+                let jobs = Arc::clone(&job_receiver);
+                let results = result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("asset-loader-{i}"))
+                    .spawn(move || worker_loop(&jobs, &results))
+                    .expect("failed to spawn asset loader thread")
+            })
+            .collect();
+        info!("Started asset loader with {worker_count} worker threads");
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            workers,
+            finished: HashMap::new(),
+            in_flight: HashSet::new(),
+            discarded: HashSet::new(),
+            next_id: 0,
+            requested: 0,
+            completed: 0,
+        }
+    }
+
+    /// Queues an image for decoding into RGBA8.
+    pub fn load_image(&mut self, path: impl Into<PathBuf>) -> AssetId {
+        self.load(path.into(), decode_image)
+    }
+
+    /// Queues a glTF file whose meshes are merged into a single [`MeshData`].
+    pub fn load_model(&mut self, path: impl Into<PathBuf>) -> AssetId {
+        self.load(path.into(), decode_gltf)
+    }
+
+    fn load(&mut self, path: PathBuf, decode: Decoder) -> AssetId {
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+        self.requested += 1;
+        self.in_flight.insert(id);
+        debug!("Queued asset {}", path.display());
+        self.jobs
+            .as_ref()
+            .expect("asset loader is shut down")
+            .send(Job { id, path, decode })
+            .expect("asset loader threads exited");
+        id
+    }
+
+    /// Collects assets the workers have finished since the last call. Call once per frame.
+    pub fn update(&mut self) {
+        for (id, result) in self.results.try_iter() {
+            self.completed += 1;
+            self.in_flight.remove(&id);
+            if !self.discarded.remove(&id) {
+                self.finished.insert(id, result);
+            }
+        }
+        if self.completed == self.requested {
+            self.requested = 0;
+            self.completed = 0;
+        }
+    }
+
+    /// Takes the decoded asset if it has finished loading, leaving `None` while it is pending.
+    pub fn take(&mut self, id: AssetId) -> Option<Result<AssetData>> {
+        self.finished.remove(&id)
+    }
+
+    /// Drops the asset whether it has finished or not. Used by owners that no longer wait for
+    /// `id`, e.g. because they requested the same file again.
+    pub fn discard(&mut self, id: AssetId) {
+        if self.finished.remove(&id).is_none() && self.in_flight.contains(&id) {
+            self.discarded.insert(id);
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.completed < self.requested
+    }
+
+    /// Fraction of the current batch of requests that has been decoded.
+    pub fn progress(&self) -> f32 {
+        if self.requested == 0 {
+            1.0
+        } else {
+            self.completed as f32 / self.requested as f32
+        }
+    }
+
+    /// Returns `(completed, requested)` for the current batch.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.completed, self.requested)
+    }
+}
+
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        // Closing the job channel lets every worker fall out of its loop.
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_loop(
+    jobs: &Mutex<mpsc::Receiver<Job>>,
+    results: &mpsc::Sender<(AssetId, Result<AssetData>)>,
+) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else {
+            return;
+        };
+        let time_start = std::time::Instant::now();
+        // A panicking decoder must still report back, or the asset would stay loading forever.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| (job.decode)(&job.path)))
+            .unwrap_or_else(|payload| {
+                Err(anyhow!("decoder panicked: {}", panic_message(&*payload)))
+            })
+            .with_context(|| format!("failed to load {}", job.path.display()));
+        debug!(
+            "Decoded {} in {:.2?}",
+            job.path.display(),
+            time_start.elapsed()
+        );
+        if results.send((job.id, result)).is_err() {
+            return;
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown cause"
+    }
+}
+
+pub fn decode_image(path: &Path) -> Result<AssetData> {
+    Ok(AssetData::Image(image::open(path)?.to_rgba8()))
+}
+
+/// Reads every mesh primitive of a glTF file into one [`MeshData`]. Node transforms are not
+/// applied, so models are expected to be exported with their transforms baked in.
+pub fn decode_gltf(path: &Path) -> Result<AssetData> {
+    let (document, buffers, _images) = gltf::import(path)?;
+    let mut mesh = MeshData::default();
+
+    for primitive in document.meshes().flat_map(|m| m.primitives()) {
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            continue;
+        };
+        let base = mesh.positions.len() as u32;
+        let count_before = mesh.positions.len();
+        mesh.positions.extend(positions);
+        let added = mesh.positions.len() - count_before;
+
+        match reader.read_normals() {
+            Some(normals) => mesh.normals.extend(normals),
+            None => mesh
+                .normals
+                .extend(std::iter::repeat_n([0.0, 0.0, 0.0], added)),
+        }
+        match reader.read_tex_coords(0) {
+            Some(tex_coords) => mesh.tex_coords.extend(tex_coords.into_f32()),
+            None => mesh
+                .tex_coords
+                .extend(std::iter::repeat_n([0.0, 0.0], added)),
+        }
+        match reader.read_indices() {
+            Some(indices) => mesh
+                .indices
+                .extend(indices.into_u32().map(|index| base + index)),
+            None => mesh.indices.extend(base..base + added as u32),
+        }
+    }
+
+    anyhow::ensure!(!mesh.positions.is_empty(), "no mesh data in glTF file");
+    Ok(AssetData::Mesh(mesh))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::*;
+
+    /// A fresh directory under the system temp dir for one test, removed again on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("solar-system-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes a glTF file with one indexed triangle without normals or texture coordinates.
+    fn write_triangle(dir: &Path) -> PathBuf {
+        let mut buffer = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            buffer.extend(value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2] {
+            buffer.extend(index.to_le_bytes());
+        }
+        buffer.extend([0, 0]);
+        fs::write(dir.join("triangle.bin"), &buffer).unwrap();
+
+        let gltf = r#"{
+            "asset": { "version": "2.0" },
+            "buffers": [{ "uri": "triangle.bin", "byteLength": 44 }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+            ],
+            "accessors": [
+                {
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }]
+        }"#;
+        let path = dir.join("triangle.gltf");
+        fs::write(&path, gltf).unwrap();
+        path
+    }
+
+    fn write_image(dir: &Path) -> PathBuf {
+        let path = dir.join("pixel.png");
+        RgbaImage::from_fn(3, 2, |x, y| image::Rgba([x as u8, y as u8, 7, 255]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    /// Calls [`AssetLoader::update`] until the current batch is done.
+    fn wait(loader: &mut AssetLoader) {
+        for _ in 0..500 {
+            loader.update();
+            if !loader.is_loading() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("assets did not finish loading");
+    }
+
+    #[test]
+    fn decodes_png() {
+        let dir = TempDir::new("png");
+        let path = write_image(&dir.0);
+        let Ok(AssetData::Image(image)) = decode_image(&path) else {
+            panic!("expected an image");
+        };
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(2, 1).0, [2, 1, 7, 255]);
+    }
+
+    #[test]
+    fn decodes_gltf_with_defaults_for_missing_attributes() {
+        let dir = TempDir::new("gltf");
+        let path = write_triangle(&dir.0);
+        let Ok(AssetData::Mesh(mesh)) = decode_gltf(&path) else {
+            panic!("expected a mesh");
+        };
+        assert_eq!(mesh.positions[1], [1.0, 0.0, 0.0]);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.normals, [[0.0; 3]; 3]);
+        assert_eq!(mesh.tex_coords, [[0.0; 2]; 3]);
+    }
+
+    #[test]
+    fn loader_reports_progress_and_hands_out_assets() {
+        let dir = TempDir::new("loader");
+        let mut loader = AssetLoader::new();
+        assert_eq!(loader.progress(), 1.0);
+
+        let image = loader.load_image(write_image(&dir.0));
+        let model = loader.load_model(write_triangle(&dir.0));
+        assert!(loader.is_loading());
+        assert_eq!(loader.counts().1, 2);
+        assert!(loader.progress() < 1.0);
+
+        wait(&mut loader);
+        assert_eq!(loader.progress(), 1.0);
+        assert_eq!(loader.counts(), (0, 0));
+        assert!(matches!(loader.take(image), Some(Ok(AssetData::Image(_)))));
+        assert!(matches!(loader.take(model), Some(Ok(AssetData::Mesh(_)))));
+        assert!(loader.take(image).is_none());
+    }
+
+    #[test]
+    fn loader_passes_errors_to_the_caller() {
+        let dir = TempDir::new("missing");
+        let mut loader = AssetLoader::new();
+        let missing = loader.load_image(dir.0.join("missing.png"));
+        wait(&mut loader);
+        let Some(Err(error)) = loader.take(missing) else {
+            panic!("expected an error");
+        };
+        assert!(format!("{error:#}").contains("missing.png"));
+    }
+
+    #[test]
+    fn loader_reports_decoder_panics_as_errors() {
+        let mut loader = AssetLoader::new();
+        let id = loader.load("broken.png".into(), |_| panic!("corrupt header"));
+        wait(&mut loader);
+        let Some(Err(error)) = loader.take(id) else {
+            panic!("expected an error");
+        };
+        let message = format!("{error:#}");
+        assert!(message.contains("broken.png") && message.contains("corrupt header"));
+    }
+
+    #[test]
+    fn loader_drops_discarded_assets() {
+        let dir = TempDir::new("discard");
+        let mut loader = AssetLoader::new();
+        let finished = loader.load_image(write_image(&dir.0));
+        wait(&mut loader);
+        loader.discard(finished);
+        assert!(loader.finished.is_empty());
+
+        // Discarded before the loader has collected it.
+        let in_flight = loader.load_image(write_image(&dir.0));
+        loader.discard(in_flight);
+        wait(&mut loader);
+        assert!(loader.take(in_flight).is_none());
+        assert!(loader.finished.is_empty() && loader.discarded.is_empty());
+
+        // Discarding an asset that was already taken does not remember it.
+        let taken = loader.load_image(write_image(&dir.0));
+        wait(&mut loader);
+        assert!(loader.take(taken).is_some());
+        loader.discard(taken);
+        assert!(loader.discarded.is_empty() && loader.in_flight.is_empty());
+    }
+}
@@ -0,0 +1,614 @@
+//! Asteroid belts.
+//!
+//! Each belt is generated from ranges of orbital elements, so every asteroid follows its own
+//! Kepler orbit around the sun. Propagating hundreds of thousands of orbits is too expensive to
+//! wait for every frame, so each belt propagates on a background thread, split across all cores,
+//! while the frame keeps drawing the last finished positions. Nothing is propagated while the
+//! clock stands still. The positions are streamed into a per-instance vertex buffer and every
+//! rock shape is drawn with a single instanced call per belt.
+//!
+//! The rock shapes are generated procedurally. Any of them can be replaced by a glTF model at
+//! `assets/models/rock_<n>.glb`.
+
+use std::{
+    f64::consts::TAU,
+    ops::{Range, RangeInclusive},
+    path::Path,
+    sync::{Arc, mpsc},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{Result, anyhow};
+use glam::{Mat4, Vec3};
+use glium::{
+    Display, DrawParameters, IndexBuffer, Program, Surface, VertexBuffer,
+    glutin::surface::WindowSurface, index::PrimitiveType,
+};
+use log::{info, warn};
+
+use crate::{
+    assets::{AssetData, AssetId, AssetLoader, MeshData},
+    celestial::ecliptic_to_scene,
+    frustum::{CullStats, Frustum},
+    kepler::{GM_SUN, Orbit, OrbitalElements},
+    parallel::parallel_chunks_mut,
+    random::Rng,
+    shaders::ProgramDesc,
+    system::AU,
+};
+
+pub const ASTEROID_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "asteroid.vert",
+    fragment: "asteroid.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+/// Number of distinct rock shapes.
+const ROCK_VARIANTS: usize = 3;
+const MODEL_DIR: &str = "assets/models/";
+/// Orbits propagated per thread at minimum; smaller batches are not worth a thread.
+const MIN_ORBITS_PER_THREAD: usize = 4096;
+/// Smallest apparent diameter of an asteroid in pixels, so distant ones stay visible.
+const MIN_SIZE_PX: f32 = 1.5;
+
+/// How to generate one belt.
+#[derive(Clone, Debug)]
+pub struct BeltDefinition {
+    pub name: &'static str,
+    pub count: usize,
+    /// In AU.
+    pub semi_major_axis: RangeInclusive<f64>,
+    pub eccentricity: RangeInclusive<f64>,
+    pub inclination_deg: RangeInclusive<f64>,
+    /// Rock radius in scene units. Sizes follow a power law, so most rocks are close to the
+    /// lower end.
+    pub radius: RangeInclusive<f32>,
+    /// Linear albedo.
+    pub color: [f32; 3],
+    pub seed: u64,
+}
+
+/// The main belt between Mars and Jupiter.
+pub fn main_belt() -> BeltDefinition {
+    BeltDefinition {
+        name: "Main belt",
+        count: 20_000,
+        semi_major_axis: 2.1..=3.3,
+        eccentricity: 0.0..=0.2,
+        inclination_deg: 0.0..=20.0,
+        radius: 0.02..=0.3,
+        color: [0.16, 0.14, 0.12],
+        seed: 1,
+    }
+}
+
+/// The Kuiper belt beyond Neptune, between the 3:2 and 2:1 resonances.
+pub fn kuiper_belt() -> BeltDefinition {
+    BeltDefinition {
+        name: "Kuiper belt",
+        count: 30_000,
+        semi_major_axis: 39.5..=48.0,
+        eccentricity: 0.0..=0.2,
+        inclination_deg: 0.0..=30.0,
+        radius: 0.1..=0.8,
+        color: [0.22, 0.17, 0.15],
+        seed: 2,
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct RockVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+}
+
+implement_vertex!(RockVertex, position, normal);
+
+/// Per-asteroid attributes that never change.
+#[derive(Copy, Clone)]
+pub struct AsteroidInstance {
+    instance_radius: f32,
+    spin_axis: [f32; 3],
+    /// Angular velocity in radians per second and the phase at time 0.
+    spin: [f32; 2],
+    /// Multiplies the belt color so rocks are not all equally dark.
+    brightness: f32,
+}
+
+implement_vertex!(
+    AsteroidInstance,
+    instance_radius,
+    spin_axis,
+    spin,
+    brightness
+);
+
+/// Per-asteroid position, rewritten whenever the belt is propagated.
+#[derive(Copy, Clone, Default)]
+pub struct AsteroidPosition {
+    instance_position: [f32; 3],
+}
+
+implement_vertex!(AsteroidPosition, instance_position);
+
+pub struct RockMesh {
+    pub vertex_buffer: VertexBuffer<RockVertex>,
+    pub index_buffer: IndexBuffer<u32>,
+}
+
+impl RockMesh {
+    pub fn new(display: &Display<WindowSurface>, mesh: &MeshData) -> Result<Self> {
+        let vertices: Vec<_> = mesh
+            .positions
+            .iter()
+            .zip(&mesh.normals)
+            .map(|(&position, &normal)| RockVertex { position, normal })
+            .collect();
+        Ok(Self {
+            vertex_buffer: VertexBuffer::new(display, &vertices)?,
+            index_buffer: IndexBuffer::new(display, PrimitiveType::TrianglesList, &mesh.indices)?,
+        })
+    }
+}
+
+pub struct Belt {
+    pub definition: BeltDefinition,
+    orbits: Arc<[Orbit]>,
+    propagator: Propagator,
+    instances: VertexBuffer<AsteroidInstance>,
+    position_buffer: VertexBuffer<AsteroidPosition>,
+    /// Radius around the sun that contains every orbit, in scene units.
+    extent: f32,
+    /// Time of the positions currently in `position_buffer`.
+    propagated_days: Option<f64>,
+}
+
+impl Belt {
+    pub fn new(display: &Display<WindowSurface>, definition: BeltDefinition) -> Result<Self> {
+        let mut rng = Rng::new(definition.seed);
+        let orbits: Arc<[Orbit]> = (0..definition.count)
+            .map(|_| Orbit::new(&random_elements(&mut rng, &definition), GM_SUN))
+            .collect();
+        let instances: Vec<_> = (0..definition.count)
+            .map(|_| random_instance(&mut rng, &definition))
+            .collect();
+        let extent = orbits.iter().map(Orbit::aphelion).fold(0.0, f64::max) as f32 * AU;
+        let propagator = Propagator::new(Arc::clone(&orbits));
+        info!(
+            "Generated {} asteroids for the {}",
+            orbits.len(),
+            definition.name
+        );
+        Ok(Self {
+            instances: VertexBuffer::new(display, &instances)?,
+            position_buffer: VertexBuffer::empty_dynamic(display, orbits.len())?,
+            definition,
+            orbits,
+            propagator,
+            extent,
+            propagated_days: None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.orbits.len()
+    }
+
+    /// Moves the asteroids towards their positions `days` after the epoch. Only the first call
+    /// waits for the result; later ones upload whatever the background thread has finished and
+    /// hand it the new time, so the drawn positions trail the clock by a frame or so.
+    fn propagate(&mut self, days: f64) {
+        if self.propagated_days.is_none() {
+            let positions = self
+                .propagator
+                .spare
+                .as_mut()
+                .expect("nothing is in flight yet");
+            propagate(&self.orbits, positions, days);
+            self.position_buffer.write(positions);
+            self.propagated_days = Some(days);
+            return;
+        }
+        if let Some((propagated, positions)) = self.propagator.finished() {
+            self.position_buffer.write(positions);
+            self.propagated_days = Some(propagated);
+        }
+        if self.propagated_days != Some(days) {
+            self.propagator.request(days);
+        }
+    }
+
+    /// Instances drawn with rock shape `variant`. The shapes are handed out in contiguous blocks
+    /// so each one is a single slice of the instance buffers.
+    fn variant_range(&self, variant: usize) -> Range<usize> {
+        let count = self.len();
+        variant * count / ROCK_VARIANTS..(variant + 1) * count / ROCK_VARIANTS
+    }
+}
+
+fn random_elements(rng: &mut Rng, definition: &BeltDefinition) -> OrbitalElements {
+    let range =
+        |rng: &mut Rng, range: &RangeInclusive<f64>| rng.range(*range.start(), *range.end());
+    OrbitalElements {
+        semi_major_axis: range(rng, &definition.semi_major_axis),
+        eccentricity: range(rng, &definition.eccentricity),
+        inclination: range(rng, &definition.inclination_deg).to_radians(),
+        longitude_of_ascending_node: rng.range(0.0, TAU),
+        argument_of_periapsis: rng.range(0.0, TAU),
+        mean_anomaly: rng.range(0.0, TAU),
+    }
+}
+
+fn random_instance(rng: &mut Rng, definition: &BeltDefinition) -> AsteroidInstance {
+    // Inverse transform sampling of a power law with exponent -2.5, cut off at the upper end.
+    let (min, max) = (*definition.radius.start(), *definition.radius.end());
+    let radius = (min * (1.0 - rng.next_f64() as f32).powf(-1.0 / 1.5)).min(max);
+    AsteroidInstance {
+        instance_radius: radius,
+        spin_axis: random_direction(rng).as_vec3().to_array(),
+        spin: [rng.range(-1.5, 1.5) as f32, rng.range(0.0, TAU) as f32],
+        brightness: rng.range(0.6, 1.4) as f32,
+    }
+}
+
+fn random_direction(rng: &mut Rng) -> glam::DVec3 {
+    let z = rng.range(-1.0, 1.0);
+    let angle = rng.range(0.0, TAU);
+    let r = (1.0 - z * z).sqrt();
+    glam::DVec3::new(r * angle.cos(), r * angle.sin(), z)
+}
+
+/// Writes the scene position of every orbit at `days` into `positions`, in parallel batches.
+pub fn propagate(orbits: &[Orbit], positions: &mut [AsteroidPosition], days: f64) {
+    parallel_chunks_mut(positions, MIN_ORBITS_PER_THREAD, |start, positions| {
+        for (orbit, position) in orbits[start..].iter().zip(positions) {
+            let scene = ecliptic_to_scene(orbit.position(days)) * AU;
+            position.instance_position = scene.to_array();
+        }
+    });
+}
+
+/// Propagates a belt on a background thread. The positions travel back and forth between the
+/// frame and the thread, so only one propagation is in flight at a time.
+struct Propagator {
+    requests: Option<mpsc::Sender<(f64, Vec<AsteroidPosition>)>>,
+    results: mpsc::Receiver<(f64, Vec<AsteroidPosition>)>,
+    /// The positions while no propagation is in flight, holding the last finished result.
+    spare: Option<Vec<AsteroidPosition>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Propagator {
+    fn new(orbits: Arc<[Orbit]>) -> Self {
+        let (requests, jobs) = mpsc::channel::<(f64, Vec<AsteroidPosition>)>();
+        let (finished, results) = mpsc::channel();
+        let spare = vec![AsteroidPosition::default(); orbits.len()];
+        let worker = thread::Builder::new()
+            .name("asteroid-propagator".to_string())
+            .spawn(move || {
+                for (days, mut positions) in jobs {
+                    propagate(&orbits, &mut positions, days);
+                    if finished.send((days, positions)).is_err() {
+                        return;
+                    }
+                }
+            })
+            .expect("failed to spawn asteroid propagator thread");
+        Self {
+            requests: Some(requests),
+            results,
+            spare: Some(spare),
+            worker: Some(worker),
+        }
+    }
+
+    /// Starts propagating to `days` unless a propagation is already in flight.
+    fn request(&mut self, days: f64) {
+        let Some(positions) = self.spare.take() else {
+            return;
+        };
+        self.requests
+            .as_ref()
+            .expect("propagator is shut down")
+            .send((days, positions))
+            .expect("asteroid propagator thread exited");
+    }
+
+    /// Returns the time and positions of a propagation that finished since the last call.
+    fn finished(&mut self) -> Option<(f64, &[AsteroidPosition])> {
+        let (days, positions) = self.results.try_recv().ok()?;
+        Some((days, self.spare.insert(positions)))
+    }
+}
+
+impl Drop for Propagator {
+    fn drop(&mut self) {
+        // Closing the request channel lets the worker fall out of its loop.
+        self.requests = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+pub struct AsteroidBelts {
+    /// Indexed by variant.
+    rocks: Vec<RockMesh>,
+    pending_models: [Option<AssetId>; ROCK_VARIANTS],
+    pub belts: Vec<Belt>,
+}
+
+impl AsteroidBelts {
+    /// Generates the belts and rock shapes, and queues any rock models found on disk. The program
+    /// is expected to be registered in the shader library as `"asteroid"` from
+    /// [`ASTEROID_PROGRAM`].
+    pub fn init(
+        display: &Display<WindowSurface>,
+        assets: &mut AssetLoader,
+        definitions: Vec<BeltDefinition>,
+    ) -> Result<Self> {
+        let rocks = (0..ROCK_VARIANTS)
+            .map(|variant| RockMesh::new(display, &rock_mesh(variant as u64)))
+            .collect::<Result<_>>()?;
+        let pending_models = std::array::from_fn(|variant| {
+            let path = format!("{MODEL_DIR}rock_{variant}.glb");
+            Path::new(&path).exists().then(|| assets.load_model(path))
+        });
+        let belts = definitions
+            .into_iter()
+            .map(|definition| Belt::new(display, definition))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            rocks,
+            pending_models,
+            belts,
+        })
+    }
+
+    /// Swaps in rock models that finished loading and propagates every belt to `days`.
+    pub fn update(
+        &mut self,
+        display: &Display<WindowSurface>,
+        assets: &mut AssetLoader,
+        days: f64,
+    ) {
+        for (variant, pending) in self.pending_models.iter_mut().enumerate() {
+            let Some(id) = *pending else {
+                continue;
+            };
+            let Some(result) = assets.take(id) else {
+                continue;
+            };
+            *pending = None;
+            match result {
+                Ok(AssetData::Mesh(mesh)) => match RockMesh::new(display, &mesh) {
+                    Ok(rock) => self.rocks[variant] = rock,
+                    Err(e) => warn!("Failed to upload rock model {variant}: {e:#}"),
+                },
+                Ok(_) => warn!("Rock model {variant} is not a mesh"),
+                Err(e) => warn!("{e:#}"),
+            }
+        }
+        for belt in &mut self.belts {
+            belt.propagate(days);
+        }
+    }
+
+    /// Regenerates the belts whose requested count differs from what they hold.
+    pub fn regenerate(&mut self, display: &Display<WindowSurface>) -> Result<()> {
+        for belt in &mut self.belts {
+            if belt.definition.count != belt.len() {
+                *belt = Belt::new(display, belt.definition.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws every belt, one instanced call per rock shape. `pixel_angle` is the angle one pixel
+    /// covers at the center of the screen, used to keep distant rocks at least a pixel wide.
+    #[expect(clippy::too_many_arguments, reason = "per-frame render state")]
+    pub fn draw(
+        &self,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+        light_position: Vec3,
+        light_color: [f32; 3],
+        pixel_angle: f32,
+        time: f32,
+        frustum: &Frustum,
+    ) -> Result<CullStats> {
+        let params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut stats = CullStats::default();
+        for belt in &self.belts {
+            if !stats.record(frustum.intersects_sphere(Vec3::ZERO, belt.extent)) {
+                continue;
+            }
+            let uniforms = uniform! {
+                view: view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                camera_position: camera_position.to_array(),
+                min_radius_angle: 0.5 * MIN_SIZE_PX * pixel_angle,
+                time: time,
+                tint: belt.definition.color,
+                light_position: light_position.to_array(),
+                light_color: light_color,
+            };
+            for (variant, rock) in self.rocks.iter().enumerate() {
+                let range = belt.variant_range(variant);
+                if range.is_empty() {
+                    continue;
+                }
+                let (Some(instances), Some(positions)) = (
+                    belt.instances.slice(range.clone()),
+                    belt.position_buffer.slice(range),
+                ) else {
+                    continue;
+                };
+                frame.draw(
+                    (
+                        &rock.vertex_buffer,
+                        instances.per_instance().map_err(|_| instancing_error())?,
+                        positions.per_instance().map_err(|_| instancing_error())?,
+                    ),
+                    &rock.index_buffer,
+                    program,
+                    &uniforms,
+                    &params,
+                )?;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn instancing_error() -> anyhow::Error {
+    anyhow!("instanced drawing is not supported by this OpenGL context")
+}
+
+/// A lumpy, elongated unit rock: a twice subdivided icosahedron, stretched along random axes and
+/// pushed out by a few smooth bumps. `seed` picks the shape.
+pub fn rock_mesh(seed: u64) -> MeshData {
+    let mut rng = Rng::new(seed.wrapping_mul(0x2545_f491_4f6c_dd1d) ^ 0x5eed);
+    let (positions, indices) = icosphere(2);
+    let stretch = Vec3::new(
+        1.0,
+        rng.range(0.6, 0.9) as f32,
+        rng.range(0.45, 0.75) as f32,
+    );
+    let bumps: Vec<_> = (0..8)
+        .map(|_| {
+            let center = random_direction(&mut rng).as_vec3();
+            let height = rng.range(-0.15, 0.2) as f32;
+            let width = rng.range(0.05, 0.3) as f32;
+            (center, height, width)
+        })
+        .collect();
+    let positions: Vec<_> = positions
+        .into_iter()
+        .map(|direction| {
+            let lumps: f32 = bumps
+                .iter()
+                .map(|&(center, height, width)| {
+                    height * (-(1.0 - direction.dot(center)) / width).exp()
+                })
+                .sum();
+            direction * stretch * (1.0 + lumps)
+        })
+        .collect();
+
+    // Smooth normals from the area-weighted face normals around each vertex.
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let face = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        for vertex in [a, b, c] {
+            normals[vertex] += face;
+        }
+    }
+
+    MeshData {
+        tex_coords: vec![[0.0; 2]; positions.len()],
+        positions: positions.iter().map(|p| p.to_array()).collect(),
+        normals: normals
+            .iter()
+            .map(|n| n.normalize_or_zero().to_array())
+            .collect(),
+        indices,
+    }
+}
+
+/// Unit icosahedron with every triangle split into four `subdivisions` times, counter-clockwise
+/// seen from outside.
+fn icosphere(subdivisions: u32) -> (Vec<Vec3>, Vec<u32>) {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut positions: Vec<_> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|p| Vec3::from_array(p).normalize())
+    .collect();
+    let mut indices: Vec<u32> = vec![
+        0, 11, 5, 0, 5, 1, 0, 1, 7, 0, 7, 10, 0, 10, 11, 1, 5, 9, 5, 11, 4, 11, 10, 2, 10, 7, 6, 7,
+        1, 8, 3, 9, 4, 3, 4, 2, 3, 2, 6, 3, 6, 8, 3, 8, 9, 4, 9, 5, 2, 4, 11, 6, 2, 10, 8, 6, 7, 9,
+        8, 1,
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = std::collections::HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let p = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(p);
+                positions.len() as u32 - 1
+            })
+        };
+        indices = indices
+            .chunks_exact(3)
+            .flat_map(|triangle| {
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]
+            })
+            .collect();
+    }
+    (positions, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn propagator_hands_back_positions_without_blocking() {
+        let mut rng = Rng::new(7);
+        let orbits: Arc<[Orbit]> = (0..100)
+            .map(|_| Orbit::new(&random_elements(&mut rng, &main_belt()), GM_SUN))
+            .collect();
+        let mut expected = vec![AsteroidPosition::default(); orbits.len()];
+        propagate(&orbits, &mut expected, 250.0);
+
+        let mut propagator = Propagator::new(Arc::clone(&orbits));
+        propagator.request(250.0);
+        // A second request while the first is in flight is dropped.
+        propagator.request(500.0);
+        let (days, positions) = (0..500)
+            .find_map(|_| {
+                thread::sleep(Duration::from_millis(10));
+                propagator
+                    .finished()
+                    .map(|(days, positions)| (days, positions.to_vec()))
+            })
+            .expect("propagation did not finish");
+        assert_eq!(days, 250.0);
+        for (position, expected) in positions.iter().zip(&expected) {
+            assert_eq!(position.instance_position, expected.instance_position);
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(propagator.finished().is_none());
+    }
+}
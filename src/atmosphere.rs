@@ -0,0 +1,94 @@
+//! Single-scattering atmospheres.
+//!
+//! Bodies with an [`Atmosphere`] get a shell drawn around them after the opaque pass. Its fragment
+//! shader marches the view ray through the shell and adds the light scattered towards the camera
+//! by gas (Rayleigh) and haze (Mie), so limbs turn blue and the terminator red.
+//!
+//! Lengths are measured in planet radii so the same values work for any scene scale. The shells are
+//! far thicker than the real ones to stay visible at the exaggerated planet sizes; the scattering
+//! coefficients are scaled down by the same factor to keep the optical depth realistic.
+
+use glam::{Mat4, Vec3};
+
+use crate::shaders::ProgramDesc;
+
+pub const ATMOSPHERE_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "atmosphere.vert",
+    fragment: "atmosphere.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Atmosphere {
+    /// Height of the top of the shell above the surface.
+    pub height: f32,
+    /// Height over which the gas density falls to 1/e.
+    pub rayleigh_scale_height: f32,
+    /// Height over which the haze density falls to 1/e.
+    pub mie_scale_height: f32,
+    /// Rayleigh scattering coefficients per planet radius at the surface, per RGB channel.
+    pub rayleigh: [f32; 3],
+    /// Mie scattering coefficient per planet radius at the surface.
+    pub mie: f32,
+    /// Mie phase asymmetry; values towards 1 concentrate the glow around the sun.
+    pub mie_g: f32,
+    /// Brightness of the incoming sunlight.
+    pub sun_intensity: f32,
+}
+
+impl Atmosphere {
+    pub fn earth() -> Self {
+        Self {
+            height: 0.04,
+            rayleigh_scale_height: 0.01,
+            mie_scale_height: 0.0015,
+            rayleigh: [4.6, 10.8, 26.5],
+            mie: 16.8,
+            mie_g: 0.76,
+            sun_intensity: 20.0,
+        }
+    }
+
+    /// Thick, sulfuric haze dominated by Mie scattering.
+    pub fn venus() -> Self {
+        Self {
+            height: 0.06,
+            rayleigh_scale_height: 0.02,
+            mie_scale_height: 0.01,
+            rayleigh: [9.0, 8.0, 5.0],
+            mie: 30.0,
+            mie_g: 0.6,
+            sun_intensity: 15.0,
+        }
+    }
+
+    /// Thin air whose dust scatters red light more than blue.
+    pub fn mars() -> Self {
+        Self {
+            height: 0.03,
+            rayleigh_scale_height: 0.008,
+            mie_scale_height: 0.004,
+            rayleigh: [6.0, 3.0, 1.5],
+            mie: 6.0,
+            mie_g: 0.7,
+            sun_intensity: 12.0,
+        }
+    }
+
+    /// Model matrix of the shell around a body of `radius` at `center`.
+    pub fn shell_matrix(&self, center: Vec3, radius: f32) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(radius * (1.0 + self.height)),
+            glam::Quat::IDENTITY,
+            center,
+        )
+    }
+
+    /// Whether `point` lies inside the shell around a body of `radius` at `center`. The shell is
+    /// drawn from the inside then.
+    pub fn contains(&self, center: Vec3, radius: f32, point: Vec3) -> bool {
+        point.distance(center) < radius * (1.0 + self.height)
+    }
+}
@@ -0,0 +1,335 @@
+//! Barnes-Hut gravity for large n-body simulations.
+//!
+//! The bodies are sorted into an octree whose nodes know the total mass and center of mass of
+//! everything below them. A body then treats any node that looks smaller than the opening angle θ
+//! from where it is (node width / distance < θ) as a single point mass, and only opens the nodes
+//! that are close or large. That brings the cost down from O(n²) to about O(n log n), at an error
+//! that grows with θ; θ = 0 opens every node and gives the direct sum back.
+//!
+//! Building the tree is serial, walking it is done for batches of bodies in parallel.
+//! [`ForceComparison`] measures both the speed-up and the error against direct summation for the
+//! bodies in the sandbox, and `cargo bench` times both methods for growing numbers of bodies.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use glam::DVec3;
+
+use crate::{
+    nbody::{NBody, direct_accelerations},
+    parallel::parallel_chunks_mut,
+};
+
+/// Bodies in a leaf before it is split.
+const LEAF_SIZE: usize = 8;
+/// Deeper nodes are never split, which bounds the tree when bodies sit on top of each other.
+const MAX_DEPTH: usize = 32;
+/// Fewest bodies worth a thread of their own.
+const MIN_BODIES_PER_THREAD: usize = 256;
+
+struct Node {
+    center: DVec3,
+    half_size: f64,
+    gm: f64,
+    center_of_mass: DVec3,
+    /// Radius of the largest body below this node, which softens its pull like the bodies' own.
+    max_radius: f64,
+    /// Index of the first of eight consecutive children in `nodes`, or 0 for a leaf.
+    children: usize,
+    /// Range into `order` of the bodies in this node.
+    bodies: Range<usize>,
+}
+
+pub struct Octree {
+    nodes: Vec<Node>,
+    /// Body indices, grouped so that every node's bodies are consecutive.
+    order: Vec<usize>,
+}
+
+impl Octree {
+    pub fn build(bodies: &[NBody]) -> Self {
+        let (min, max) = bodies.iter().fold(
+            (DVec3::splat(f64::INFINITY), DVec3::splat(f64::NEG_INFINITY)),
+            |(min, max), body| (min.min(body.position), max.max(body.position)),
+        );
+        let center = if bodies.is_empty() {
+            DVec3::ZERO
+        } else {
+            (min + max) / 2.0
+        };
+        let half_size = ((max - min).max_element() / 2.0).max(f64::MIN_POSITIVE);
+        let mut tree = Self {
+            nodes: vec![Node {
+                center,
+                half_size,
+                gm: 0.0,
+                center_of_mass: DVec3::ZERO,
+                max_radius: 0.0,
+                children: 0,
+                bodies: 0..bodies.len(),
+            }],
+            order: (0..bodies.len()).collect(),
+        };
+        tree.split(bodies, 0, 0);
+        tree
+    }
+
+    /// Fills in the mass of node `index` and splits it into octants if it holds too many bodies.
+    fn split(&mut self, bodies: &[NBody], index: usize, depth: usize) {
+        let range = self.nodes[index].bodies.clone();
+        let members = &self.order[range.clone()];
+        let gm: f64 = members.iter().map(|&body| bodies[body].gm).sum();
+        let weighted: DVec3 = members
+            .iter()
+            .map(|&body| bodies[body].position * bodies[body].gm)
+            .sum();
+        let max_radius = members
+            .iter()
+            .map(|&body| bodies[body].radius)
+            .fold(0.0, f64::max);
+        let node = &mut self.nodes[index];
+        node.gm = gm;
+        node.max_radius = max_radius;
+        node.center_of_mass = if gm > 0.0 { weighted / gm } else { node.center };
+        if range.len() <= LEAF_SIZE || depth >= MAX_DEPTH {
+            return;
+        }
+
+        let (center, half_size) = (node.center, node.half_size);
+        let octant = |body: usize| {
+            let offset = bodies[body].position - center;
+            usize::from(offset.x >= 0.0)
+                | usize::from(offset.y >= 0.0) << 1
+                | usize::from(offset.z >= 0.0) << 2
+        };
+        self.order[range.clone()].sort_unstable_by_key(|&body| octant(body));
+
+        let first_child = self.nodes.len();
+        self.nodes[index].children = first_child;
+        let mut start = range.start;
+        for child in 0..8 {
+            let count = self.order[start..range.end]
+                .iter()
+                .take_while(|&&body| octant(body) == child)
+                .count();
+            let sign = |bit: usize| if child & bit != 0 { 1.0 } else { -1.0 };
+            self.nodes.push(Node {
+                center: center + DVec3::new(sign(1), sign(2), sign(4)) * (half_size / 2.0),
+                half_size: half_size / 2.0,
+                gm: 0.0,
+                center_of_mass: DVec3::ZERO,
+                max_radius: 0.0,
+                children: 0,
+                bodies: start..start + count,
+            });
+            start += count;
+        }
+        for child in first_child..first_child + 8 {
+            if !self.nodes[child].bodies.is_empty() {
+                self.split(bodies, child, depth + 1);
+            }
+        }
+    }
+
+    /// Acceleration of body `index` from every other body, opening nodes that look larger than
+    /// `theta`. Close pairs are softened like in [`direct_accelerations`], and nodes as if they
+    /// were their largest body. `stack` is scratch space kept between calls.
+    fn acceleration(
+        &self,
+        bodies: &[NBody],
+        index: usize,
+        theta: f64,
+        stack: &mut Vec<usize>,
+    ) -> DVec3 {
+        let body = &bodies[index];
+        let mut acceleration = DVec3::ZERO;
+        stack.clear();
+        stack.push(0);
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.bodies.is_empty() {
+                continue;
+            }
+            if node.children == 0 {
+                for &other in &self.order[node.bodies.clone()] {
+                    if other == index {
+                        continue;
+                    }
+                    let other = &bodies[other];
+                    let offset = other.position - body.position;
+                    let distance = offset.length().max(body.radius + other.radius);
+                    acceleration += offset * (other.gm / (distance * distance * distance));
+                }
+                continue;
+            }
+            let offset = node.center_of_mass - body.position;
+            let distance = offset.length();
+            let inside = (body.position - node.center).abs().max_element() <= node.half_size;
+            if !inside && 2.0 * node.half_size < theta * distance {
+                let distance = distance.max(body.radius + node.max_radius);
+                acceleration += offset * (node.gm / (distance * distance * distance));
+            } else {
+                stack.extend(node.children..node.children + 8);
+            }
+        }
+        acceleration
+    }
+}
+
+/// Acceleration of every body with opening angle `theta`, walking the tree for batches of bodies
+/// in parallel.
+pub fn tree_accelerations(bodies: &[NBody], theta: f64) -> Vec<DVec3> {
+    let tree = Octree::build(bodies);
+    let mut accelerations = vec![DVec3::ZERO; bodies.len()];
+    parallel_chunks_mut(&mut accelerations, MIN_BODIES_PER_THREAD, |start, chunk| {
+        let mut stack = Vec::new();
+        for (offset, acceleration) in chunk.iter_mut().enumerate() {
+            *acceleration = tree.acceleration(bodies, start + offset, theta, &mut stack);
+        }
+    });
+    accelerations
+}
+
+/// Speed and accuracy of the tree against direct summation on the same bodies.
+#[derive(Clone, Copy, Debug)]
+pub struct ForceComparison {
+    pub bodies: usize,
+    pub theta: f64,
+    pub direct_time: Duration,
+    pub tree_time: Duration,
+    /// Root mean square and largest error of the tree accelerations, relative to the direct
+    /// ones.
+    pub rms_error: f64,
+    pub max_error: f64,
+}
+
+impl ForceComparison {
+    pub fn measure(bodies: &[NBody], theta: f64) -> Self {
+        let start = Instant::now();
+        let direct = direct_accelerations(bodies);
+        let direct_time = start.elapsed();
+        let start = Instant::now();
+        let tree = tree_accelerations(bodies, theta);
+        let tree_time = start.elapsed();
+
+        let errors: Vec<f64> = direct
+            .iter()
+            .zip(&tree)
+            .filter(|(direct, _)| direct.length() > 0.0)
+            .map(|(direct, tree)| (*tree - *direct).length() / direct.length())
+            .collect();
+        let count = errors.len().max(1) as f64;
+        Self {
+            bodies: bodies.len(),
+            theta,
+            direct_time,
+            tree_time,
+            rms_error: (errors.iter().map(|error| error * error).sum::<f64>() / count).sqrt(),
+            max_error: errors.iter().copied().fold(0.0, f64::max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbody::NBodySimulation;
+
+    const BODIES: usize = 2000;
+
+    /// Largest accepted RMS error relative to direct summation for each opening angle.
+    const RMS_BOUNDS: [(f64, f64); 3] = [(0.0, 1e-12), (0.5, 5e-3), (1.0, 5e-2)];
+
+    fn check_bounds(bodies: &[NBody]) {
+        let mut previous = 0.0;
+        for (theta, bound) in RMS_BOUNDS {
+            let comparison = ForceComparison::measure(bodies, theta);
+            assert_eq!(comparison.bodies, bodies.len());
+            assert!(
+                comparison.rms_error < bound,
+                "θ {theta}: RMS error {} above {bound}",
+                comparison.rms_error
+            );
+            assert!(comparison.max_error >= comparison.rms_error);
+            assert!(comparison.rms_error >= previous, "θ {theta}: error shrank");
+            previous = comparison.rms_error;
+        }
+    }
+
+    #[test]
+    fn star_cluster_accuracy() {
+        check_bounds(&NBodySimulation::star_cluster(BODIES, 1).bodies);
+    }
+
+    #[test]
+    fn disk_accuracy() {
+        check_bounds(&NBodySimulation::protoplanetary_disk(BODIES, 2).bodies);
+    }
+
+    #[test]
+    fn tree_holds_every_body_once() {
+        let bodies = NBodySimulation::star_cluster(BODIES, 3).bodies;
+        let tree = Octree::build(&bodies);
+        let mut order = tree.order.clone();
+        order.sort_unstable();
+        assert!(order.iter().copied().eq(0..bodies.len()));
+
+        let root = &tree.nodes[0];
+        let gm: f64 = bodies.iter().map(|body| body.gm).sum();
+        assert!((root.gm - gm).abs() < 1e-12 * gm);
+        for node in &tree.nodes {
+            for &body in &tree.order[node.bodies.clone()] {
+                let offset = (bodies[body].position - node.center).abs();
+                assert!(offset.max_element() <= node.half_size * (1.0 + 1e-12));
+            }
+            if node.children != 0 {
+                let children = &tree.nodes[node.children..node.children + 8];
+                let child_gm: f64 = children.iter().map(|child| child.gm).sum();
+                assert!((child_gm - node.gm).abs() <= 1e-12 * node.gm);
+            } else {
+                assert!(node.bodies.len() <= LEAF_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn coincident_bodies_stop_splitting() {
+        let body = NBody {
+            position: DVec3::ONE,
+            velocity: DVec3::ZERO,
+            gm: 1e-9,
+            radius: 1e-3,
+        };
+        let bodies = vec![body; 3 * LEAF_SIZE];
+        let accelerations = tree_accelerations(&bodies, 0.5);
+        assert!(
+            accelerations
+                .iter()
+                .all(|acceleration| acceleration.is_finite())
+        );
+        assert!(tree_accelerations(&[], 0.5).is_empty());
+        assert_eq!(tree_accelerations(&bodies[..1], 0.5), [DVec3::ZERO]);
+    }
+
+    #[test]
+    fn nodes_are_softened_like_their_bodies() {
+        let body = |position: DVec3, radius: f64| NBody {
+            position,
+            velocity: DVec3::ZERO,
+            gm: 1e-9,
+            radius,
+        };
+        // A clump of large bodies far enough to be one node, but within touching distance of the
+        // probe, so that every pull is softened.
+        let mut bodies = vec![body(DVec3::NEG_ONE, 0.5)];
+        for index in 0..=LEAF_SIZE {
+            let spread = DVec3::new(index as f64, (index * 3 % 7) as f64, (index * 5 % 9) as f64);
+            bodies.push(body(DVec3::ONE - spread * 1e-4, 3.0));
+        }
+        let direct = direct_accelerations(&bodies)[0];
+        let tree = tree_accelerations(&bodies, 1.0)[0];
+        assert!((tree - direct).length() < 1e-3 * direct.length());
+    }
+}
@@ -0,0 +1,97 @@
+//! Definitions of the bodies that make up a planetary system.
+//!
+//! A [`BodyDefinition`] is plain data: what a body is called, how big it is, how it moves and
+//! which textures it uses. Rendering state lives with the renderers.
+
+use glam::Vec3;
+
+use crate::{atmosphere::Atmosphere, color::temperature_to_linear_rgb, kepler::OrbitalElements};
+
+/// Texture files for a body. Missing entries fall back to neutral defaults: flat albedo tinted by
+/// [`BodyDefinition::color`], no bumps, no specular highlights and no night lights.
+#[derive(Clone, Debug, Default)]
+pub struct BodyTextures {
+    /// Diffuse color, sRGB.
+    pub albedo: Option<String>,
+    /// Tangent-space normal map, linear.
+    pub normal: Option<String>,
+    /// Specular mask in the red channel, linear. Bright where water reflects the sun.
+    pub specular: Option<String>,
+    /// Emissive city lights shown on the night side, sRGB.
+    pub night: Option<String>,
+}
+
+/// A flat ring system in the body's equatorial plane.
+#[derive(Clone, Debug)]
+pub struct Rings {
+    /// Inner edge in planet radii.
+    pub inner_radius: f32,
+    /// Outer edge in planet radii.
+    pub outer_radius: f32,
+    /// Radial strip running from the inner to the outer edge, sRGB color with opacity in alpha.
+    pub texture: Option<String>,
+    /// Linear color multiplied with the texture.
+    pub color: [f32; 3],
+    /// Opacity multiplied with the texture alpha.
+    pub opacity: f32,
+}
+
+/// Kepler orbit of a body around another one.
+#[derive(Clone, Debug)]
+pub struct BodyOrbit {
+    /// Name of the body orbited.
+    pub parent: String,
+    /// Elements relative to the parent at J2000, in AU.
+    pub elements: OrbitalElements,
+}
+
+#[derive(Clone, Debug)]
+pub struct BodyDefinition {
+    pub name: String,
+    /// Radius in scene units.
+    pub radius: f32,
+    /// Position in scene units, at J2000 for bodies with an orbit.
+    pub position: Vec3,
+    /// Orbit the body moves on, `None` for bodies that stay at `position`.
+    pub orbit: Option<BodyOrbit>,
+    /// Gravitational parameter in AU³/day².
+    pub gm: f64,
+    /// Tilt of the rotation axis against the ecliptic normal in degrees.
+    pub axial_tilt_deg: f32,
+    /// Linear color multiplied with the albedo texture.
+    pub color: [f32; 3],
+    pub textures: BodyTextures,
+    /// Stars light the system and are drawn unlit.
+    pub emissive: bool,
+    /// Effective surface temperature in Kelvin. Sets the color of stars.
+    pub temperature: Option<f32>,
+    pub atmosphere: Option<Atmosphere>,
+    pub rings: Option<Rings>,
+}
+
+impl Default for BodyDefinition {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            radius: 1.0,
+            position: Vec3::ZERO,
+            orbit: None,
+            gm: 0.0,
+            axial_tilt_deg: 0.0,
+            color: [1.0, 1.0, 1.0],
+            textures: BodyTextures::default(),
+            emissive: false,
+            temperature: None,
+            atmosphere: None,
+            rings: None,
+        }
+    }
+}
+
+impl BodyDefinition {
+    /// Linear color of the light the body emits, from its temperature if it has one.
+    pub fn emission_color(&self) -> [f32; 3] {
+        self.temperature
+            .map_or(self.color, temperature_to_linear_rgb)
+    }
+}
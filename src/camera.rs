@@ -1,8 +1,9 @@
-use glam::{Mat4, Vec3, vec3};
+use glam::{Mat3, Mat4, Vec3, vec3};
 use glium::winit::keyboard::{KeyCode, PhysicalKey};
 use std::collections::HashSet;
 
 pub struct Camera {
+    /// Vertical field of view in degrees.
     fov: f32,
     dist: f32,
     position: Vec3,
@@ -32,6 +33,17 @@ impl Camera {
         // Create a view matrix using the camera's position, target, and up vector
         Mat4::look_at_rh(self.position, self.target, self.up)
     }
+    pub fn get_perspective_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        Mat4::perspective_rh(self.get_fov_y(), aspect_ratio, 0.1, self.dist)
+    }
+    /// Vertical field of view in radians.
+    pub fn get_fov_y(&self) -> f32 {
+        self.fov.to_radians()
+    }
+    /// View matrix without the camera translation, for things infinitely far away.
+    pub fn get_sky_view_matrix(&self) -> Mat4 {
+        Mat4::from_mat3(Mat3::from_mat4(self.get_view_matrix()))
+    }
     fn translate(&mut self, delta: Vec3) {
         self.position += delta;
         self.target += delta;
@@ -63,16 +75,37 @@ impl Camera {
         self.target = self.position + f;
     }
 
-    pub fn set_speed(&mut self, speed: f32) {
-        self.speed = speed;
+    /// Moves the camera to `position` and turns it towards `target`.
+    pub fn look_at(&mut self, position: Vec3, target: Vec3) {
+        let f = (target - position).normalize();
+        self.position = position;
+        self.up = Vec3::Y;
+        self.yaw = f.x.atan2(f.z);
+        self.pitch = f.y.asin();
+        self.apply_yaw_pitch();
+    }
+
+    /// Places the camera at `position` looking at `target` with `up` towards the top of the
+    /// screen, for views attached to a moving object. [`Camera::look_at`] restores the Y up
+    /// vector for free flight.
+    pub fn set_pose(&mut self, position: Vec3, target: Vec3, up: Vec3) {
+        self.position = position;
+        self.target = target;
+        self.up = up;
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        self.position
     }
 
-    pub fn get_fov(&self) -> f32 {
-        self.fov
+    #[expect(dead_code, reason = "part of the camera API, no setting drives it yet")]
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
     }
 
-    pub fn get_dist(&self) -> f32 {
-        self.dist
+    /// Unit vector in the direction the camera is looking.
+    pub fn get_forward(&self) -> Vec3 {
+        (self.target - self.position).normalize()
     }
 
     pub fn update_camera(&mut self, keys_pressed: &HashSet<PhysicalKey>, delta_time: f32) {
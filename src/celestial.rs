@@ -0,0 +1,141 @@
+//! Celestial coordinate frames.
+//!
+//! Directions on the sky are unit vectors. The frames follow the usual astronomical conventions:
+//! in the equatorial frame `+X` points at the vernal equinox and `+Z` at the north celestial pole,
+//! in the ecliptic frame `+Z` points at the north ecliptic pole. The scene is laid out on the
+//! ecliptic with Y up, so [`ecliptic_to_scene`] maps the ecliptic pole onto `+Y`.
+
+use glam::{DMat3, DVec3, Vec3};
+
+/// Obliquity of the ecliptic at J2000 in degrees.
+const OBLIQUITY_J2000_DEG: f64 = 23.439_291_1;
+
+/// Unit vector for a longitude/latitude pair in radians (right ascension/declination, ecliptic
+/// longitude/latitude, ...).
+pub fn spherical_to_cartesian(longitude: f64, latitude: f64) -> DVec3 {
+    let (sin_lat, cos_lat) = latitude.sin_cos();
+    let (sin_lon, cos_lon) = longitude.sin_cos();
+    DVec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat)
+}
+
+/// Direction of a star given its right ascension in hours and declination in degrees.
+pub fn equatorial_direction(ra_hours: f64, dec_deg: f64) -> DVec3 {
+    spherical_to_cartesian((ra_hours * 15.0).to_radians(), dec_deg.to_radians())
+}
+
+pub fn equatorial_to_ecliptic(v: DVec3) -> DVec3 {
+    let (sin_e, cos_e) = OBLIQUITY_J2000_DEG.to_radians().sin_cos();
+    DVec3::new(v.x, v.y * cos_e + v.z * sin_e, -v.y * sin_e + v.z * cos_e)
+}
+
+/// Converts from the ecliptic frame to the Y-up scene frame.
+pub fn ecliptic_to_scene(v: DVec3) -> Vec3 {
+    Vec3::new(v.x as f32, v.z as f32, -v.y as f32)
+}
+
+/// Converts from the Y-up scene frame back to the ecliptic frame.
+pub fn scene_to_ecliptic(v: Vec3) -> DVec3 {
+    DVec3::new(v.x as f64, -v.z as f64, v.y as f64)
+}
+
+pub fn ecliptic_to_equatorial(v: DVec3) -> DVec3 {
+    let (sin_e, cos_e) = OBLIQUITY_J2000_DEG.to_radians().sin_cos();
+    DVec3::new(v.x, v.y * cos_e - v.z * sin_e, v.y * sin_e + v.z * cos_e)
+}
+
+/// Rotation from J2000 equatorial to galactic coordinates (Hipparcos, ESA 1997, vol. 1, §1.5.3).
+const EQUATORIAL_TO_GALACTIC: DMat3 = DMat3::from_cols_array(&[
+    -0.054_875_560_4,
+    0.494_109_427_9,
+    -0.867_666_149_0,
+    -0.873_437_090_2,
+    -0.444_829_630_0,
+    -0.198_076_373_4,
+    -0.483_835_015_5,
+    0.746_982_244_5,
+    0.455_983_776_2,
+]);
+
+pub fn equatorial_to_galactic(v: DVec3) -> DVec3 {
+    EQUATORIAL_TO_GALACTIC * v
+}
+
+pub fn galactic_to_equatorial(v: DVec3) -> DVec3 {
+    EQUATORIAL_TO_GALACTIC.transpose() * v
+}
+
+/// Longitude in `[0, 2π)` and latitude in radians of a direction. Inverse of
+/// [`spherical_to_cartesian`].
+pub fn cartesian_to_spherical(v: DVec3) -> (f64, f64) {
+    let v = v.normalize();
+    let longitude = v.y.atan2(v.x).rem_euclid(std::f64::consts::TAU);
+    let latitude = v.z.clamp(-1.0, 1.0).asin();
+    (longitude, latitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Right ascension and declination in degrees of an equatorial direction.
+    fn ra_dec_deg(v: DVec3) -> (f64, f64) {
+        let (ra, dec) = cartesian_to_spherical(v);
+        (ra.to_degrees(), dec.to_degrees())
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    fn sample_directions() -> impl Iterator<Item = DVec3> {
+        (0..24).flat_map(|hour| {
+            [-89.0, -45.0, 0.0, 30.0, 89.9].map(|dec| equatorial_direction(hour as f64, dec))
+        })
+    }
+
+    #[test]
+    fn frame_conversions_round_trip() {
+        for v in sample_directions() {
+            let ecliptic = equatorial_to_ecliptic(v);
+            assert!(ecliptic_to_equatorial(ecliptic).abs_diff_eq(v, 1e-12));
+            assert!(galactic_to_equatorial(equatorial_to_galactic(v)).abs_diff_eq(v, 1e-9));
+            let scene = ecliptic_to_scene(ecliptic);
+            assert!(scene_to_ecliptic(scene).abs_diff_eq(ecliptic, 1e-6));
+            assert_close(equatorial_to_galactic(v).length(), 1.0, 1e-9);
+        }
+    }
+
+    #[test]
+    fn spherical_round_trip() {
+        for (longitude, latitude) in [(0.0, 0.0), (1.0, 0.5), (4.0, -1.2), (6.2, 1.5)] {
+            let (lon, lat) = cartesian_to_spherical(spherical_to_cartesian(longitude, latitude));
+            assert_close(lon, longitude, 1e-12);
+            assert_close(lat, latitude, 1e-12);
+        }
+    }
+
+    #[test]
+    fn ecliptic_pole_position() {
+        let (ra, dec) = ra_dec_deg(ecliptic_to_equatorial(DVec3::Z));
+        assert_close(ra, 270.0, 1e-9);
+        assert_close(dec, 90.0 - OBLIQUITY_J2000_DEG, 1e-9);
+        assert_close(dec, 66.56, 0.01);
+        // The equinox lies on both the equator and the ecliptic.
+        assert!(equatorial_to_ecliptic(DVec3::X).abs_diff_eq(DVec3::X, 1e-12));
+        assert_eq!(ecliptic_to_scene(DVec3::Z), Vec3::Y);
+    }
+
+    #[test]
+    fn galactic_reference_points() {
+        let (ra, dec) = ra_dec_deg(galactic_to_equatorial(DVec3::Z));
+        assert_close(ra, 192.859_48, 1e-4);
+        assert_close(dec, 27.128_25, 1e-4);
+        // Galactic center (l = 0, b = 0), near Sagittarius A*.
+        let (ra, dec) = ra_dec_deg(galactic_to_equatorial(DVec3::X));
+        assert_close(ra, 266.405, 1e-3);
+        assert_close(dec, -28.936, 1e-3);
+    }
+}
@@ -0,0 +1,41 @@
+//! Simulation time.
+
+/// Days since the J2000 epoch, advanced by the frame time at an adjustable rate.
+pub struct SimulationClock {
+    pub days: f64,
+    /// Simulated days per real second.
+    pub days_per_second: f64,
+    pub paused: bool,
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        Self {
+            days: 0.0,
+            days_per_second: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        if !self.paused {
+            self.days += self.days_per_second * delta_time as f64;
+        }
+    }
+}
+
+/// Calendar date (UTC, ignoring the difference to TT) of `days` after J2000, as `YYYY-MM-DD`.
+pub fn format_date(days: f64) -> String {
+    // Days since 1970-01-01, converted with Howard Hinnant's civil_from_days.
+    let z = (days + 0.5).floor() as i64 + 10_957 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
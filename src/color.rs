@@ -0,0 +1,48 @@
+//! Star colors.
+
+/// Effective temperature in Kelvin for a B-V color index (Ballesteros 2012).
+pub fn bv_to_temperature(b_v: f32) -> f32 {
+    4600.0 * (1.0 / (0.92 * b_v + 1.7) + 1.0 / (0.92 * b_v + 0.62))
+}
+
+/// Approximate sRGB color of a black body at `kelvin`, normalized so the brightest channel is 1.
+/// Based on Tanner Helland's fit of the CIE 1964 color matching functions.
+pub fn temperature_to_rgb(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40_000.0) / 100.0;
+
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    let rgb = [red, green, blue].map(|c: f32| c.clamp(0.0, 255.0) / 255.0);
+    let max = rgb.iter().copied().fold(f32::EPSILON, f32::max);
+    rgb.map(|c| c / max)
+}
+
+/// Linear color of a black body at `kelvin`, for lighting and the HDR target.
+pub fn temperature_to_linear_rgb(kelvin: f32) -> [f32; 3] {
+    temperature_to_rgb(kelvin).map(srgb_to_linear)
+}
+
+/// Decodes one sRGB channel.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
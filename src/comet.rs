@@ -0,0 +1,396 @@
+//! Comets.
+//!
+//! A comet's nucleus follows a Kepler orbit, usually a very eccentric one, and sheds particles
+//! into two tails once it gets close enough to the sun:
+//!
+//! - The ion tail is gas carried off by the solar wind, so its particles stream straight away
+//!   from the sun much faster than the comet moves.
+//! - The dust tail is grains pushed out only by radiation pressure. That weakens the sun's
+//!   gravity by a factor `1 - β` per grain, so grains drift slowly onto wider orbits and the tail
+//!   curves back along the comet's path.
+//!
+//! Activity falls off with the square of the distance to the sun and stops beyond
+//! [`ACTIVITY_LIMIT_AU`], which makes both tails shorter and fainter away from perihelion.
+//! Particles live in heliocentric ecliptic coordinates in AU and are integrated in simulation
+//! time.
+
+use glam::{DVec3, Vec3};
+
+use crate::{
+    celestial::ecliptic_to_scene,
+    frustum::Frustum,
+    kepler::{GM_SUN, Orbit, OrbitalElements},
+    particles::ParticleInstance,
+    random::Rng,
+    system::AU,
+};
+
+/// Beyond this distance from the sun a comet is inactive.
+const ACTIVITY_LIMIT_AU: f64 = 5.0;
+/// Activity stops growing closer to the sun than this.
+const MAX_ACTIVITY: f64 = 10.0;
+/// Particles emitted per simulated day into each tail at an activity of 1.
+const ION_RATE: f64 = 400.0;
+const DUST_RATE: f64 = 200.0;
+/// Solar wind speed in AU per day (about 400 km/s).
+const SOLAR_WIND_SPEED: f64 = 0.23;
+/// Lifetime in days of an ion particle at an activity of 1.
+const ION_LIFETIME: f64 = 0.4;
+const DUST_LIFETIME: f64 = 30.0;
+/// Longest integration step in days.
+const MAX_STEP: f64 = 0.5;
+/// Larger jumps in simulation time restart the tails instead of integrating across them.
+const MAX_JUMP: f64 = 365.0;
+const MAX_PARTICLES: usize = 20_000;
+
+#[derive(Clone, Debug)]
+pub struct CometDefinition {
+    pub name: String,
+    pub elements: OrbitalElements,
+    /// Radius of the coma glow at an activity of 1, in scene units.
+    pub coma_radius: f32,
+    /// Linear colors.
+    pub ion_color: [f32; 3],
+    pub dust_color: [f32; 3],
+    /// Scales the brightness of the coma and tails.
+    pub brightness: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tail {
+    Ion,
+    Dust,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TailParticle {
+    tail: Tail,
+    position: DVec3,
+    velocity: DVec3,
+    age: f64,
+    lifetime: f64,
+    /// Fraction of the sun's gravity cancelled by radiation pressure.
+    beta: f64,
+    /// Activity when the particle was emitted.
+    brightness: f32,
+}
+
+impl TailParticle {
+    /// Advances the particle by `dt` days. Ions coast, dust falls in the reduced gravity.
+    fn integrate(&mut self, dt: f64) {
+        self.age += dt;
+        if self.tail == Tail::Ion {
+            self.position += self.velocity * dt;
+            return;
+        }
+        let steps = (dt / MAX_STEP).ceil().max(1.0);
+        let step = dt / steps;
+        for _ in 0..steps as usize {
+            let r = self.position.length();
+            let acceleration = -self.position * ((1.0 - self.beta) * GM_SUN / (r * r * r));
+            self.velocity += acceleration * step;
+            self.position += self.velocity * step;
+        }
+    }
+}
+
+/// Relative activity at `distance` AU from the sun: `1 / r²`, fading to zero between 60% of
+/// [`ACTIVITY_LIMIT_AU`] and the limit.
+pub fn activity(distance: f64) -> f64 {
+    let fade = ((ACTIVITY_LIMIT_AU - distance) / (0.4 * ACTIVITY_LIMIT_AU)).clamp(0.0, 1.0);
+    let fade = fade * fade * (3.0 - 2.0 * fade);
+    (fade / (distance * distance)).min(MAX_ACTIVITY)
+}
+
+pub struct Comet {
+    pub definition: CometDefinition,
+    orbit: Orbit,
+    /// Heliocentric ecliptic position in AU and velocity in AU per day.
+    pub position: DVec3,
+    pub velocity: DVec3,
+    particles: Vec<TailParticle>,
+    rng: Rng,
+    simulated_days: Option<f64>,
+    /// Distance in scene units from the nucleus to its furthest particle.
+    extent: f32,
+}
+
+impl Comet {
+    pub fn new(definition: CometDefinition, seed: u64) -> Self {
+        let orbit = Orbit::new(&definition.elements, GM_SUN);
+        let (position, velocity) = orbit.state(0.0);
+        Self {
+            definition,
+            orbit,
+            position,
+            velocity,
+            particles: Vec::new(),
+            rng: Rng::new(seed),
+            simulated_days: None,
+            extent: 0.0,
+        }
+    }
+
+    pub fn distance_to_sun(&self) -> f64 {
+        self.position.length()
+    }
+
+    pub fn activity(&self) -> f64 {
+        activity(self.distance_to_sun())
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Moves the nucleus to `days` after the epoch, ages the tails and emits new particles.
+    pub fn update(&mut self, days: f64) {
+        let dt = self.simulated_days.map_or(0.0, |last| days - last);
+        self.simulated_days = Some(days);
+        (self.position, self.velocity) = self.orbit.state(days);
+        if !(0.0..=MAX_JUMP).contains(&dt) {
+            self.particles.clear();
+            return;
+        }
+        if dt == 0.0 {
+            return;
+        }
+
+        for particle in &mut self.particles {
+            particle.integrate(dt);
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        let activity = self.activity();
+        if activity > 0.0 {
+            self.emit(days, Tail::Ion, ION_RATE, dt, activity);
+            self.emit(days, Tail::Dust, DUST_RATE, dt, activity);
+        }
+
+        let nucleus = self.scene_position();
+        self.extent = self
+            .particles
+            .iter()
+            .map(|particle| scene_position(particle.position).distance(nucleus))
+            .fold(
+                self.definition.coma_radius * MAX_ACTIVITY.sqrt() as f32,
+                f32::max,
+            );
+    }
+
+    /// Emits the particles of one tail released during the last `dt` days before `days`. Each one
+    /// starts where the nucleus was when it was released. Particles that would already have died
+    /// by now are never created, so large time steps stay cheap.
+    fn emit(&mut self, days: f64, tail: Tail, rate: f64, dt: f64, activity: f64) {
+        let lifetime = match tail {
+            Tail::Ion => ION_LIFETIME * activity.sqrt().clamp(0.3, 2.0),
+            Tail::Dust => DUST_LIFETIME,
+        };
+        let window = dt.min(lifetime);
+        let expected = rate * activity * window;
+        let mut count = expected.floor() as usize;
+        if self.rng.next_f64() < expected.fract() {
+            count += 1;
+        }
+        count = count.min(MAX_PARTICLES.saturating_sub(self.particles.len()));
+
+        for _ in 0..count {
+            let age = self.rng.range(0.0, window);
+            let (position, nucleus_velocity) = self.orbit.state(days - age);
+            let away_from_sun = position.normalize();
+            let spread = DVec3::new(
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+            );
+            let (velocity, beta) = match tail {
+                Tail::Ion => ((away_from_sun + spread * 0.03) * SOLAR_WIND_SPEED, 0.0),
+                Tail::Dust => (
+                    nucleus_velocity + (away_from_sun + spread) * 2e-4,
+                    self.rng.range(0.05, 1.0),
+                ),
+            };
+            let mut particle = TailParticle {
+                tail,
+                position,
+                velocity,
+                age: 0.0,
+                lifetime: lifetime * self.rng.range(0.6, 1.0),
+                beta,
+                brightness: activity.min(4.0) as f32,
+            };
+            particle.integrate(age);
+            if particle.age < particle.lifetime {
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    pub fn scene_position(&self) -> Vec3 {
+        scene_position(self.position)
+    }
+
+    /// Appends the coma and tail particles to `instances` unless the comet is outside `frustum`.
+    /// Returns whether it was visible.
+    pub fn collect_particles(
+        &self,
+        frustum: &Frustum,
+        instances: &mut Vec<ParticleInstance>,
+    ) -> bool {
+        let nucleus = self.scene_position();
+        if !frustum.intersects_sphere(nucleus, self.extent) {
+            return false;
+        }
+        let definition = &self.definition;
+        let activity = self.activity() as f32;
+        // The nucleus stays visible as a faint point even when the comet is inactive.
+        instances.push(ParticleInstance {
+            particle_position: nucleus.to_array(),
+            particle_color: definition.dust_color.map(|c| c * definition.brightness),
+            particle_size: 0.05,
+        });
+        if activity > 0.0 {
+            instances.push(ParticleInstance {
+                particle_position: nucleus.to_array(),
+                particle_color: definition
+                    .dust_color
+                    .map(|c| c * definition.brightness * activity.min(4.0)),
+                particle_size: definition.coma_radius * activity.sqrt(),
+            });
+        }
+        for particle in &self.particles {
+            let life = (particle.age / particle.lifetime) as f32;
+            let (color, size, intensity) = match particle.tail {
+                Tail::Ion => (definition.ion_color, 0.4, 0.15),
+                Tail::Dust => (definition.dust_color, 0.3 + 1.5 * life, 0.05),
+            };
+            // Fade in quickly so the tail does not start with a hard edge, then fade out.
+            let fade = (life * 20.0).min(1.0) * (1.0 - life) * (1.0 - life);
+            let brightness = intensity * particle.brightness * definition.brightness * fade;
+            instances.push(ParticleInstance {
+                particle_position: scene_position(particle.position).to_array(),
+                particle_color: color.map(|c| c * brightness),
+                particle_size: size,
+            });
+        }
+        true
+    }
+}
+
+fn scene_position(ecliptic_au: DVec3) -> Vec3 {
+    ecliptic_to_scene(ecliptic_au) * AU
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A comet on a 3 AU orbit with its perihelion at 0.6 AU, `mean_anomaly` past perihelion.
+    fn comet(mean_anomaly: f64) -> Comet {
+        let definition = CometDefinition {
+            name: "Test".to_owned(),
+            elements: OrbitalElements {
+                semi_major_axis: 3.0,
+                eccentricity: 0.8,
+                inclination: 0.2,
+                longitude_of_ascending_node: 1.0,
+                argument_of_periapsis: 2.0,
+                mean_anomaly,
+            },
+            coma_radius: 0.6,
+            ion_color: [0.25, 0.45, 1.0],
+            dust_color: [1.0, 0.85, 0.6],
+            brightness: 1.0,
+        };
+        Comet::new(definition, 3)
+    }
+
+    /// Runs the comet for `days` in steps of `step` from the epoch.
+    fn run(comet: &mut Comet, days: f64, step: f64) {
+        let steps = (days / step).round() as usize;
+        for i in 0..=steps {
+            comet.update(i as f64 * step);
+        }
+    }
+
+    fn tail(comet: &Comet, tail: Tail) -> impl Iterator<Item = &TailParticle> {
+        comet.particles.iter().filter(move |p| p.tail == tail)
+    }
+
+    #[test]
+    fn ion_tail_points_away_from_the_sun() {
+        let mut comet = comet(0.0);
+        run(&mut comet, 1.0, 0.05);
+        let away_from_sun = comet.position.normalize();
+        let mut count = 0;
+        for particle in tail(&comet, Tail::Ion) {
+            let offset = particle.position - comet.position;
+            // The nucleus has moved on since older particles left it, but far less than they have.
+            if offset.length() > 0.01 {
+                assert!(offset.normalize().dot(away_from_sun) > 0.95);
+                count += 1;
+            }
+        }
+        assert!(count > 100, "only {count} ion particles");
+    }
+
+    #[test]
+    fn dust_tail_trails_the_orbit() {
+        let mut comet = comet(0.0);
+        run(&mut comet, 20.0, 0.5);
+        // Along the orbit, across the line to the sun.
+        let ahead = comet
+            .position
+            .cross(comet.velocity)
+            .cross(comet.position)
+            .normalize();
+        let away_from_sun = comet.position.normalize();
+        let old: Vec<_> = tail(&comet, Tail::Dust)
+            .filter(|particle| particle.age > 10.0)
+            .map(|particle| particle.position - comet.position)
+            .collect();
+        assert!(old.len() > 100, "only {} old dust particles", old.len());
+        let mean = old.iter().sum::<DVec3>() / old.len() as f64;
+        // Behind the nucleus along its path, and pushed away from the sun.
+        assert!(mean.dot(ahead) < 0.0, "{mean}");
+        assert!(mean.dot(away_from_sun) > 0.0, "{mean}");
+        let trailing = old.iter().filter(|offset| offset.dot(ahead) < 0.0).count();
+        assert!(trailing * 10 > old.len() * 9, "{trailing} of {}", old.len());
+    }
+
+    #[test]
+    fn tails_grow_towards_the_sun() {
+        // Perihelion at 0.6 AU against about 2.3 AU on the way out.
+        let mut near = comet(0.0);
+        let mut far = comet(0.5);
+        assert!(far.distance_to_sun() > 2.0 && far.distance_to_sun() < 3.0);
+        assert!(near.activity() > far.activity() && far.activity() > 0.0);
+
+        let length = |comet: &Comet| {
+            tail(comet, Tail::Ion)
+                .map(|particle| particle.position.distance(comet.position))
+                .fold(0.0, f64::max)
+        };
+        let brightness = |comet: &Comet| tail(comet, Tail::Ion).map(|p| p.brightness).sum::<f32>();
+        let (start_near, start_far) = (near.distance_to_sun(), far.distance_to_sun());
+        for comet in [&mut near, &mut far] {
+            run(comet, 2.0, 0.1);
+        }
+        assert!(near.distance_to_sun() < start_near + 0.1);
+        assert!(far.distance_to_sun() > start_far);
+        assert!(length(&near) > 2.0 * length(&far));
+        assert!(brightness(&near) > 4.0 * brightness(&far));
+        assert!(tail(&near, Tail::Ion).count() > tail(&far, Tail::Ion).count());
+    }
+
+    #[test]
+    fn inactive_far_from_the_sun() {
+        assert_eq!(activity(ACTIVITY_LIMIT_AU), 0.0);
+        assert_eq!(activity(2.0 * ACTIVITY_LIMIT_AU), 0.0);
+        assert_eq!(activity(0.01), MAX_ACTIVITY);
+        let mut comet = comet(std::f64::consts::PI);
+        run(&mut comet, 5.0, 0.5);
+        assert_eq!(comet.particle_count(), 0);
+    }
+}
@@ -0,0 +1,265 @@
+//! Cube face conventions used by skybox packs.
+//!
+//! OpenGL samples a cubemap with the RenderMan layout: for every face the image is stored top row
+//! first and `s`/`t` run along fixed world axes (e.g. the `+X` face has `s = -z`, `t = -y`).
+//! Tools exporting six separate images do not agree on that layout, so each pack declares the
+//! [`FaceConvention`] it was authored in and [`FaceConvention::mapping`] tells the loader which
+//! source image ends up on which OpenGL face and how it has to be turned to line up with its
+//! neighbours.
+
+use glium::texture::CubeLayer;
+use image::{RgbaImage, imageops};
+
+/// One side of a cube, in the order skybox packs name their images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// Position of this face in [`CubeFace::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn layer(self) -> CubeLayer {
+        match self {
+            CubeFace::PositiveX => CubeLayer::PositiveX,
+            CubeFace::NegativeX => CubeLayer::NegativeX,
+            CubeFace::PositiveY => CubeLayer::PositiveY,
+            CubeFace::NegativeY => CubeLayer::NegativeY,
+            CubeFace::PositiveZ => CubeLayer::PositiveZ,
+            CubeFace::NegativeZ => CubeLayer::NegativeZ,
+        }
+    }
+}
+
+/// How a source image has to be turned before it matches the OpenGL face layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaceOrientation {
+    Identity,
+    FlipVertical,
+    /// Quarter turn clockwise.
+    Rotate90,
+    Rotate180,
+    /// Quarter turn counter-clockwise.
+    Rotate270,
+}
+
+impl FaceOrientation {
+    pub fn apply(self, image: RgbaImage) -> RgbaImage {
+        match self {
+            FaceOrientation::Identity => image,
+            FaceOrientation::FlipVertical => imageops::flip_vertical(&image),
+            FaceOrientation::Rotate90 => imageops::rotate90(&image),
+            FaceOrientation::Rotate180 => imageops::rotate180(&image),
+            FaceOrientation::Rotate270 => imageops::rotate270(&image),
+        }
+    }
+}
+
+/// Where the image for one OpenGL face comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceMapping {
+    pub source: CubeFace,
+    pub orientation: FaceOrientation,
+}
+
+/// The layout a skybox pack was authored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaceConvention {
+    /// Y-up, images top row first. Matches the OpenGL cubemap layout one to one.
+    OpenGl,
+    /// Unity six-sided skyboxes, as exported for the bundled galaxy pack: the OpenGL layout
+    /// mirrored along Y, so every face is flipped and the up/down images trade places. This is
+    /// what that pack needs to line up; other Unity exports have not been checked against it.
+    Unity,
+    /// Blender's Z-up world (`+Z` up, `-Y` towards the viewer) with OpenGL face orientation.
+    /// The Z images become the sky and ground, and the side faces are rotated onto Y-up.
+    Blender,
+}
+
+impl FaceConvention {
+    pub const ALL: [FaceConvention; 3] = [
+        FaceConvention::OpenGl,
+        FaceConvention::Unity,
+        FaceConvention::Blender,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FaceConvention::OpenGl => "OpenGL",
+            FaceConvention::Unity => "Unity",
+            FaceConvention::Blender => "Blender",
+        }
+    }
+
+    /// Returns which source image fills the OpenGL face `target` and how it has to be oriented.
+    pub fn mapping(self, target: CubeFace) -> FaceMapping {
+        use CubeFace::*;
+        use FaceOrientation::*;
+
+        let (source, orientation) = match (self, target) {
+            (FaceConvention::OpenGl, face) => (face, Identity),
+
+            (FaceConvention::Unity, PositiveY) => (NegativeY, FlipVertical),
+            (FaceConvention::Unity, NegativeY) => (PositiveY, FlipVertical),
+            (FaceConvention::Unity, face) => (face, FlipVertical),
+
+            (FaceConvention::Blender, PositiveX) => (PositiveX, Rotate90),
+            (FaceConvention::Blender, NegativeX) => (NegativeX, Rotate270),
+            (FaceConvention::Blender, PositiveY) => (PositiveZ, Identity),
+            (FaceConvention::Blender, NegativeY) => (NegativeZ, Rotate180),
+            (FaceConvention::Blender, PositiveZ) => (NegativeY, Identity),
+            (FaceConvention::Blender, NegativeZ) => (PositiveY, Rotate180),
+        };
+        FaceMapping {
+            source,
+            orientation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use image::Rgba;
+
+    use super::*;
+
+    const SIZE: u32 = 4;
+
+    /// Direction through the center of pixel `(column, row)` of an OpenGL `face`, following the
+    /// `s`/`t` table of the OpenGL specification.
+    fn direction(face: CubeFace, column: u32, row: u32) -> Vec3 {
+        let s = 2.0 * (column as f32 + 0.5) / SIZE as f32 - 1.0;
+        let t = 2.0 * (row as f32 + 0.5) / SIZE as f32 - 1.0;
+        match face {
+            CubeFace::PositiveX => Vec3::new(1.0, -t, -s),
+            CubeFace::NegativeX => Vec3::new(-1.0, -t, s),
+            CubeFace::PositiveY => Vec3::new(s, 1.0, t),
+            CubeFace::NegativeY => Vec3::new(s, -1.0, -t),
+            CubeFace::PositiveZ => Vec3::new(s, -t, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-s, -t, -1.0),
+        }
+    }
+
+    /// Where the single marked pixel of `image` is.
+    fn marked_pixel(image: &RgbaImage) -> (u32, u32) {
+        let marked: Vec<_> = image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] == 255)
+            .map(|(column, row, _)| (column, row))
+            .collect();
+        assert_eq!(marked.len(), 1);
+        marked[0]
+    }
+
+    #[test]
+    fn every_convention_uses_each_source_once() {
+        for convention in FaceConvention::ALL {
+            let mut used = [false; 6];
+            for target in CubeFace::ALL {
+                let source = convention.mapping(target).source;
+                assert!(
+                    !used[source.index()],
+                    "{} uses {source:?} twice",
+                    convention.name()
+                );
+                used[source.index()] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn opengl_is_identity() {
+        for target in CubeFace::ALL {
+            let mapping = FaceConvention::OpenGl.mapping(target);
+            assert_eq!(mapping.source, target);
+            assert_eq!(mapping.orientation, FaceOrientation::Identity);
+        }
+    }
+
+    /// Samples every pixel of every OpenGL face through `convention` and checks that it reads the
+    /// source pixel pointing the same way, once `to_opengl` maps the pack's world onto OpenGL's.
+    fn assert_pixels_keep_their_direction(
+        convention: FaceConvention,
+        to_opengl: impl Fn(Vec3) -> Vec3,
+    ) {
+        for target in CubeFace::ALL {
+            let mapping = convention.mapping(target);
+            // Every source pixel stores its own position, so the oriented image tells which one a
+            // sample reads.
+            let source = RgbaImage::from_fn(SIZE, SIZE, |column, row| {
+                Rgba([column as u8, row as u8, 0, 255])
+            });
+            let oriented = mapping.orientation.apply(source);
+            for (column, row, pixel) in oriented.enumerate_pixels() {
+                let sampled = direction(target, column, row);
+                let (source_column, source_row) = (pixel[0] as u32, pixel[1] as u32);
+                let expected = to_opengl(direction(mapping.source, source_column, source_row));
+                assert!(
+                    sampled.abs_diff_eq(expected, 1e-6),
+                    "{} {target:?} ({column}, {row}) reads {:?} ({source_column}, {source_row}), \
+                     which points at {expected} instead of {sampled}",
+                    convention.name(),
+                    mapping.source,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unity_pixels_are_mirrored_along_y() {
+        assert_pixels_keep_their_direction(FaceConvention::Unity, |direction| {
+            Vec3::new(direction.x, -direction.y, direction.z)
+        });
+    }
+
+    #[test]
+    fn blender_pixels_keep_their_direction() {
+        // A Blender image lays its face out like OpenGL would, but in the Z-up world, so its
+        // pixels point along the Blender direction rotated onto Y-up.
+        assert_pixels_keep_their_direction(FaceConvention::Blender, |direction| {
+            Vec3::new(direction.x, direction.z, -direction.y)
+        });
+    }
+
+    #[test]
+    fn blender_horizon_meets_the_sky() {
+        // The top edge of Blender's front (-Y) image has to continue into the bottom edge of its
+        // sky (+Z) image, which both end up on the OpenGL +Z and +Y faces.
+        let front = FaceConvention::Blender.mapping(CubeFace::PositiveZ);
+        let sky = FaceConvention::Blender.mapping(CubeFace::PositiveY);
+        assert_eq!(front.source, CubeFace::NegativeY);
+        assert_eq!(sky.source, CubeFace::PositiveZ);
+
+        let mut image = RgbaImage::new(SIZE, SIZE);
+        image.put_pixel(2, 0, Rgba([255, 0, 0, 255]));
+        let on_front = marked_pixel(&front.orientation.apply(image));
+        let mut image = RgbaImage::new(SIZE, SIZE);
+        image.put_pixel(2, SIZE - 1, Rgba([255, 0, 0, 255]));
+        let on_sky = marked_pixel(&sky.orientation.apply(image));
+
+        assert_eq!(on_front, (2, 0));
+        assert_eq!(on_sky, (2, SIZE - 1));
+        let step = direction(CubeFace::PositiveZ, on_front.0, on_front.1)
+            .normalize()
+            .angle_between(direction(CubeFace::PositiveY, on_sky.0, on_sky.1).normalize());
+        assert!(step < 0.5, "edges are {step} rad apart");
+    }
+}
@@ -0,0 +1,332 @@
+//! Analytic eclipses between spherical bodies.
+//!
+//! A point is shadowed by the part of the star's disc that an occluder's disc covers as seen from
+//! that point. Umbra and penumbra follow from the angular sizes of both discs, so no shadow maps
+//! are needed. The shaders evaluate the same formula per pixel in `include/eclipse.glsl`; the
+//! functions here answer the question for the UI.
+
+use std::f32::consts::PI;
+
+use glam::Vec3;
+
+use crate::body::BodyDefinition;
+
+/// Occluders considered per body in the shaders.
+pub const MAX_OCCLUDERS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EclipseKind {
+    /// Part of the star is hidden.
+    Partial,
+    /// The occluder covers the star completely: the umbra.
+    Total,
+    /// The occluder sits inside the star's disc and leaves a ring.
+    Annular,
+}
+
+impl EclipseKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Partial => "partial",
+            Self::Total => "total",
+            Self::Annular => "annular",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Eclipse {
+    pub occluder: String,
+    pub target: String,
+    /// Largest fraction of the star's disc hidden anywhere on the target.
+    pub fraction: f32,
+    pub kind: EclipseKind,
+}
+
+/// Fraction of a disc of angular radius `light` hidden by a disc of angular radius `occluder`
+/// whose center is `separation` away. All angles in radians.
+pub fn disc_overlap_fraction(light: f32, occluder: f32, separation: f32) -> f32 {
+    if light <= 0.0 {
+        return 0.0;
+    }
+    if separation >= light + occluder {
+        return 0.0;
+    }
+    if separation <= (light - occluder).abs() {
+        return if occluder >= light {
+            1.0
+        } else {
+            (occluder / light).powi(2)
+        };
+    }
+    // Lens-shaped intersection of two circles.
+    let (a, b, d) = (light, occluder, separation);
+    let alpha = ((d * d + a * a - b * b) / (2.0 * d * a))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let beta = ((d * d + b * b - a * a) / (2.0 * d * b))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let kite = ((-d + a + b) * (d + a - b) * (d - a + b) * (d + a + b))
+        .max(0.0)
+        .sqrt();
+    let area = a * a * alpha + b * b * beta - 0.5 * kite;
+    (area / (PI * a * a)).clamp(0.0, 1.0)
+}
+
+/// Angular radius of a sphere of `radius` seen from `distance`.
+fn angular_radius(radius: f32, distance: f32) -> f32 {
+    if distance <= radius {
+        PI / 2.0
+    } else {
+        (radius / distance).asin()
+    }
+}
+
+/// Fraction of the star at `light_center` hidden from `point` by the occluding sphere.
+pub fn occluded_fraction(
+    point: Vec3,
+    light_center: Vec3,
+    light_radius: f32,
+    occluder_center: Vec3,
+    occluder_radius: f32,
+) -> f32 {
+    let to_light = light_center - point;
+    let to_occluder = occluder_center - point;
+    // Only something between the point and the star can block it.
+    if to_occluder.length() >= to_light.length() || to_occluder.dot(to_light) <= 0.0 {
+        return 0.0;
+    }
+    let separation = to_light.angle_between(to_occluder);
+    disc_overlap_fraction(
+        angular_radius(light_radius, to_light.length()),
+        angular_radius(occluder_radius, to_occluder.length()),
+        separation,
+    )
+}
+
+/// Point on `target` closest to the shadow axis running from the star through the occluder.
+/// That is where the shadow is deepest.
+fn deepest_point(light: Vec3, occluder: Vec3, target: Vec3, target_radius: f32) -> Vec3 {
+    let axis = (occluder - light).normalize_or_zero();
+    let on_axis = light + axis * (target - light).dot(axis);
+    let offset = on_axis - target;
+    target + offset.clamp_length_max(target_radius)
+}
+
+/// Eclipse of `target` by `occluder` under the star `light`, if any part of `target` is shadowed.
+pub fn eclipse_between(
+    light: &BodyDefinition,
+    occluder: &BodyDefinition,
+    target: &BodyDefinition,
+) -> Option<Eclipse> {
+    let point = deepest_point(
+        light.position,
+        occluder.position,
+        target.position,
+        target.radius,
+    );
+    let fraction = occluded_fraction(
+        point,
+        light.position,
+        light.radius,
+        occluder.position,
+        occluder.radius,
+    );
+    if fraction <= 0.0 {
+        return None;
+    }
+    let light_size = angular_radius(light.radius, light.position.distance(point));
+    let occluder_size = angular_radius(occluder.radius, occluder.position.distance(point));
+    let kind = if fraction >= 1.0 {
+        EclipseKind::Total
+    } else if occluder_size < light_size
+        && (light.position - point).angle_between(occluder.position - point)
+            <= light_size - occluder_size
+    {
+        EclipseKind::Annular
+    } else {
+        EclipseKind::Partial
+    };
+    Some(Eclipse {
+        occluder: occluder.name.clone(),
+        target: target.name.clone(),
+        fraction,
+        kind,
+    })
+}
+
+/// Every eclipse currently happening among `bodies`, lit by the first emissive body.
+pub fn find_eclipses<'a>(bodies: impl IntoIterator<Item = &'a BodyDefinition>) -> Vec<Eclipse> {
+    let bodies: Vec<_> = bodies.into_iter().collect();
+    let Some(light) = bodies.iter().find(|body| body.emissive) else {
+        return Vec::new();
+    };
+    let mut eclipses = Vec::new();
+    for &occluder in bodies.iter().filter(|body| !body.emissive) {
+        for &target in bodies.iter().filter(|body| !body.emissive) {
+            if std::ptr::eq(occluder, target) {
+                continue;
+            }
+            eclipses.extend(eclipse_between(light, occluder, target));
+        }
+    }
+    eclipses
+}
+
+/// The bodies most likely to shadow `target`: the nearest non-emissive others, at most
+/// [`MAX_OCCLUDERS`].
+pub fn nearest_occluders<'a>(
+    bodies: impl Iterator<Item = &'a BodyDefinition>,
+    target: &BodyDefinition,
+) -> Vec<&'a BodyDefinition> {
+    let mut candidates: Vec<_> = bodies
+        .filter(|body| !body.emissive && body.name != target.name)
+        .collect();
+    candidates.sort_by(|a, b| {
+        let da = a.position.distance_squared(target.position);
+        let db = b.position.distance_squared(target.position);
+        da.total_cmp(&db)
+    });
+    candidates.truncate(MAX_OCCLUDERS);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(name: &str, radius: f32, x: f32, emissive: bool) -> BodyDefinition {
+        BodyDefinition {
+            name: name.to_string(),
+            radius,
+            position: Vec3::new(x, 0.0, 0.0),
+            emissive,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn disc_overlap_without_contact_is_zero() {
+        assert_eq!(disc_overlap_fraction(1.0, 1.0, 2.5), 0.0);
+        assert_eq!(disc_overlap_fraction(1.0, 0.5, 1.5), 0.0);
+        assert_eq!(disc_overlap_fraction(0.0, 1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn disc_overlap_of_crossing_discs_is_the_lens_area() {
+        // Two unit discs one radius apart overlap in 2·acos(1/2) - √3/2.
+        let lens = 2.0 * 0.5f32.acos() - 3.0f32.sqrt() / 2.0;
+        let fraction = disc_overlap_fraction(1.0, 1.0, 1.0);
+        assert!((fraction - lens / PI).abs() < 1e-5, "{fraction}");
+
+        // The partial phase runs smoothly from first contact to the start of totality.
+        let mut last = 0.0;
+        for step in (0..=100).rev() {
+            let separation = 2.0 * step as f32 / 100.0 + 0.5;
+            let fraction = disc_overlap_fraction(1.0, 1.5, separation);
+            assert!(fraction >= last - 1e-6 && fraction - last < 0.05);
+            last = fraction;
+        }
+        assert!(disc_overlap_fraction(1.0, 1.5, 2.49) < 0.01);
+        assert!(disc_overlap_fraction(1.0, 1.5, 0.51) > 0.99);
+    }
+
+    #[test]
+    fn disc_overlap_inside_the_light_is_total_or_annular() {
+        assert_eq!(disc_overlap_fraction(1.0, 1.0, 0.0), 1.0);
+        assert_eq!(disc_overlap_fraction(1.0, 2.0, 0.5), 1.0);
+        // A smaller occluder anywhere inside the disc hides its own area.
+        assert!((disc_overlap_fraction(1.0, 0.5, 0.0) - 0.25).abs() < 1e-6);
+        assert!((disc_overlap_fraction(1.0, 0.5, 0.4) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn occluded_fraction_needs_the_occluder_in_between() {
+        let light = Vec3::new(-100.0, 0.0, 0.0);
+        let point = Vec3::ZERO;
+        // On the axis and close enough to cover the star.
+        assert_eq!(
+            occluded_fraction(point, light, 1.0, Vec3::new(-2.0, 0.0, 0.0), 0.5),
+            1.0
+        );
+        // Behind the point, and beyond the star.
+        assert_eq!(
+            occluded_fraction(point, light, 1.0, Vec3::new(2.0, 0.0, 0.0), 0.5),
+            0.0
+        );
+        assert_eq!(
+            occluded_fraction(point, light, 1.0, Vec3::new(-200.0, 0.0, 0.0), 50.0),
+            0.0
+        );
+        // Off to the side of the star's disc.
+        assert_eq!(
+            occluded_fraction(point, light, 1.0, Vec3::new(-50.0, 10.0, 0.0), 1.0),
+            0.0
+        );
+        // Grazing the edge of the star's disc: the penumbra.
+        let fraction = occluded_fraction(point, light, 1.0, Vec3::new(-50.0, 0.75, 0.0), 0.5);
+        assert!(fraction > 0.0 && fraction < 1.0, "{fraction}");
+    }
+
+    #[test]
+    fn eclipse_kind_follows_the_disc_sizes() {
+        let sun = body("Sun", 10.0, 0.0, true);
+        let earth = body("Earth", 1.0, 1000.0, false);
+
+        let close = body("Moon", 0.27, 997.0, false);
+        let total = eclipse_between(&sun, &close, &earth).expect("total eclipse");
+        assert_eq!(total.kind, EclipseKind::Total);
+        assert_eq!(total.fraction, 1.0);
+        assert_eq!(
+            (total.occluder.as_str(), total.target.as_str()),
+            ("Moon", "Earth")
+        );
+
+        // Far from Earth the same moon looks smaller than the Sun and leaves a ring.
+        let far = body("Moon", 0.27, 900.0, false);
+        let annular = eclipse_between(&sun, &far, &earth).expect("annular eclipse");
+        assert_eq!(annular.kind, EclipseKind::Annular);
+        // The ring leaves the Moon's share of the Sun's disc, seen from the shadow axis.
+        let expected = ((0.27f32 / 100.0).asin() / (10.0f32 / 1000.0).asin()).powi(2);
+        assert!((annular.fraction - expected).abs() < 1e-4, "{annular:?}");
+
+        // With the shadow axis passing just above Earth, its edge sees the Sun's disc grazed.
+        let mut aside = body("Moon", 0.27, 997.0, false);
+        aside.position.y = 1.27;
+        let partial = eclipse_between(&sun, &aside, &earth).expect("partial eclipse");
+        assert_eq!(partial.kind, EclipseKind::Partial);
+        assert!(partial.fraction > 0.0 && partial.fraction < 1.0);
+
+        aside.position.y = 5.0;
+        assert_eq!(eclipse_between(&sun, &aside, &earth), None);
+    }
+
+    #[test]
+    fn finds_solar_and_lunar_eclipses() {
+        let sun = body("Sun", 10.0, 0.0, true);
+        let earth = body("Earth", 1.0, 1000.0, false);
+
+        // New moon on the axis: the Moon shadows Earth, never the other way round.
+        let new_moon = body("Moon", 0.27, 997.0, false);
+        let eclipses = find_eclipses([&sun, &earth, &new_moon]);
+        assert_eq!(eclipses.len(), 1, "{eclipses:?}");
+        assert_eq!(eclipses[0].occluder, "Moon");
+        assert_eq!(eclipses[0].target, "Earth");
+        assert_eq!(eclipses[0].kind, EclipseKind::Total);
+
+        // Full moon behind Earth.
+        let full_moon = body("Moon", 0.27, 1003.0, false);
+        let eclipses = find_eclipses([&sun, &earth, &full_moon]);
+        assert_eq!(eclipses.len(), 1, "{eclipses:?}");
+        assert_eq!(eclipses[0].occluder, "Earth");
+        assert_eq!(eclipses[0].target, "Moon");
+
+        // A quarter moon is out of line with both.
+        let mut quarter_moon = body("Moon", 0.27, 1000.0, false);
+        quarter_moon.position.z = 3.0;
+        assert!(find_eclipses([&sun, &earth, &quarter_moon]).is_empty());
+        // Nothing is lit without a star.
+        assert!(find_eclipses([&earth, &new_moon]).is_empty());
+    }
+}
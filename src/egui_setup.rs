@@ -3,6 +3,7 @@ use std::{collections::HashSet, time::Instant};
 use anyhow::Result;
 
 use egui::{TextWrapMode, ViewportId};
+use glam::{DVec3, Mat4, Vec2, Vec3};
 use glium::{
     Surface,
     glutin::surface::WindowSurface,
@@ -15,19 +16,101 @@ use glium::{
         window::{CursorGrabMode, Window, WindowId},
     },
 };
-use log::debug;
+use log::{debug, error};
 
-use crate::{camera::Camera, skybox::Skybox};
+use crate::{
+    assets::AssetLoader,
+    asteroids::{ASTEROID_PROGRAM, AsteroidBelts, kuiper_belt, main_belt},
+    atmosphere::{ATMOSPHERE_PROGRAM, Atmosphere},
+    barnes_hut::ForceComparison,
+    camera::Camera,
+    celestial::{
+        cartesian_to_spherical, ecliptic_to_equatorial, ecliptic_to_scene, equatorial_to_galactic,
+        scene_to_ecliptic,
+    },
+    clock::{SimulationClock, format_date},
+    comet::Comet,
+    cubemap::FaceConvention,
+    emitter::{ParticleSystem, Placement, impact, solar_wind},
+    frustum::{CullStats, Frustum},
+    gravity::GravityField,
+    hdr::{
+        BLOOM_DOWNSAMPLE_PROGRAM, BLOOM_PREFILTER_PROGRAM, BLOOM_UPSAMPLE_PROGRAM, HdrPipeline,
+        HdrSettings, TONEMAP_PROGRAM, ToneMapping,
+    },
+    hot_reload::FileWatcher,
+    kepler::GM_SUN,
+    lod::LodSelector,
+    maneuver::{ManeuverNode, hohmann_transfer},
+    nbody::{CollisionOutcome, ForceMethod, NBodySimulation},
+    overlays::{OVERLAY_PROGRAM, OverlaySettings, Overlays, project_to_screen},
+    particles::{PARTICLE_PROGRAM, ParticleInstance, ParticleRenderer},
+    planet::{BODY_POINT_PROGRAM, IMPOSTOR_PROGRAM, PLANET_PROGRAM, Planets},
+    porkchop::Porkchop,
+    rings::RINGS_PROGRAM,
+    shaders::ShaderLibrary,
+    skybox::{SKYBOX_PROGRAM, Skybox},
+    spacecraft::{CameraMode, KM_PER_AU, SECONDS_PER_DAY, SHIP_PROGRAM, ShipControls, Spacecraft},
+    starfield::{STARFIELD_PROGRAM, Starfield},
+    sun::{CORONA_PROGRAM, LENS_FLARE_PROGRAM, STAR_PROGRAM, SunEffects, SunSettings},
+    system::{AU, comets, solar_system},
+    trajectory::{
+        EventKind, OrbitTarget, PatchedTrajectory, TrajectoryEvent, TrajectoryRenderer, conic_path,
+        orbit_path, patched_conics,
+    },
+};
 
 pub struct GliumAttributes {
     pub window: Window,
     pub display: glium::Display<WindowSurface>,
 }
 
+/// Colors of predicted paths, picked by the index of the central body. sRGB with alpha.
+const TRAJECTORY_COLORS: [[f32; 4]; 6] = [
+    [1.0, 0.85, 0.3, 0.8],
+    [0.3, 1.0, 0.5, 0.8],
+    [0.4, 0.7, 1.0, 0.8],
+    [1.0, 0.45, 0.35, 0.8],
+    [0.85, 0.5, 1.0, 0.8],
+    [0.3, 0.95, 0.95, 0.8],
+];
+const TRANSFER_COLOR: [f32; 4] = [1.0, 0.5, 0.9, 0.9];
+const PLANET_ORBIT_COLOR: [f32; 4] = [0.6, 0.6, 0.7, 0.35];
+/// Gravitational parameter of the Earth in AU³/day², for masses in the collision log.
+const GM_EARTH: f64 = 8.887_692e-10;
+/// Comet orbits are cut off this many AU from the sun.
+const MAX_PREDICTED_RADIUS: f64 = 100.0;
+
 struct Settings {
     // Add any settings you want to manage here
     show_ui: bool,
     show_fps: bool,
+    /// Reload shaders and textures from disk when they change.
+    hot_reload: bool,
+    show_skybox: bool,
+    show_stars: bool,
+    star_size: f32,
+    show_atmospheres: bool,
+    show_asteroids: bool,
+    show_comets: bool,
+    /// Planetesimals in a new n-body sandbox.
+    nbody_count: usize,
+    show_collision_log: bool,
+    /// Index of the body the impact demo hits.
+    impact_target: usize,
+    /// Fly the spacecraft instead of the free camera.
+    pilot: bool,
+    ship_camera: CameraMode,
+    /// Index into the gravity field of the body the HUD reports against.
+    ship_target: usize,
+    show_transfer_planner: bool,
+    /// Object whose osculating orbit is drawn.
+    predicted_orbit: OrbitTarget,
+    /// Multiplies projected body sizes before picking a level of detail.
+    lod_bias: f32,
+    hdr: HdrSettings,
+    sun: SunSettings,
+    overlays: OverlaySettings,
 }
 
 pub struct App {
@@ -36,7 +119,37 @@ pub struct App {
     pub glium_attributes: GliumAttributes,
     delta_time: f32,
     last_frame: std::time::Instant,
+    /// Drives shader animations.
+    start_time: Instant,
     pub skybox: Skybox,
+    starfield: Option<Starfield>,
+    overlays: Option<Overlays>,
+    planets: Option<Planets>,
+    asteroids: Option<AsteroidBelts>,
+    clock: SimulationClock,
+    comets: Vec<Comet>,
+    particle_system: ParticleSystem,
+    gravity: GravityField,
+    spacecraft: Option<Spacecraft>,
+    /// Mouse movement since the last update, steering the spacecraft while piloting.
+    mouse_delta: Vec2,
+    particle_renderer: ParticleRenderer,
+    trajectories: TrajectoryRenderer,
+    /// Predicted path of the spacecraft, updated every frame.
+    ship_trajectory: PatchedTrajectory,
+    porkchop: Porkchop,
+    nbody: Option<NBodySimulation>,
+    /// Last timing and accuracy comparison of the n-body force methods.
+    force_comparison: Option<ForceComparison>,
+    /// Reused every frame to collect the particles to draw.
+    particle_instances: Vec<ParticleInstance>,
+    hdr: HdrPipeline,
+    /// Objects drawn and culled in the last frame.
+    cull_stats: CullStats,
+    sun_effects: SunEffects,
+    assets: AssetLoader,
+    shaders: ShaderLibrary,
+    watcher: FileWatcher,
     camera: Camera,
     keys_pressed: HashSet<PhysicalKey>,
 }
@@ -47,7 +160,41 @@ impl App {
 
         let egui_glium = &mut self.egui_glium;
         let window = &self.glium_attributes.window;
+        let size = window.inner_size();
+        let aspect_ratio = size.width as f32 / size.height.max(1) as f32;
+        let perspective = self.camera.get_perspective_matrix(aspect_ratio);
+        let sky_view_projection = perspective * self.camera.get_sky_view_matrix();
+        let view_projection = perspective * self.camera.get_view_matrix();
+        let mut show_date = None;
         egui_glium.run(window, |egui_ctx| {
+            if let Some(overlays) = &self.overlays {
+                overlays.paint_labels(egui_ctx, sky_view_projection, &self.settings.overlays);
+            }
+            let events = self
+                .ship_trajectory
+                .events
+                .iter()
+                .map(|event| (event.position, event_text(event, &self.gravity)));
+            let transfer = self.porkchop.selected.iter().flat_map(|transfer| {
+                [
+                    (
+                        transfer.departure_position,
+                        format!("{} at departure", transfer.from),
+                    ),
+                    (
+                        transfer.arrival_position,
+                        format!("{} at arrival", transfer.to),
+                    ),
+                ]
+            });
+            paint_markers(egui_ctx, view_projection, events.chain(transfer));
+            show_date = self
+                .porkchop
+                .show(egui_ctx, &mut self.settings.show_transfer_planner);
+            if let Some(nbody) = &self.nbody {
+                collision_log(egui_ctx, nbody, &mut self.settings.show_collision_log);
+            }
+
             if self.settings.show_fps {
                 egui::Area::new("fps_hud".into())
                     .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
@@ -60,10 +207,51 @@ impl App {
                             .inner_margin(egui::Margin::symmetric(8, 4))
                             .show(ui, |ui| {
                                 ui.monospace(format!("{:.0}", 1.0 / self.delta_time));
+                                ui.monospace(format!(
+                                    "{} drawn / {} culled",
+                                    self.cull_stats.drawn, self.cull_stats.culled
+                                ));
                             });
                     });
             }
 
+            if let Some(ship) = &self.spacecraft
+                && self.settings.pilot
+            {
+                egui::Area::new("ship_hud".into())
+                    .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+                    .order(egui::Order::Foreground)
+                    .show(egui_ctx, |ui| {
+                        ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                        ship_hud(ui, ship, &self.gravity, self.settings.ship_target);
+                    });
+            }
+
+            for (name, error) in self.shaders.errors() {
+                egui::Window::new(format!("Shader error: {name}"))
+                    .default_width(500.0)
+                    .show(egui_ctx, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.monospace(error);
+                        });
+                    });
+            }
+
+            if self.assets.is_loading() {
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() / 2.0 - 40.0);
+                        ui.heading("Loading assets...");
+                        let (completed, requested) = self.assets.counts();
+                        ui.add(
+                            egui::ProgressBar::new(self.assets.progress())
+                                .desired_width(300.0)
+                                .text(format!("{completed} / {requested}")),
+                        );
+                    });
+                });
+            }
+
             egui::SidePanel::left("general_ui").show_animated(
                 egui_ctx,
                 self.settings.show_ui,
@@ -71,6 +259,268 @@ impl App {
                     ui.heading("General Settings");
                     ui.separator();
                     ui.checkbox(&mut self.settings.show_fps, "Show FPS");
+                    ui.collapsing("Sky overlays", |ui| {
+                        let overlays = &mut self.settings.overlays;
+                        ui.checkbox(&mut overlays.constellations, "Constellations");
+                        ui.checkbox(&mut overlays.equatorial_grid, "Equatorial grid (RA/Dec)");
+                        ui.checkbox(&mut overlays.ecliptic_grid, "Ecliptic grid");
+                        ui.checkbox(&mut overlays.galactic_grid, "Galactic grid");
+                        ui.checkbox(&mut overlays.ecliptic_plane, "Ecliptic plane");
+                        ui.checkbox(&mut overlays.labels, "Labels");
+                        ui.separator();
+                        view_direction_ui(ui, self.camera.get_forward());
+                    });
+                    ui.checkbox(&mut self.settings.show_skybox, "Show galaxy skybox");
+                    ui.checkbox(&mut self.settings.show_stars, "Show catalog stars");
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.star_size, 0.25..=4.0)
+                            .text("Star size"),
+                    );
+                    ui.collapsing("Exposure & bloom", |ui| {
+                        let hdr = &mut self.settings.hdr;
+                        ui.add(
+                            egui::Slider::new(&mut hdr.exposure, 0.05..=20.0)
+                                .logarithmic(true)
+                                .text("Exposure"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut hdr.bloom_strength, 0.0..=1.0)
+                                .text("Bloom strength"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut hdr.bloom_threshold, 0.1..=10.0)
+                                .logarithmic(true)
+                                .text("Bloom threshold"),
+                        );
+                        egui::ComboBox::from_label("Tone mapping")
+                            .selected_text(hdr.tone_mapping.name())
+                            .show_ui(ui, |ui| {
+                                for option in ToneMapping::ALL {
+                                    ui.selectable_value(
+                                        &mut hdr.tone_mapping,
+                                        option,
+                                        option.name(),
+                                    );
+                                }
+                            });
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.lod_bias, 0.25..=4.0)
+                            .logarithmic(true)
+                            .text("Detail bias"),
+                    )
+                    .on_hover_text("Higher values keep detailed meshes further away");
+                    ui.collapsing("Sun", |ui| {
+                        let sun = &mut self.settings.sun;
+                        ui.checkbox(&mut sun.corona, "Corona");
+                        ui.add(
+                            egui::Slider::new(&mut sun.corona_intensity, 0.0..=5.0)
+                                .text("Corona intensity"),
+                        );
+                        ui.checkbox(&mut sun.lens_flare, "Lens flare");
+                        ui.add(
+                            egui::Slider::new(&mut sun.flare_intensity, 0.0..=2.0)
+                                .text("Flare intensity"),
+                        );
+                    });
+                    ui.collapsing("Eclipses", |ui| {
+                        let eclipses = self
+                            .planets
+                            .as_ref()
+                            .map(Planets::eclipses)
+                            .unwrap_or_default();
+                        if eclipses.is_empty() {
+                            ui.label("No eclipses right now");
+                        }
+                        for eclipse in eclipses {
+                            ui.monospace(format!(
+                                "{} shadows {}: {:.0}% ({})",
+                                eclipse.occluder,
+                                eclipse.target,
+                                eclipse.fraction * 100.0,
+                                eclipse.kind.name()
+                            ));
+                        }
+                    });
+                    ui.collapsing("Time", |ui| {
+                        ui.checkbox(&mut self.clock.paused, "Paused");
+                        ui.add(
+                            egui::Slider::new(&mut self.clock.days_per_second, 0.01..=1000.0)
+                                .logarithmic(true)
+                                .text("Days per second"),
+                        );
+                        ui.monospace(format!(
+                            "Day {:.1} since J2000 ({})",
+                            self.clock.days,
+                            format_date(self.clock.days)
+                        ));
+                    });
+                    ui.collapsing("Asteroids", |ui| {
+                        ui.checkbox(&mut self.settings.show_asteroids, "Show asteroids");
+                        if let Some(asteroids) = &mut self.asteroids {
+                            let mut regenerate = false;
+                            for belt in &mut asteroids.belts {
+                                let response = ui.add(
+                                    egui::Slider::new(&mut belt.definition.count, 0..=500_000)
+                                        .logarithmic(true)
+                                        .text(belt.definition.name),
+                                );
+                                regenerate |= response.drag_stopped()
+                                    || (response.changed() && !response.dragged());
+                            }
+                            if regenerate
+                                && let Err(e) = asteroids.regenerate(&self.glium_attributes.display)
+                            {
+                                error!("Failed to regenerate asteroid belts: {e:#}");
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let name = |target: OrbitTarget| match target {
+                            OrbitTarget::None => "None".to_owned(),
+                            OrbitTarget::Spacecraft => "Spacecraft".to_owned(),
+                            OrbitTarget::Comet(index) => self.comets[index].definition.name.clone(),
+                        };
+                        egui::ComboBox::from_label("Predicted orbit")
+                            .selected_text(name(self.settings.predicted_orbit))
+                            .show_ui(ui, |ui| {
+                                let targets = [OrbitTarget::None, OrbitTarget::Spacecraft]
+                                    .into_iter()
+                                    .chain((0..self.comets.len()).map(OrbitTarget::Comet));
+                                for target in targets {
+                                    ui.selectable_value(
+                                        &mut self.settings.predicted_orbit,
+                                        target,
+                                        name(target),
+                                    );
+                                }
+                            });
+                    });
+                    ui.checkbox(&mut self.settings.show_transfer_planner, "Transfer planner");
+                    ui.collapsing("Comets", |ui| {
+                        ui.checkbox(&mut self.settings.show_comets, "Show comets");
+                        for comet in &self.comets {
+                            ui.monospace(format!(
+                                "{}: {:.2} AU, activity {:.2}, {} particles",
+                                comet.definition.name,
+                                comet.distance_to_sun(),
+                                comet.activity(),
+                                comet.particle_count()
+                            ));
+                        }
+                    });
+                    ui.collapsing("Spacecraft", |ui| {
+                        ui.checkbox(&mut self.settings.pilot, "Pilot spacecraft (F3)");
+                        egui::ComboBox::from_label("View (F4)")
+                            .selected_text(self.settings.ship_camera.name())
+                            .show_ui(ui, |ui| {
+                                for option in CameraMode::ALL {
+                                    ui.selectable_value(
+                                        &mut self.settings.ship_camera,
+                                        option,
+                                        option.name(),
+                                    );
+                                }
+                            });
+                        let attractors = &self.gravity.attractors;
+                        if let Some(target) = attractors.get(self.settings.ship_target) {
+                            egui::ComboBox::from_label("Target")
+                                .selected_text(&target.name)
+                                .show_ui(ui, |ui| {
+                                    for (index, body) in attractors.iter().enumerate() {
+                                        ui.selectable_value(
+                                            &mut self.settings.ship_target,
+                                            index,
+                                            &body.name,
+                                        );
+                                    }
+                                });
+                        }
+                        if let Some(ship) = &mut self.spacecraft {
+                            ui.add(
+                                egui::Slider::new(&mut ship.thrust, 1e-5..=1e-1)
+                                    .logarithmic(true)
+                                    .text("Thrust (AU/day²)"),
+                            );
+                            ui.checkbox(&mut ship.stability_assist, "Stability assist");
+                            ui.separator();
+                            maneuver_ui(
+                                ui,
+                                ship,
+                                &self.gravity,
+                                self.settings.ship_target,
+                                &self.ship_trajectory,
+                            );
+                            ui.separator();
+                            if ui.button("Reset to orbit around target").clicked() {
+                                let target = self.settings.ship_target;
+                                let altitude = self.gravity.attractors[target].radius;
+                                match Spacecraft::in_orbit(
+                                    &self.glium_attributes.display,
+                                    &self.gravity,
+                                    target,
+                                    altitude,
+                                ) {
+                                    Ok(new_ship) => *ship = new_ship,
+                                    Err(e) => error!("Failed to reset spacecraft: {e:#}"),
+                                }
+                            }
+                        }
+                        ui.label(
+                            "W/S thrust, A/D/Space/Ctrl translate, mouse and Q/E rotate, \
+                             Shift boost",
+                        );
+                    });
+                    ui.collapsing("N-body sandbox", |ui| {
+                        nbody_ui(
+                            ui,
+                            &mut self.nbody,
+                            &mut self.settings.nbody_count,
+                            &mut self.settings.show_collision_log,
+                            &mut self.force_comparison,
+                        );
+                    });
+                    ui.collapsing("Particles", |ui| {
+                        ui.label(format!(
+                            "{} particles",
+                            self.particle_system.particle_count()
+                        ));
+                        for emitter in &mut self.particle_system.emitters {
+                            if emitter.definition.rate > 0.0 {
+                                ui.checkbox(&mut emitter.enabled, &emitter.definition.name);
+                            }
+                        }
+                        if let Some(planets) = &self.planets {
+                            impact_ui(
+                                ui,
+                                planets,
+                                &mut self.settings.impact_target,
+                                self.camera.get_position(),
+                                &mut self.particle_system,
+                            );
+                        }
+                    });
+                    ui.collapsing("Atmospheres", |ui| {
+                        ui.checkbox(&mut self.settings.show_atmospheres, "Show atmospheres");
+                        if let Some(planets) = &mut self.planets {
+                            for (name, atmosphere) in planets.atmospheres_mut() {
+                                ui.collapsing(name, |ui| atmosphere_ui(ui, atmosphere));
+                            }
+                        }
+                    });
+                    let mut convention = self.skybox.requested_convention();
+                    egui::ComboBox::from_label("Skybox faces")
+                        .selected_text(convention.name())
+                        .show_ui(ui, |ui| {
+                            for option in FaceConvention::ALL {
+                                ui.selectable_value(&mut convention, option, option.name());
+                            }
+                        });
+                    if convention != self.skybox.requested_convention() {
+                        self.skybox.request_faces(&mut self.assets, convention);
+                    }
+                    ui.checkbox(&mut self.settings.hot_reload, "Hot reload assets")
+                        .on_hover_text("Reload shaders and textures when they change on disk");
                     ui.label("Press ESC to toggle this menu");
                     ui.label("Press F2 to toggle FPS display");
                     ui.separator();
@@ -83,17 +533,165 @@ impl App {
                 },
             );
         });
+        if let Some(days) = show_date {
+            self.jump_to(days);
+        }
     }
+
+    /// Moves the clock to `days`, carrying the spacecraft along with the body it is near so that
+    /// it keeps its orbit.
+    fn jump_to(&mut self, days: f64) {
+        if let Some(ship) = &mut self.spacecraft {
+            let flight = &mut ship.flight;
+            if let Some(index) = self.gravity.dominant(flight.position, self.gravity.days) {
+                let (from, from_velocity) = self.gravity.state(index, self.gravity.days);
+                let (to, to_velocity) = self.gravity.state(index, days);
+                flight.position += to - from;
+                flight.velocity += to_velocity - from_velocity;
+            }
+        }
+        self.clock.days = days;
+        self.gravity.set_time(days);
+        if let Some(planets) = &mut self.planets {
+            planets.move_bodies(&self.gravity);
+        }
+    }
+
     pub fn new(event_loop: &EventLoop<()>, glium_attributes: GliumAttributes) -> Self {
         let dist = 10_000.0;
         let fov = 90.0;
-        let camera = Camera::new(fov, dist);
-        let skybox = Skybox::init(&glium_attributes.display, dist);
-        let keys_pressed: HashSet<PhysicalKey> = std::collections::HashSet::new();
-        let settings = Settings {
+        let mut camera = Camera::new(fov, dist);
+        let mut settings = Settings {
             show_ui: false,
             show_fps: true,
+            hot_reload: cfg!(debug_assertions),
+            show_skybox: true,
+            show_stars: true,
+            star_size: 1.0,
+            show_atmospheres: true,
+            show_asteroids: true,
+            show_comets: true,
+            nbody_count: 200,
+            show_collision_log: false,
+            impact_target: 0,
+            pilot: false,
+            ship_camera: CameraMode::Chase,
+            ship_target: 0,
+            show_transfer_planner: false,
+            predicted_orbit: OrbitTarget::Spacecraft,
+            lod_bias: 1.0,
+            hdr: HdrSettings::default(),
+            sun: SunSettings::default(),
+            overlays: OverlaySettings {
+                constellations: false,
+                equatorial_grid: false,
+                ecliptic_grid: false,
+                galactic_grid: false,
+                ecliptic_plane: false,
+                labels: true,
+            },
         };
+        let mut assets = AssetLoader::new();
+        let mut shaders = ShaderLibrary::new(settings.hot_reload);
+        shaders
+            .load(&glium_attributes.display, "skybox", SKYBOX_PROGRAM)
+            .unwrap();
+        shaders
+            .load(&glium_attributes.display, "stars", STARFIELD_PROGRAM)
+            .unwrap();
+        shaders
+            .load(&glium_attributes.display, "overlay", OVERLAY_PROGRAM)
+            .unwrap();
+        shaders
+            .load(&glium_attributes.display, "planet", PLANET_PROGRAM)
+            .unwrap();
+        shaders
+            .load(&glium_attributes.display, "rings", RINGS_PROGRAM)
+            .unwrap();
+        shaders
+            .load(&glium_attributes.display, "atmosphere", ATMOSPHERE_PROGRAM)
+            .unwrap();
+        for (name, desc) in [
+            ("bloom_prefilter", BLOOM_PREFILTER_PROGRAM),
+            ("bloom_downsample", BLOOM_DOWNSAMPLE_PROGRAM),
+            ("bloom_upsample", BLOOM_UPSAMPLE_PROGRAM),
+            ("tonemap", TONEMAP_PROGRAM),
+            ("star", STAR_PROGRAM),
+            ("impostor", IMPOSTOR_PROGRAM),
+            ("body_point", BODY_POINT_PROGRAM),
+            ("corona", CORONA_PROGRAM),
+            ("lens_flare", LENS_FLARE_PROGRAM),
+            ("asteroid", ASTEROID_PROGRAM),
+            ("particle", PARTICLE_PROGRAM),
+            ("ship", SHIP_PROGRAM),
+        ] {
+            shaders.load(&glium_attributes.display, name, desc).unwrap();
+        }
+        let hdr = HdrPipeline::new(&glium_attributes.display).unwrap();
+        let sun_effects = SunEffects::new(&glium_attributes.display).unwrap();
+        let particle_renderer = ParticleRenderer::new(&glium_attributes.display).unwrap();
+        let trajectories = TrajectoryRenderer::new(&glium_attributes.display).unwrap();
+        let comets = comets()
+            .into_iter()
+            .zip(1..)
+            .map(|(definition, seed)| Comet::new(definition, seed))
+            .collect();
+        let starfield = Starfield::init(&glium_attributes.display)
+            .inspect_err(|e| error!("Failed to load starfield: {e:#}"))
+            .ok();
+        let stars = starfield.as_ref().map_or(&[][..], |s| &s.stars);
+        let overlays = Overlays::init(&glium_attributes.display, stars)
+            .inspect_err(|e| error!("Failed to load sky overlays: {e:#}"))
+            .ok();
+        let skybox = Skybox::init(&glium_attributes.display, &mut assets);
+        let bodies = solar_system();
+        // Start just outside Earth's orbit, looking back at Earth with the Sun behind it.
+        let earth_position = bodies
+            .iter()
+            .find(|body| body.name == "Earth")
+            .map_or(Vec3::new(AU, 0.0, 0.0), |body| body.position);
+        let outward = earth_position.normalize_or(Vec3::X);
+        camera.look_at(
+            earth_position + outward * 8.0 + outward.cross(Vec3::Y) * 3.0 + Vec3::Y * 1.5,
+            earth_position,
+        );
+        let gravity = GravityField::new(&bodies);
+        let planets = Planets::init(&glium_attributes.display, &mut assets, &bodies)
+            .inspect_err(|e| error!("Failed to create planets: {e:#}"))
+            .ok();
+        let asteroids = AsteroidBelts::init(
+            &glium_attributes.display,
+            &mut assets,
+            vec![main_belt(), kuiper_belt()],
+        )
+        .inspect_err(|e| error!("Failed to create asteroid belts: {e:#}"))
+        .ok();
+        let mut particle_system = ParticleSystem::default();
+        if let Some(sun) = planets.as_ref().and_then(Planets::light_source) {
+            particle_system.add(solar_wind(&sun.name)).enabled = false;
+        }
+        let earth = gravity.find("Earth");
+        settings.ship_target = earth.unwrap_or_default();
+        let spacecraft = earth
+            .map(|earth| {
+                let altitude = gravity.attractors[earth].radius;
+                Spacecraft::in_orbit(&glium_attributes.display, &gravity, earth, altitude)
+            })
+            .transpose()
+            .inspect_err(|e| error!("Failed to create spacecraft: {e:#}"))
+            .ok()
+            .flatten();
+        let mut watcher = FileWatcher::new();
+        for path in shaders.watched_paths() {
+            watcher.watch(path);
+        }
+        for path in skybox.watched_paths() {
+            watcher.watch(path);
+        }
+        for path in planets.iter().flat_map(Planets::watched_paths) {
+            watcher.watch(path);
+        }
+        let keys_pressed: HashSet<PhysicalKey> = std::collections::HashSet::new();
         let egui_glium = egui_glium::EguiGlium::new(
             ViewportId::ROOT,
             &glium_attributes.display,
@@ -105,56 +703,703 @@ impl App {
             glium_attributes,
             delta_time: 0.0,
             last_frame: Instant::now(),
+            start_time: Instant::now(),
             skybox,
+            starfield,
+            overlays,
+            planets,
+            asteroids,
+            clock: SimulationClock::new(),
+            comets,
+            particle_system,
+            gravity,
+            spacecraft,
+            mouse_delta: Vec2::ZERO,
+            particle_renderer,
+            trajectories,
+            ship_trajectory: PatchedTrajectory::default(),
+            porkchop: Porkchop::new(),
+            nbody: None,
+            force_comparison: None,
+            particle_instances: Vec::new(),
+            hdr,
+            cull_stats: CullStats::default(),
+            sun_effects,
+            assets,
+            shaders,
+            watcher,
             settings,
             camera,
             keys_pressed,
         }
     }
-    fn redraw(&mut self) -> Result<()> {
-        let dist = self.camera.get_dist();
-        let fov = self.camera.get_fov();
+    /// Adds the paths to draw to the trajectory lines: the transfer picked in the planner with
+    /// the orbits of its planets, and the predicted path of the object picked in the settings,
+    /// which is either the spacecraft's patched conics colored by the body they go around or a
+    /// comet's osculating orbit around the sun.
+    fn collect_trajectories(&mut self) {
+        self.trajectories.clear();
+        if let Some(transfer) = &self.porkchop.selected {
+            for name in [transfer.from, transfer.to] {
+                if let Some(orbit) = self.porkchop.orbits.iter().find(|orbit| orbit.name == name) {
+                    let points = orbit_path(&orbit.elements, MAX_PREDICTED_RADIUS);
+                    self.trajectories.add_path(&points, PLANET_ORBIT_COLOR);
+                }
+            }
+            self.trajectories.add_path(&transfer.path(), TRANSFER_COLOR);
+        }
+        match self.settings.predicted_orbit {
+            OrbitTarget::None => {}
+            OrbitTarget::Spacecraft => {
+                for segment in &self.ship_trajectory.segments {
+                    let color = TRAJECTORY_COLORS[segment.body % TRAJECTORY_COLORS.len()];
+                    self.trajectories.add_path(&segment.points, color);
+                }
+            }
+            OrbitTarget::Comet(index) => {
+                if let Some(comet) = self.comets.get(index) {
+                    let points = conic_path(
+                        comet.position,
+                        comet.velocity,
+                        DVec3::ZERO,
+                        GM_SUN,
+                        MAX_PREDICTED_RADIUS,
+                    );
+                    self.trajectories.add_path(&points, TRAJECTORY_COLORS[0]);
+                }
+            }
+        }
+    }
 
-        let mut frame = self.glium_attributes.display.draw();
-        frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+    fn redraw(&mut self) -> Result<()> {
+        self.collect_trajectories();
+        let display = &self.glium_attributes.display;
+        let mut frame = display.draw();
         let (width, height) = frame.get_dimensions();
+        let mut target = self.hdr.scene_target(display, (width, height))?;
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
 
         let aspect_ratio = width as f32 / height as f32;
 
         let view = self.camera.get_view_matrix();
-        let perspective = glam::Mat4::perspective_rh(fov, aspect_ratio, 0.1, dist);
-        let skybox_uniforms = uniform! {
-            view: view.to_cols_array_2d(),
-            perspective: perspective.to_cols_array_2d(),
-            skybox: self.skybox.cubemap.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear),
-        };
+        let perspective = self.camera.get_perspective_matrix(aspect_ratio);
+        let frustum = Frustum::from_view_projection(perspective * view);
+        let pixel_angle = 2.0 * (0.5 * self.camera.get_fov_y()).tan() / height as f32;
+        let mut cull_stats = CullStats::default();
+        if let Some(planets) = &self.planets {
+            cull_stats += planets.draw(
+                &mut target,
+                &self.shaders,
+                view,
+                perspective,
+                self.camera.get_position(),
+                self.start_time.elapsed().as_secs_f32(),
+                &LodSelector {
+                    fov_y: self.camera.get_fov_y(),
+                    viewport_height: height as f32,
+                    bias: self.settings.lod_bias,
+                },
+                &frustum,
+            )?;
+        }
+        if let Some(asteroids) = &self.asteroids
+            && self.settings.show_asteroids
+        {
+            let (light_position, light_color) = self
+                .planets
+                .as_ref()
+                .map_or((Vec3::ZERO, [1.0; 3]), |planets| {
+                    (planets.light_position(), planets.light_color())
+                });
+            cull_stats += asteroids.draw(
+                &mut target,
+                self.shaders.get("asteroid"),
+                view,
+                perspective,
+                self.camera.get_position(),
+                light_position,
+                light_color,
+                pixel_angle,
+                self.start_time.elapsed().as_secs_f32(),
+                &frustum,
+            )?;
+        }
+
+        if let Some(ship) = &self.spacecraft
+            && !(self.settings.pilot && self.settings.ship_camera == CameraMode::Cockpit)
+        {
+            let (light_position, light_color) = self
+                .planets
+                .as_ref()
+                .map_or((Vec3::ZERO, [1.0; 3]), |planets| {
+                    (planets.light_position(), planets.light_color())
+                });
+            ship.draw(
+                &mut target,
+                self.shaders.get("ship"),
+                view,
+                perspective,
+                light_position,
+                light_color,
+            )?;
+        }
+
+        // The sky goes last so it only fills pixels nothing else has covered. Translation is
+        // stripped from the view so the camera can never leave it.
+        let sky_view = self.camera.get_sky_view_matrix();
+        if let Some(skybox_sampler) = self.skybox.sampled()
+            && self.settings.show_skybox
+        {
+            let skybox_uniforms = uniform! {
+                view: sky_view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                skybox: skybox_sampler,
+            };
+
+            let params = glium::DrawParameters {
+                depth: glium::Depth {
+                    test: glium::DepthTest::IfLessOrEqual,
+                    write: false,
+                    ..Default::default()
+                },
+                backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise, // Remove this comment to enable backface culling - Not drawing the back faces of the triangles
+                ..Default::default()
+            };
+
+            target.draw(
+                &self.skybox.vertex_buffer,
+                &self.skybox.index_buffer,
+                self.shaders.get("skybox"),
+                &skybox_uniforms,
+                &params,
+            )?;
+        }
 
-        let params = glium::DrawParameters {
-            depth: glium::Depth {
-                test: glium::DepthTest::IfLess,
-                write: true,
+        if let Some(starfield) = &self.starfield
+            && self.settings.show_stars
+        {
+            let uniforms = uniform! {
+                view: sky_view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                size_scale: self.settings.star_size,
+            };
+            let params = glium::DrawParameters {
+                depth: glium::Depth {
+                    test: glium::DepthTest::IfLessOrEqual,
+                    write: false,
+                    ..Default::default()
+                },
+                blend: glium::Blend {
+                    color: glium::BlendingFunction::Addition {
+                        source: glium::LinearBlendingFactor::One,
+                        destination: glium::LinearBlendingFactor::One,
+                    },
+                    ..Default::default()
+                },
                 ..Default::default()
-            },
-            backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise, // Remove this comment to enable backface culling - Not drawing the back faces of the triangles
-            ..Default::default()
-        };
+            };
+            target.draw(
+                &starfield.vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::Points),
+                self.shaders.get("stars"),
+                &uniforms,
+                &params,
+            )?;
+        }
 
-        frame.draw(
-            &self.skybox.vertex_buffer,
-            &self.skybox.index_buffer,
-            &self.skybox.cubemap_program,
-            &skybox_uniforms,
-            &params,
+        // Rings and atmospheres blend onto whatever is behind them, so they come after the sky.
+        if let Some(planets) = &self.planets {
+            cull_stats += planets.draw_rings(
+                &mut target,
+                self.shaders.get("rings"),
+                view,
+                perspective,
+                self.camera.get_position(),
+                &frustum,
+            )?;
+        }
+        if let Some(planets) = &self.planets
+            && self.settings.show_atmospheres
+        {
+            cull_stats += planets.draw_atmospheres(
+                &mut target,
+                self.shaders.get("atmosphere"),
+                view,
+                perspective,
+                self.camera.get_position(),
+                &frustum,
+            )?;
+        }
+        // Particles add light, so they go after everything opaque and the sky, in any order.
+        self.particle_instances.clear();
+        if self.settings.show_comets {
+            for comet in &self.comets {
+                cull_stats.record(comet.collect_particles(&frustum, &mut self.particle_instances));
+            }
+        }
+        if let Some(nbody) = &self.nbody {
+            nbody.collect_particles(&frustum, &mut self.particle_instances);
+        }
+        for emitter in &self.particle_system.emitters {
+            cull_stats.record(emitter.collect_particles(&frustum, &mut self.particle_instances));
+        }
+        if let Some(ship) = &self.spacecraft {
+            cull_stats.record(
+                ship.exhaust
+                    .collect_particles(&frustum, &mut self.particle_instances),
+            );
+        }
+        self.particle_renderer.draw(
+            display,
+            &mut target,
+            self.shaders.get("particle"),
+            view,
+            perspective,
+            pixel_angle,
+            &self.particle_instances,
         )?;
+        self.cull_stats = cull_stats;
+
+        if let Some(sun) = self.planets.as_ref().and_then(Planets::light_source) {
+            self.sun_effects.draw(
+                display,
+                &mut target,
+                self.shaders.get("corona"),
+                self.shaders.get("lens_flare"),
+                sun,
+                view,
+                perspective,
+                self.camera.get_position(),
+                self.start_time.elapsed().as_secs_f32(),
+                &self.settings.sun,
+            )?;
+        }
 
-        self.egui_glium
-            .paint(&self.glium_attributes.display, &mut frame);
+        self.trajectories.draw(
+            display,
+            &mut target,
+            self.shaders.get("overlay"),
+            view,
+            perspective,
+        )?;
+
+        if let Some(overlays) = &self.overlays {
+            overlays.draw(
+                &mut target,
+                self.shaders.get("overlay"),
+                view,
+                sky_view,
+                perspective,
+                &self.settings.overlays,
+            )?;
+        }
+
+        drop(target);
+        self.hdr
+            .resolve(display, &mut frame, &self.shaders, &self.settings.hdr)?;
+
+        self.egui_glium.paint(display, &mut frame);
         frame.finish()?;
 
         Ok(())
     }
 }
 
+/// Shows where the camera points in equatorial, ecliptic and galactic coordinates.
+fn view_direction_ui(ui: &mut egui::Ui, forward: Vec3) {
+    let ecliptic = scene_to_ecliptic(forward);
+    let equatorial = ecliptic_to_equatorial(ecliptic);
+    let galactic = equatorial_to_galactic(equatorial);
+
+    let (ra, dec) = cartesian_to_spherical(equatorial);
+    let ra_minutes = (ra.to_degrees() / 15.0 * 60.0).round() as u32 % (24 * 60);
+    let (lambda, beta) = cartesian_to_spherical(ecliptic);
+    let (l, b) = cartesian_to_spherical(galactic);
+
+    ui.label("View direction");
+    ui.monospace(format!(
+        "RA {:02}h {:02}m  Dec {:+.1}°",
+        ra_minutes / 60,
+        ra_minutes % 60,
+        dec.to_degrees()
+    ));
+    ui.monospace(format!(
+        "λ {:.1}°  β {:+.1}°",
+        lambda.to_degrees(),
+        beta.to_degrees()
+    ));
+    ui.monospace(format!(
+        "l {:.1}°  b {:+.1}°",
+        l.to_degrees(),
+        b.to_degrees()
+    ));
+}
+
+/// Maps the flight keys and mouse movement to spacecraft controls.
+fn ship_controls(keys: &HashSet<PhysicalKey>, mouse_delta: Vec2) -> ShipControls {
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        let pressed = |key| keys.contains(&PhysicalKey::Code(key)) as i32 as f32;
+        pressed(positive) - pressed(negative)
+    };
+    let mouse_sensitivity = 0.05;
+    ShipControls {
+        translation: Vec3::new(
+            axis(KeyCode::KeyD, KeyCode::KeyA),
+            axis(KeyCode::Space, KeyCode::ControlLeft),
+            axis(KeyCode::KeyW, KeyCode::KeyS),
+        ),
+        rotation: Vec3::new(
+            (-mouse_delta.y * mouse_sensitivity).clamp(-1.0, 1.0),
+            (-mouse_delta.x * mouse_sensitivity).clamp(-1.0, 1.0),
+            axis(KeyCode::KeyQ, KeyCode::KeyE),
+        ),
+        boost: keys.contains(&PhysicalKey::Code(KeyCode::ShiftLeft)),
+    }
+}
+
+/// Speed, the target body and the orbit around the dominant body, from the state vector relative
+/// to the moving bodies.
+fn ship_hud(ui: &mut egui::Ui, ship: &Spacecraft, gravity: &GravityField, target: usize) {
+    let km_per_s = KM_PER_AU / SECONDS_PER_DAY;
+    let flight = &ship.flight;
+    ui.monospace(format!(
+        "Speed {:.3} km/s",
+        flight.velocity.length() * km_per_s
+    ));
+    if let Some(body) = gravity.attractors.get(target) {
+        let (offset, relative) = flight.relative_to(gravity, target);
+        let range_rate = relative.dot(offset.normalize());
+        ui.monospace(format!(
+            "{}: {:.0} km, closing {:.3} km/s, relative {:.3} km/s",
+            body.name,
+            (offset.length() - body.radius) * KM_PER_AU,
+            -range_rate * km_per_s,
+            relative.length() * km_per_s,
+        ));
+    }
+    if let Some(landed) = flight.landed_on {
+        ui.monospace(format!("Landed on {}", gravity.attractors[landed].name));
+        return;
+    }
+    let Some((index, orbit)) = flight.orbit(gravity) else {
+        if let Some(index) = gravity.dominant(flight.position, gravity.days) {
+            let name = &gravity.attractors[index].name;
+            ui.monospace(format!("Moving straight towards or away from {name}"));
+        }
+        return;
+    };
+    let body = &gravity.attractors[index];
+    let altitude = |distance: f64| (distance - body.radius) * KM_PER_AU;
+    ui.monospace(format!(
+        "Orbiting {}: a {:.0} km, e {:.4}, i {:.2}°",
+        body.name,
+        orbit.semi_major_axis * KM_PER_AU,
+        orbit.eccentricity,
+        orbit.inclination.to_degrees()
+    ));
+    ui.monospace(format!(
+        "Periapsis {:.0} km, energy {:.3} km²/s²",
+        altitude(orbit.periapsis()),
+        orbit.specific_energy(body.gm) * km_per_s * km_per_s
+    ));
+    match (orbit.apoapsis(), orbit.period(body.gm)) {
+        (Some(apoapsis), Some(period)) => ui.monospace(format!(
+            "Apoapsis {:.0} km, period {:.2} days",
+            altitude(apoapsis),
+            period
+        )),
+        _ => ui.monospace(format!(
+            "Escape trajectory, v∞ {:.3} km/s",
+            orbit.hyperbolic_excess_velocity(body.gm).unwrap_or(0.0) * km_per_s
+        )),
+    };
+}
+
+/// Text describing a predicted event, with the time until it.
+fn event_text(event: &TrajectoryEvent, gravity: &GravityField) -> String {
+    let body = &gravity.attractors[event.body];
+    let what = match event.kind {
+        EventKind::Encounter { periapsis } => format!(
+            "{} encounter, periapsis {:.0} km",
+            body.name,
+            (periapsis - body.radius) * KM_PER_AU
+        ),
+        EventKind::Escape => format!("Leave {}", body.name),
+        EventKind::Impact => format!("Impact on {}", body.name),
+        EventKind::Maneuver => "Burn".to_owned(),
+    };
+    format!("{what} in {:.2} days", event.time)
+}
+
+/// Marks points of interest along predicted paths, given in heliocentric ecliptic AU with their
+/// labels, where they are on screen.
+fn paint_markers(
+    ctx: &egui::Context,
+    view_projection: Mat4,
+    markers: impl Iterator<Item = (DVec3, String)>,
+) {
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    let screen = ctx.screen_rect();
+    let color = egui::Color32::from_rgb(255, 230, 120);
+    for (point, label) in markers {
+        let point = ecliptic_to_scene(point) * AU;
+        let Some(position) = project_to_screen(view_projection, point, screen) else {
+            continue;
+        };
+        painter.circle_stroke(position, 5.0, egui::Stroke::new(1.5, color));
+        painter.text(
+            position + egui::vec2(8.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            label,
+            egui::FontId::proportional(13.0),
+            color,
+        );
+    }
+}
+
+/// Editor for the spacecraft's maneuver nodes, with delta-v in m/s, and the events they lead to.
+fn maneuver_ui(
+    ui: &mut egui::Ui,
+    ship: &mut Spacecraft,
+    gravity: &GravityField,
+    target: usize,
+    trajectory: &PatchedTrajectory,
+) {
+    let m_per_s = KM_PER_AU * 1000.0 / SECONDS_PER_DAY;
+    ui.label("Maneuver nodes");
+    let mut removed = None;
+    for (index, node) in ship.flight.maneuvers.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut node.time)
+                    .range(0.0..=f64::MAX)
+                    .speed(0.01)
+                    .prefix("T-")
+                    .suffix(" d"),
+            );
+            let mut delta_v = node.delta_v * m_per_s;
+            for (value, name) in [
+                (&mut delta_v.x, "pro "),
+                (&mut delta_v.y, "nrm "),
+                (&mut delta_v.z, "rad "),
+            ] {
+                ui.add(
+                    egui::DragValue::new(value)
+                        .speed(1.0)
+                        .prefix(name)
+                        .suffix(" m/s"),
+                );
+            }
+            node.delta_v = delta_v / m_per_s;
+            if ui.small_button("✖").clicked() {
+                removed = Some(index);
+            }
+        });
+    }
+    if let Some(index) = removed {
+        ship.flight.maneuvers.remove(index);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add node").clicked() {
+            ship.flight.maneuvers.push(ManeuverNode {
+                time: 1.0,
+                delta_v: DVec3::ZERO,
+            });
+        }
+        let hohmann = hohmann_transfer(gravity, ship.flight.position, ship.flight.velocity, target);
+        let name = &gravity.attractors[target].name;
+        let button = ui
+            .add_enabled(
+                hohmann.is_some(),
+                egui::Button::new(format!("Hohmann transfer to {name}")),
+            )
+            .on_disabled_hover_text("The target must orbit the body the craft is orbiting");
+        if let Some(transfer) = hohmann {
+            let button = button.on_hover_text(format!(
+                "Arrives after {:.1} days, {:.0} m/s to match {name}'s orbit",
+                transfer.duration,
+                transfer.arrival_delta_v * m_per_s
+            ));
+            if button.clicked() {
+                ship.flight.maneuvers.push(transfer.departure);
+            }
+        }
+    });
+    for event in &trajectory.events {
+        ui.monospace(event_text(event, gravity));
+    }
+}
+
+/// Starts and stops the n-body sandbox, picks how gravity is summed and what collisions do, and
+/// compares the force methods.
+fn nbody_ui(
+    ui: &mut egui::Ui,
+    nbody: &mut Option<NBodySimulation>,
+    count: &mut usize,
+    show_log: &mut bool,
+    comparison: &mut Option<ForceComparison>,
+) {
+    ui.add(
+        egui::Slider::new(count, 10..=20_000)
+            .logarithmic(true)
+            .text("Bodies"),
+    );
+    ui.horizontal(|ui| {
+        if ui.button("Start disk").clicked() {
+            *nbody = Some(NBodySimulation::protoplanetary_disk(*count, 0x5eed));
+        }
+        if ui.button("Start star cluster").clicked() {
+            *nbody = Some(NBodySimulation::star_cluster(*count, 0x5eed));
+        }
+        if nbody.is_some() && ui.button("Stop").clicked() {
+            *nbody = None;
+        }
+    });
+    let Some(simulation) = nbody else {
+        ui.label(
+            "Runs a disk of colliding planetesimals around the sun, or a star cluster above it, \
+             in simulation time",
+        );
+        return;
+    };
+    egui::ComboBox::from_label("Gravity")
+        .selected_text(simulation.force_method.name())
+        .show_ui(ui, |ui| {
+            for option in ForceMethod::ALL {
+                ui.selectable_value(&mut simulation.force_method, option, option.name());
+            }
+        });
+    if simulation.force_method == ForceMethod::BarnesHut {
+        ui.add(egui::Slider::new(&mut simulation.theta, 0.0..=1.5).text("Opening angle θ"));
+    }
+    if ui.button("Compare with direct summation").clicked() {
+        *comparison = Some(ForceComparison::measure(
+            &simulation.bodies,
+            simulation.theta,
+        ));
+    }
+    if let Some(comparison) = comparison {
+        ui.monospace(format!(
+            "{} bodies: direct {:.1} ms, tree (θ {:.2}) {:.1} ms",
+            comparison.bodies,
+            comparison.direct_time.as_secs_f64() * 1000.0,
+            comparison.theta,
+            comparison.tree_time.as_secs_f64() * 1000.0
+        ));
+        ui.monospace(format!(
+            "Relative error: RMS {:.2e}, max {:.2e}",
+            comparison.rms_error, comparison.max_error
+        ));
+    }
+    egui::ComboBox::from_label("Collisions")
+        .selected_text(simulation.outcome.name())
+        .show_ui(ui, |ui| {
+            for option in CollisionOutcome::ALL {
+                ui.selectable_value(&mut simulation.outcome, option, option.name());
+            }
+        });
+    ui.add(
+        egui::Slider::new(&mut simulation.step, 0.05..=5.0)
+            .logarithmic(true)
+            .text("Step (days)"),
+    );
+    ui.monospace(format!(
+        "{} bodies, {} collisions in {:.0} days",
+        simulation.bodies.len(),
+        simulation.collisions,
+        simulation.time
+    ));
+    ui.checkbox(show_log, "Collision log");
+}
+
+/// The most recent collisions of the n-body sandbox, newest first.
+fn collision_log(ctx: &egui::Context, nbody: &NBodySimulation, open: &mut bool) {
+    let km_per_s = KM_PER_AU / SECONDS_PER_DAY;
+    egui::Window::new("Collision log")
+        .open(open)
+        .default_width(380.0)
+        .show(ctx, |ui| {
+            if nbody.events.is_empty() {
+                ui.label("No collisions yet");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for event in nbody.events.iter().rev() {
+                    ui.monospace(format!(
+                        "Day {:.1}: {} {:.3} + {:.3} M⊕ at {:.2} km/s → {}",
+                        event.time,
+                        event.outcome.name(),
+                        event.masses.0 / GM_EARTH,
+                        event.masses.1 / GM_EARTH,
+                        event.impact_speed * km_per_s,
+                        event.remnants
+                    ));
+                }
+            });
+        });
+}
+
+/// Picks a body and throws debris off the side of it facing the camera.
+fn impact_ui(
+    ui: &mut egui::Ui,
+    planets: &Planets,
+    target: &mut usize,
+    camera_position: Vec3,
+    particle_system: &mut ParticleSystem,
+) {
+    let bodies: Vec<_> = planets.bodies().filter(|body| !body.emissive).collect();
+    let Some(body) = bodies.get(*target).or(bodies.first()) else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("impact_target")
+            .selected_text(&body.name)
+            .show_ui(ui, |ui| {
+                for (index, body) in bodies.iter().enumerate() {
+                    ui.selectable_value(target, index, &body.name);
+                }
+            });
+        if ui.button("Impact").clicked() {
+            let normal = (camera_position - body.position).normalize_or(Vec3::Y);
+            let point = body.position + normal * body.radius;
+            particle_system
+                .add(impact(point, normal, body.radius))
+                .burst(1500);
+        }
+    });
+}
+
+/// Sliders for the scattering parameters of one atmosphere.
+fn atmosphere_ui(ui: &mut egui::Ui, atmosphere: &mut Atmosphere) {
+    ui.add(egui::Slider::new(&mut atmosphere.height, 0.005..=0.2).text("Shell height"));
+    ui.add(
+        egui::Slider::new(&mut atmosphere.rayleigh_scale_height, 0.001..=0.1)
+            .logarithmic(true)
+            .text("Rayleigh scale height"),
+    );
+    ui.add(
+        egui::Slider::new(&mut atmosphere.mie_scale_height, 0.0005..=0.05)
+            .logarithmic(true)
+            .text("Mie scale height"),
+    );
+    for (channel, label) in ["Rayleigh R", "Rayleigh G", "Rayleigh B"]
+        .iter()
+        .enumerate()
+    {
+        ui.add(
+            egui::Slider::new(&mut atmosphere.rayleigh[channel], 0.0..=100.0)
+                .logarithmic(true)
+                .text(*label),
+        );
+    }
+    ui.add(
+        egui::Slider::new(&mut atmosphere.mie, 0.0..=100.0)
+            .logarithmic(true)
+            .text("Mie"),
+    );
+    ui.add(egui::Slider::new(&mut atmosphere.mie_g, 0.0..=0.99).text("Mie asymmetry"));
+    ui.add(egui::Slider::new(&mut atmosphere.sun_intensity, 0.0..=100.0).text("Sun intensity"));
+}
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {
         debug!("Resumed");
@@ -215,6 +1460,21 @@ impl ApplicationHandler for App {
                         if keycode == PhysicalKey::Code(KeyCode::F2) {
                             self.settings.show_fps = !self.settings.show_fps;
                         }
+                        if keycode == PhysicalKey::Code(KeyCode::F3) {
+                            self.settings.pilot = !self.settings.pilot;
+                            if !self.settings.pilot {
+                                // Back to free flight where the ship view left off.
+                                let position = self.camera.get_position();
+                                let forward = self.camera.get_forward();
+                                self.camera.look_at(position, position + forward);
+                            }
+                        }
+                        if keycode == PhysicalKey::Code(KeyCode::F4) {
+                            self.settings.ship_camera = match self.settings.ship_camera {
+                                CameraMode::Cockpit => CameraMode::Chase,
+                                CameraMode::Chase => CameraMode::Cockpit,
+                            };
+                        }
                     }
                     winit::event::ElementState::Released => {
                         self.keys_pressed.remove(&keycode);
@@ -250,15 +1510,23 @@ impl ApplicationHandler for App {
         event: DeviceEvent,
     ) {
         match event {
+            #[expect(
+                clippy::collapsible_match,
+                reason = "pilot and camera look share the UI check"
+            )]
             DeviceEvent::MouseMotion { delta } => {
                 if !self.settings.show_ui {
-                    let mouse_sensitivity = 0.001;
-                    self.camera.add_to_yaw_pitch(
-                        -delta.0 as f32 * mouse_sensitivity, // yaw
-                        -delta.1 as f32 * mouse_sensitivity, // pitch
-                    );
+                    if self.settings.pilot {
+                        self.mouse_delta += Vec2::new(delta.0 as f32, delta.1 as f32);
+                    } else {
+                        let mouse_sensitivity = 0.001;
+                        self.camera.add_to_yaw_pitch(
+                            -delta.0 as f32 * mouse_sensitivity, // yaw
+                            -delta.1 as f32 * mouse_sensitivity, // pitch
+                        );
 
-                    self.camera.apply_yaw_pitch();
+                        self.camera.apply_yaw_pitch();
+                    }
                 }
             }
             DeviceEvent::MouseWheel { delta } => {
@@ -277,8 +1545,107 @@ impl ApplicationHandler for App {
         self.delta_time = self.last_frame.elapsed().as_secs_f32();
         self.last_frame = std::time::Instant::now();
 
-        self.camera
-            .update_camera(&self.keys_pressed, self.delta_time);
+        if self.settings.hot_reload {
+            let changed = self.watcher.poll();
+            for path in &changed {
+                self.shaders
+                    .reload_file(&self.glium_attributes.display, path);
+                self.skybox.on_file_changed(&mut self.assets, path);
+                if let Some(planets) = &mut self.planets {
+                    planets.on_file_changed(&mut self.assets, path);
+                }
+            }
+            // A reloaded program may include files it did not use before.
+            if !changed.is_empty() {
+                for path in self.shaders.watched_paths() {
+                    self.watcher.watch(path);
+                }
+            }
+        }
+
+        // Keep repainting while the loading screen is up, plus one frame once it finishes.
+        let was_loading = self.assets.is_loading();
+        self.assets.update();
+        if was_loading {
+            self.glium_attributes.window.request_redraw();
+        }
+        if let Err(e) = self
+            .skybox
+            .update(&self.glium_attributes.display, &mut self.assets)
+        {
+            error!("Failed to load skybox: {e:#}");
+        }
+        if let Some(planets) = &mut self.planets {
+            planets.update(&self.glium_attributes.display, &mut self.assets);
+        }
+
+        let previous_days = self.clock.days;
+        self.clock.advance(self.delta_time);
+        self.gravity.set_time(self.clock.days);
+        if let Some(planets) = &mut self.planets {
+            planets.move_bodies(&self.gravity);
+        }
+        if let Some(ship) = &mut self.spacecraft {
+            let controls = if self.settings.pilot && !self.settings.show_ui {
+                ship_controls(&self.keys_pressed, self.mouse_delta)
+            } else {
+                ShipControls::default()
+            };
+            ship.update(
+                &self.gravity,
+                &controls,
+                self.delta_time,
+                self.clock.days - previous_days,
+            );
+        }
+        self.mouse_delta = Vec2::ZERO;
+        self.ship_trajectory = match &self.spacecraft {
+            Some(ship)
+                if ship.flight.landed_on.is_none()
+                    && self.settings.predicted_orbit == OrbitTarget::Spacecraft =>
+            {
+                patched_conics(
+                    &self.gravity,
+                    ship.flight.position,
+                    ship.flight.velocity,
+                    &ship.flight.maneuvers,
+                )
+            }
+            _ => PatchedTrajectory::default(),
+        };
+        if let Some(asteroids) = &mut self.asteroids {
+            asteroids.update(
+                &self.glium_attributes.display,
+                &mut self.assets,
+                self.clock.days,
+            );
+        }
+        for comet in &mut self.comets {
+            comet.update(self.clock.days);
+        }
+        if let Some(nbody) = &mut self.nbody {
+            nbody.update(self.clock.days - previous_days);
+        }
+        let planets = &self.planets;
+        self.particle_system.update(self.delta_time, |name| {
+            let body = planets.as_ref()?.bodies().find(|body| body.name == name)?;
+            Some(Placement {
+                position: body.position,
+                radius: body.radius,
+            })
+        });
+
+        self.sun_effects.update(self.delta_time);
+
+        if let Some(ship) = &self.spacecraft
+            && self.settings.pilot
+        {
+            let (position, target, up) = ship.camera_pose(self.settings.ship_camera);
+            self.camera.set_pose(position, target, up);
+        } else {
+            self.camera
+                .update_camera(&self.keys_pressed, self.delta_time);
+        }
     }
 
     fn suspended(&mut self, event_loop: &ActiveEventLoop) {
@@ -0,0 +1,574 @@
+//! Particle emitters.
+//!
+//! An emitter spawns particles from a shape around its anchor, which is either a fixed point or a
+//! body of the system, so effects like the solar wind follow the body they belong to. Particles
+//! are integrated on the CPU in real seconds through a list of velocity fields, and their color
+//! and size are looked up from curves over their normalized age. The simulation never touches the
+//! GPU: [`step_particles`] and the curves are plain functions of their inputs, and the results are
+//! handed to the [`ParticleRenderer`](crate::particles::ParticleRenderer) as instances.
+//!
+//! Emitters with a rate of zero only emit in bursts and are dropped by the [`ParticleSystem`]
+//! once their last particle has died, which is how one-shot effects like impacts are made.
+
+use std::ops::RangeInclusive;
+
+use glam::Vec3;
+
+use crate::{frustum::Frustum, particles::ParticleInstance, random::Rng};
+
+/// Values that can be interpolated along a [`Curve`].
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(other[i], t))
+    }
+}
+
+/// Piecewise linear function of a particle's normalized age, from keys `(age, value)` sorted by
+/// age. Ages outside the keys take the value of the nearest key.
+#[derive(Clone, Debug)]
+pub struct Curve<T>(pub Vec<(f32, T)>);
+
+impl<T: Lerp> Curve<T> {
+    pub fn sample(&self, t: f32) -> T {
+        let keys = &self.0;
+        let next = keys.partition_point(|&(key, _)| key <= t);
+        match (next.checked_sub(1).map(|i| keys[i]), keys.get(next)) {
+            (Some((a, from)), Some(&(b, to))) => from.lerp(to, (t - a) / (b - a)),
+            (Some((_, value)), None) | (None, Some(&(_, value))) => value,
+            (None, None) => panic!("curve without keys"),
+        }
+    }
+}
+
+/// What an emitter is attached to.
+#[derive(Clone, Debug)]
+pub enum Anchor {
+    Fixed(Vec3),
+    /// The body with this name; emitters whose body does not exist stay idle.
+    Body(String),
+}
+
+/// Where a resolved anchor is this frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub position: Vec3,
+    /// Radius of the anchor body, 0 for fixed anchors.
+    pub radius: f32,
+}
+
+/// Where particles start and which way they move.
+#[derive(Clone, Copy, Debug)]
+pub enum EmitterShape {
+    /// From the anchor's surface, straight outwards.
+    Sphere,
+    /// From the anchor's surface in `direction`, spread up to `half_angle` radians around it.
+    Cone { direction: Vec3, half_angle: f32 },
+}
+
+/// Accelerations acting on particles, relative to the emitter's origin.
+#[derive(Clone, Copy, Debug)]
+pub enum VelocityField {
+    /// Acceleration away from the origin (towards it if negative), falling off with the square of
+    /// the distance in units of the anchor radius.
+    Radial(f32),
+    /// Slows particles down in proportion to their velocity.
+    Drag(f32),
+    /// Spins particles around an axis through the origin.
+    Vortex { axis: Vec3, strength: f32 },
+}
+
+impl VelocityField {
+    /// Acceleration of a particle at `offset` from an origin of `radius` moving at `velocity`.
+    pub fn acceleration(&self, offset: Vec3, radius: f32, velocity: Vec3) -> Vec3 {
+        match *self {
+            VelocityField::Radial(strength) => {
+                let scale = radius.max(1.0);
+                let distance = (offset.length() / scale).max(1.0);
+                offset.normalize_or_zero() * strength / (distance * distance)
+            }
+            VelocityField::Drag(coefficient) => -velocity * coefficient,
+            VelocityField::Vortex { axis, strength } => {
+                axis.cross(offset).normalize_or_zero() * strength
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// In seconds.
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+/// Advances `particles` by `dt` seconds through `fields` around `origin` and removes the ones
+/// that have outlived their lifetime.
+pub fn step_particles(
+    particles: &mut Vec<Particle>,
+    fields: &[VelocityField],
+    origin: Placement,
+    dt: f32,
+) {
+    for particle in particles.iter_mut() {
+        let offset = particle.position - origin.position;
+        let acceleration: Vec3 = fields
+            .iter()
+            .map(|field| field.acceleration(offset, origin.radius, particle.velocity))
+            .sum();
+        particle.velocity += acceleration * dt;
+        particle.position += particle.velocity * dt;
+        particle.age += dt;
+    }
+    particles.retain(|particle| particle.age < particle.lifetime);
+}
+
+#[derive(Clone, Debug)]
+pub struct EmitterDefinition {
+    pub name: String,
+    pub anchor: Anchor,
+    pub shape: EmitterShape,
+    /// Particles per second while enabled, 0 for emitters that only burst.
+    pub rate: f32,
+    /// In seconds.
+    pub lifetime: RangeInclusive<f32>,
+    /// Initial speed in scene units per second.
+    pub speed: RangeInclusive<f32>,
+    pub fields: Vec<VelocityField>,
+    /// Linear HDR color over the normalized age.
+    pub color: Curve<[f32; 3]>,
+    /// Radius in scene units over the normalized age.
+    pub size: Curve<f32>,
+    pub max_particles: usize,
+}
+
+pub struct Emitter {
+    pub definition: EmitterDefinition,
+    pub enabled: bool,
+    particles: Vec<Particle>,
+    rng: Rng,
+    /// Particles owed from fractional rates and requested bursts.
+    pending: f32,
+    origin: Option<Placement>,
+    /// Distance from the origin to the furthest particle.
+    extent: f32,
+}
+
+impl Emitter {
+    pub fn new(definition: EmitterDefinition, seed: u64) -> Self {
+        Self {
+            definition,
+            enabled: true,
+            particles: Vec::new(),
+            rng: Rng::new(seed),
+            pending: 0.0,
+            origin: None,
+            extent: 0.0,
+        }
+    }
+
+    /// Emits `count` particles at the next update, on top of the continuous rate.
+    pub fn burst(&mut self, count: usize) {
+        self.pending += count as f32;
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Points a cone-shaped emitter in `direction`.
+    pub fn set_direction(&mut self, direction: Vec3) {
+        if let EmitterShape::Cone {
+            direction: current, ..
+        } = &mut self.definition.shape
+        {
+            *current = direction;
+        }
+    }
+
+    /// Whether a burst-only emitter has nothing left to show.
+    pub fn is_finished(&self) -> bool {
+        self.definition.rate == 0.0 && self.pending < 1.0 && self.particles.is_empty()
+    }
+
+    /// Moves existing particles by `dt` seconds and emits new ones from `origin`. Without an
+    /// origin the last known one is kept, and an emitter that never had one stays idle.
+    pub fn update(&mut self, dt: f32, origin: Option<Placement>) {
+        if let Some(origin) = origin {
+            self.origin = Some(origin);
+        }
+        let Some(origin) = self.origin else {
+            return;
+        };
+        step_particles(&mut self.particles, &self.definition.fields, origin, dt);
+
+        if self.enabled {
+            self.pending += self.definition.rate * dt;
+        }
+        let room = self
+            .definition
+            .max_particles
+            .saturating_sub(self.particles.len());
+        let count = (self.pending as usize).min(room);
+        self.pending = self.pending.fract();
+        for _ in 0..count {
+            // Spread new particles over the step so they do not leave in visible sheets.
+            let mut particle = self.spawn(origin);
+            let head_start = self.rng.next_f64() as f32 * dt;
+            particle.position += particle.velocity * head_start;
+            particle.age = head_start;
+            self.particles.push(particle);
+        }
+
+        self.extent = self
+            .particles
+            .iter()
+            .map(|particle| particle.position.distance(origin.position))
+            .fold(origin.radius, f32::max);
+    }
+
+    fn spawn(&mut self, origin: Placement) -> Particle {
+        let definition = &self.definition;
+        let rng = &mut self.rng;
+        let (offset, direction) = match definition.shape {
+            EmitterShape::Sphere => {
+                let direction = random_direction(rng);
+                (direction * origin.radius, direction)
+            }
+            EmitterShape::Cone {
+                direction,
+                half_angle,
+            } => {
+                let axis = direction.normalize();
+                (axis * origin.radius, random_in_cone(rng, axis, half_angle))
+            }
+        };
+        let speed = random_in(rng, &definition.speed);
+        Particle {
+            position: origin.position + offset,
+            velocity: direction * speed,
+            age: 0.0,
+            lifetime: random_in(rng, &definition.lifetime),
+        }
+    }
+
+    /// Appends the particles as render instances unless the emitter is outside `frustum`.
+    /// Returns whether it was visible.
+    pub fn collect_particles(
+        &self,
+        frustum: &Frustum,
+        instances: &mut Vec<ParticleInstance>,
+    ) -> bool {
+        let Some(origin) = self.origin else {
+            return false;
+        };
+        if self.particles.is_empty() || !frustum.intersects_sphere(origin.position, self.extent) {
+            return false;
+        }
+        instances.extend(self.particles.iter().map(|particle| {
+            let life = particle.age / particle.lifetime;
+            ParticleInstance {
+                particle_position: particle.position.to_array(),
+                particle_color: self.definition.color.sample(life),
+                particle_size: self.definition.size.sample(life),
+            }
+        }));
+        true
+    }
+}
+
+fn random_in(rng: &mut Rng, range: &RangeInclusive<f32>) -> f32 {
+    rng.range(*range.start() as f64, *range.end() as f64) as f32
+}
+
+fn random_direction(rng: &mut Rng) -> Vec3 {
+    let z = rng.range(-1.0, 1.0) as f32;
+    let angle = rng.range(0.0, std::f64::consts::TAU) as f32;
+    let r = (1.0 - z * z).sqrt();
+    Vec3::new(r * angle.cos(), r * angle.sin(), z)
+}
+
+/// Direction uniformly distributed over the cap of `half_angle` radians around `axis`.
+fn random_in_cone(rng: &mut Rng, axis: Vec3, half_angle: f32) -> Vec3 {
+    let cos_theta = rng.range(half_angle.cos() as f64, 1.0) as f32;
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let angle = rng.range(0.0, std::f64::consts::TAU) as f32;
+    let (u, v) = axis.any_orthonormal_pair();
+    axis * cos_theta + (u * angle.cos() + v * angle.sin()) * sin_theta
+}
+
+/// All active emitters.
+#[derive(Default)]
+pub struct ParticleSystem {
+    pub emitters: Vec<Emitter>,
+    next_seed: u64,
+}
+
+impl ParticleSystem {
+    pub fn add(&mut self, definition: EmitterDefinition) -> &mut Emitter {
+        self.next_seed += 1;
+        self.emitters.push(Emitter::new(definition, self.next_seed));
+        self.emitters.last_mut().expect("emitter was just added")
+    }
+
+    /// Advances every emitter by `dt` seconds, resolving body anchors with `locate`, and drops
+    /// finished one-shot emitters.
+    pub fn update(&mut self, dt: f32, locate: impl Fn(&str) -> Option<Placement>) {
+        for emitter in &mut self.emitters {
+            let origin = match &emitter.definition.anchor {
+                Anchor::Fixed(position) => Some(Placement {
+                    position: *position,
+                    radius: 0.0,
+                }),
+                Anchor::Body(name) => locate(name),
+            };
+            emitter.update(dt, origin);
+        }
+        self.emitters.retain(|emitter| !emitter.is_finished());
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.emitters.iter().map(Emitter::particle_count).sum()
+    }
+}
+
+/// A slow outflow of plasma from a star's surface.
+pub fn solar_wind(star: &str) -> EmitterDefinition {
+    EmitterDefinition {
+        name: format!("Solar wind ({star})"),
+        anchor: Anchor::Body(star.to_owned()),
+        shape: EmitterShape::Sphere,
+        rate: 400.0,
+        lifetime: 6.0..=10.0,
+        speed: 6.0..=10.0,
+        // The star's rotation winds the outflow into a spiral.
+        fields: vec![
+            VelocityField::Radial(4.0),
+            VelocityField::Vortex {
+                axis: Vec3::Y,
+                strength: 1.5,
+            },
+        ],
+        color: Curve(vec![
+            (0.0, [0.6, 0.5, 0.3]),
+            (0.3, [0.2, 0.25, 0.4]),
+            (1.0, [0.0, 0.0, 0.0]),
+        ]),
+        size: Curve(vec![(0.0, 0.5), (1.0, 4.0)]),
+        max_particles: 5000,
+    }
+}
+
+/// Hot gas from an engine nozzle on a craft `length` units long. Point it with
+/// [`Emitter::set_direction`] and move it by updating it with the nozzle position.
+pub fn exhaust(length: f32) -> EmitterDefinition {
+    EmitterDefinition {
+        name: "Exhaust".to_owned(),
+        anchor: Anchor::Fixed(Vec3::ZERO),
+        shape: EmitterShape::Cone {
+            direction: Vec3::Z,
+            half_angle: 8f32.to_radians(),
+        },
+        rate: 300.0,
+        lifetime: 0.2..=0.5,
+        speed: length * 4.0..=length * 6.0,
+        fields: vec![VelocityField::Drag(2.0)],
+        color: Curve(vec![
+            (0.0, [3.0, 4.0, 8.0]),
+            (0.3, [2.0, 0.9, 0.3]),
+            (1.0, [0.0, 0.0, 0.0]),
+        ]),
+        size: Curve(vec![(0.0, length * 0.06), (1.0, length * 0.25)]),
+        max_particles: 500,
+    }
+}
+
+/// Debris thrown up from a surface at `point` with outward `normal`, by a body of `radius`.
+pub fn impact(point: Vec3, normal: Vec3, radius: f32) -> EmitterDefinition {
+    EmitterDefinition {
+        name: "Impact".to_owned(),
+        anchor: Anchor::Fixed(point),
+        shape: EmitterShape::Cone {
+            direction: normal,
+            half_angle: 60f32.to_radians(),
+        },
+        rate: 0.0,
+        lifetime: 1.0..=3.0,
+        speed: radius * 0.5..=radius * 3.0,
+        fields: vec![VelocityField::Drag(1.5)],
+        color: Curve(vec![
+            (0.0, [8.0, 5.0, 2.0]),
+            (0.2, [2.0, 0.6, 0.15]),
+            (1.0, [0.0, 0.0, 0.0]),
+        ]),
+        size: Curve(vec![(0.0, radius * 0.02), (1.0, radius * 0.08)]),
+        max_particles: 2000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: Placement = Placement {
+        position: Vec3::new(10.0, 0.0, 0.0),
+        radius: 2.0,
+    };
+
+    fn definition(rate: f32, lifetime: f32) -> EmitterDefinition {
+        EmitterDefinition {
+            name: "Test".to_owned(),
+            anchor: Anchor::Fixed(ORIGIN.position),
+            shape: EmitterShape::Sphere,
+            rate,
+            lifetime: lifetime..=lifetime,
+            speed: 1.0..=1.0,
+            fields: Vec::new(),
+            color: Curve(vec![(0.0, [1.0, 1.0, 1.0])]),
+            size: Curve(vec![(0.0, 1.0)]),
+            max_particles: 1000,
+        }
+    }
+
+    fn particle(velocity: Vec3, age: f32, lifetime: f32) -> Particle {
+        Particle {
+            position: ORIGIN.position + Vec3::X * 4.0,
+            velocity,
+            age,
+            lifetime,
+        }
+    }
+
+    #[test]
+    fn curves_interpolate_and_clamp() {
+        let curve = Curve(vec![(0.0, 0.0), (0.5, 10.0), (1.0, 20.0)]);
+        assert_eq!(curve.sample(0.25), 5.0);
+        assert_eq!(curve.sample(0.5), 10.0);
+        assert_eq!(curve.sample(0.75), 15.0);
+        assert_eq!(curve.sample(-1.0), 0.0);
+        assert_eq!(curve.sample(2.0), 20.0);
+        let color = Curve(vec![(0.2, [0.0, 1.0, 2.0]), (0.6, [4.0, 1.0, 0.0])]);
+        let mid = color.sample(0.4);
+        assert!(Vec3::from(mid).abs_diff_eq(Vec3::new(2.0, 1.0, 1.0), 1e-6));
+        assert_eq!(color.sample(0.0), [0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn velocity_fields() {
+        let offset = Vec3::X * 4.0;
+        // Twice the radius away the radial push is a quarter of its strength.
+        let radial = VelocityField::Radial(8.0).acceleration(offset, 2.0, Vec3::ZERO);
+        assert!(radial.abs_diff_eq(Vec3::X * 2.0, 1e-6));
+        let inside = VelocityField::Radial(8.0).acceleration(offset * 0.1, 2.0, Vec3::ZERO);
+        assert!(inside.abs_diff_eq(Vec3::X * 8.0, 1e-6));
+
+        let velocity = Vec3::new(1.0, -2.0, 0.5);
+        let drag = VelocityField::Drag(0.5).acceleration(offset, 2.0, velocity);
+        assert_eq!(drag, -velocity * 0.5);
+
+        let vortex = VelocityField::Vortex {
+            axis: Vec3::Y,
+            strength: 3.0,
+        };
+        let spin = vortex.acceleration(offset, 2.0, Vec3::ZERO);
+        assert!(spin.abs_diff_eq(Vec3::NEG_Z * 3.0, 1e-6));
+        assert_eq!(vortex.acceleration(Vec3::Y, 2.0, Vec3::ZERO), Vec3::ZERO);
+    }
+
+    #[test]
+    fn step_integrates_and_expires() {
+        let mut particles = vec![particle(Vec3::Y, 0.0, 1.0), particle(Vec3::Y, 0.95, 1.0)];
+        step_particles(&mut particles, &[VelocityField::Drag(1.0)], ORIGIN, 0.1);
+        assert_eq!(particles.len(), 1);
+        let [particle] = particles[..] else {
+            unreachable!()
+        };
+        assert!(particle.velocity.abs_diff_eq(Vec3::Y * 0.9, 1e-6));
+        assert!(
+            particle
+                .position
+                .abs_diff_eq(ORIGIN.position + Vec3::new(4.0, 0.09, 0.0), 1e-6)
+        );
+        assert!((particle.age - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn emits_at_its_rate() {
+        let mut emitter = Emitter::new(definition(100.0, 100.0), 1);
+        emitter.update(0.5, Some(ORIGIN));
+        assert_eq!(emitter.particle_count(), 50);
+        // Fractional particles carry over between frames.
+        for _ in 0..100 {
+            emitter.update(0.003, None);
+        }
+        assert_eq!(emitter.particle_count(), 80);
+        // Particles leave the surface at unit speed and have flown for at most 0.8 seconds.
+        for particle in &emitter.particles {
+            let distance = particle.position.distance(ORIGIN.position);
+            assert!(distance >= ORIGIN.radius - 1e-4);
+            assert!(distance <= ORIGIN.radius + 0.8 + 1e-4);
+        }
+
+        emitter.enabled = false;
+        emitter.update(1.0, None);
+        assert_eq!(emitter.particle_count(), 80);
+    }
+
+    #[test]
+    fn respects_max_particles_and_needs_an_origin() {
+        let mut emitter = Emitter::new(definition(1000.0, 100.0), 1);
+        emitter.update(1.0, None);
+        assert_eq!(emitter.particle_count(), 0);
+        emitter.definition.max_particles = 30;
+        emitter.update(1.0, Some(ORIGIN));
+        assert_eq!(emitter.particle_count(), 30);
+    }
+
+    #[test]
+    fn bursts_expire_and_finish() {
+        let mut emitter = Emitter::new(definition(0.0, 1.0), 1);
+        emitter.burst(20);
+        assert!(!emitter.is_finished());
+        emitter.update(0.1, Some(ORIGIN));
+        assert_eq!(emitter.particle_count(), 20);
+        emitter.update(0.5, None);
+        assert_eq!(emitter.particle_count(), 20);
+        emitter.update(0.5, None);
+        assert_eq!(emitter.particle_count(), 0);
+        assert!(emitter.is_finished());
+
+        let mut system = ParticleSystem::default();
+        system.add(definition(0.0, 1.0)).burst(5);
+        system.update(0.1, |_| None);
+        assert_eq!(system.particle_count(), 5);
+        system.update(1.0, |_| None);
+        assert!(system.emitters.is_empty());
+    }
+
+    #[test]
+    fn cone_stays_within_its_angle() {
+        let mut definition = definition(1000.0, 100.0);
+        definition.shape = EmitterShape::Cone {
+            direction: Vec3::new(0.0, 2.0, 0.0),
+            half_angle: 0.2,
+        };
+        let mut emitter = Emitter::new(definition, 7);
+        emitter.update(0.2, Some(ORIGIN));
+        assert_eq!(emitter.particle_count(), 200);
+        for particle in &emitter.particles {
+            let angle = particle.velocity.angle_between(Vec3::Y);
+            assert!(angle <= 0.2 + 1e-3, "{angle}");
+        }
+    }
+}
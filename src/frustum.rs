@@ -0,0 +1,174 @@
+//! View-frustum culling.
+//!
+//! The six planes are extracted from the combined `perspective * view` matrix (Gribb & Hartmann),
+//! so they are in world space and match exactly what the camera sees. Objects are tested by their
+//! bounding spheres and skipped before any uniforms are built.
+
+use std::ops::AddAssign;
+
+use glam::{Mat4, Vec3, Vec4};
+
+/// Plane with `normal · p + distance >= 0` on the inside. The normal has unit length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Normalizes the plane equation `(a, b, c, d)`.
+    fn from_coefficients(coefficients: Vec4) -> Self {
+        let normal = coefficients.truncate();
+        let length = normal.length();
+        Self {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    /// Signed distance of `point`, positive on the inside.
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, far.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Planes of `view_projection = perspective * view` for a projection with clip-space depth
+    /// from 0 to w, as produced by [`Mat4::perspective_rh`].
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(Plane::from_coefficients),
+        }
+    }
+
+    /// Whether any part of the sphere may be visible. Spheres near a frustum corner can pass
+    /// without being visible, which only costs a draw call.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(center) >= -radius)
+    }
+}
+
+/// Objects drawn and culled in a frame, for the HUD.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl CullStats {
+    /// Counts the object and returns `visible` for use in a condition.
+    pub fn record(&mut self, visible: bool) -> bool {
+        if visible {
+            self.drawn += 1;
+        } else {
+            self.culled += 1;
+        }
+        visible
+    }
+}
+
+impl AddAssign for CullStats {
+    fn add_assign(&mut self, other: Self) {
+        self.drawn += other.drawn;
+        self.culled += other.culled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+    use super::*;
+
+    const NEAR: f32 = 1.0;
+    const FAR: f32 = 100.0;
+
+    /// 90° square frustum from `eye` towards `target`, so the side planes are at 45°.
+    fn frustum(eye: Vec3, target: Vec3) -> Frustum {
+        let perspective = Mat4::perspective_rh(FRAC_PI_2, 1.0, NEAR, FAR);
+        Frustum::from_view_projection(perspective * Mat4::look_at_rh(eye, target, Vec3::Y))
+    }
+
+    #[test]
+    fn planes_face_inwards() {
+        let frustum = frustum(Vec3::ZERO, Vec3::NEG_Z);
+        let expected = [
+            Vec3::new(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2),
+            Vec3::new(-FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2),
+            Vec3::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            Vec3::new(0.0, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            Vec3::NEG_Z,
+            Vec3::Z,
+        ];
+        for (plane, normal) in frustum.planes.iter().zip(expected) {
+            assert!(plane.normal.abs_diff_eq(normal, 1e-5), "{plane:?}");
+            assert!(plane.signed_distance(Vec3::new(0.0, 0.0, -50.0)) > 0.0);
+        }
+        // The side planes go through the eye, near and far sit at their distances.
+        for plane in &frustum.planes[..4] {
+            assert!(plane.distance.abs() < 1e-5, "{plane:?}");
+        }
+        assert!((frustum.planes[4].distance + NEAR).abs() < 1e-4);
+        assert!((frustum.planes[5].distance - FAR).abs() < 1e-2);
+    }
+
+    #[test]
+    fn planes_are_in_world_space() {
+        let eye = Vec3::new(10.0, 5.0, 0.0);
+        let frustum = frustum(eye, eye + Vec3::X);
+        assert!(frustum.planes[4].normal.abs_diff_eq(Vec3::X, 1e-5));
+        let near_point = eye + Vec3::X * NEAR;
+        assert!(frustum.planes[4].signed_distance(near_point).abs() < 1e-4);
+        assert!(frustum.intersects_sphere(eye + Vec3::X * 50.0, 1.0));
+        assert!(!frustum.intersects_sphere(eye - Vec3::X * 50.0, 1.0));
+    }
+
+    #[test]
+    fn spheres_inside_outside_and_straddling() {
+        let frustum = frustum(Vec3::ZERO, Vec3::NEG_Z);
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, -50.0), 1.0));
+        // Half a frustum width at depth 50 is 50, so a sphere centered 10 beyond a side plane
+        // along the axis is 10 / √2 ≈ 7.07 away from it.
+        let sides = [Vec3::NEG_X, Vec3::X, Vec3::NEG_Y, Vec3::Y];
+        for side in sides {
+            let center = Vec3::new(0.0, 0.0, -50.0) + side * 60.0;
+            assert!(!frustum.intersects_sphere(center, 7.0), "{side}");
+            assert!(frustum.intersects_sphere(center, 7.2), "{side}");
+        }
+        // Near plane at z = -1.
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, -0.5), 0.4));
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, -0.5), 0.6));
+        // Far plane at z = -100.
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, -105.0), 4.0));
+        assert!(frustum.intersects_sphere(Vec3::new(0.0, 0.0, -105.0), 6.0));
+        // Behind the camera.
+        assert!(!frustum.intersects_sphere(Vec3::new(0.0, 0.0, 10.0), 5.0));
+    }
+
+    #[test]
+    fn cull_stats_count_both_outcomes() {
+        let mut stats = CullStats::default();
+        assert!(stats.record(true));
+        assert!(!stats.record(false));
+        stats += CullStats {
+            drawn: 2,
+            culled: 3,
+        };
+        assert_eq!(
+            stats,
+            CullStats {
+                drawn: 3,
+                culled: 4
+            }
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! Gravity of the bodies of the system.
+//!
+//! Bodies move on fixed Kepler orbits around their parents (see [`BodyDefinition::orbit`]), so
+//! the field doubles as their ephemeris: [`GravityField::state`] tells where any body is at any
+//! time. A craft feels the sum of the point-mass pulls of every body at their positions at that
+//! time, in the inertial frame of the sun. Orbiting a planet works because the planet falls
+//! around the sun together with the craft; what is left over is the sun's tidal pull.
+//!
+//! The masses are the scene's, not the real ones: with the exaggerated radii the real masses would
+//! put the inner planets' Hill spheres inside their own surfaces (see [`crate::system`]).
+//!
+//! Positions are heliocentric ecliptic in AU and times in days since J2000, like in
+//! [`crate::kepler`].
+
+use glam::DVec3;
+
+use crate::{
+    body::BodyDefinition,
+    celestial::scene_to_ecliptic,
+    kepler::{Orbit, OrbitalElements},
+    system::AU,
+};
+
+pub struct Attractor {
+    pub name: String,
+    /// Position and velocity at the time of the last [`GravityField::set_time`].
+    pub position: DVec3,
+    pub velocity: DVec3,
+    /// Gravitational parameter in AU³/day².
+    pub gm: f64,
+    /// In AU.
+    pub radius: f64,
+    /// Index of the body this one orbits, `None` for the root.
+    pub parent: Option<usize>,
+    /// Radius in AU within which this body dominates its parent, infinite for the root.
+    pub sphere_of_influence: f64,
+    /// Orbit relative to the parent. Bodies without one stay at `rest`.
+    orbit: Option<Orbit>,
+    rest: DVec3,
+}
+
+pub struct GravityField {
+    pub attractors: Vec<Attractor>,
+    /// Days since J2000 the attractors' positions are for.
+    pub days: f64,
+}
+
+impl GravityField {
+    /// Collects every body with a mass, at J2000. A body orbiting another body of the field moves
+    /// around it, and its sphere of influence follows from their mass ratio and its semi-major
+    /// axis.
+    pub fn new(bodies: &[BodyDefinition]) -> Self {
+        let bodies: Vec<_> = bodies.iter().filter(|body| body.gm > 0.0).collect();
+        let attractors = bodies
+            .iter()
+            .map(|body| {
+                let position = scene_to_ecliptic(body.position) / AU as f64;
+                let parent = body.orbit.as_ref().and_then(|orbit| {
+                    bodies
+                        .iter()
+                        .position(|other| other.name == orbit.parent)
+                        .map(|parent| (parent, &orbit.elements))
+                });
+                let (orbit, sphere_of_influence) = match parent {
+                    Some((parent, elements)) => {
+                        let parent_gm = bodies[parent].gm;
+                        let ratio = body.gm / parent_gm;
+                        (
+                            Some(Orbit::new(elements, parent_gm)),
+                            elements.semi_major_axis * ratio.powf(0.4),
+                        )
+                    }
+                    None => (None, f64::INFINITY),
+                };
+                Attractor {
+                    name: body.name.clone(),
+                    position,
+                    velocity: DVec3::ZERO,
+                    gm: body.gm,
+                    radius: (body.radius / AU) as f64,
+                    parent: parent.map(|(parent, _)| parent),
+                    sphere_of_influence,
+                    orbit,
+                    rest: position,
+                }
+            })
+            .collect();
+        let mut field = Self {
+            attractors,
+            days: f64::NAN,
+        };
+        field.set_time(0.0);
+        field
+    }
+
+    /// Moves the attractors to where they are `days` after J2000.
+    pub fn set_time(&mut self, days: f64) {
+        for index in 0..self.attractors.len() {
+            let (position, velocity) = self.state(index, days);
+            self.attractors[index].position = position;
+            self.attractors[index].velocity = velocity;
+        }
+        self.days = days;
+    }
+
+    /// Heliocentric position and velocity of body `index` at `days`.
+    pub fn state(&self, index: usize, days: f64) -> (DVec3, DVec3) {
+        let body = &self.attractors[index];
+        match (&body.orbit, body.parent) {
+            (Some(orbit), Some(parent)) => {
+                let (position, velocity) = orbit.state(days);
+                let (parent_position, parent_velocity) = self.state(parent, days);
+                (parent_position + position, parent_velocity + velocity)
+            }
+            _ => (body.rest, DVec3::ZERO),
+        }
+    }
+
+    /// Index of the body whose sphere of influence is the innermost one containing `position` at
+    /// `days`.
+    pub fn dominant(&self, position: DVec3, days: f64) -> Option<usize> {
+        (0..self.attractors.len())
+            .filter(|&index| {
+                let body = &self.attractors[index];
+                self.state(index, days).0.distance(position) < body.sphere_of_influence
+            })
+            .min_by(|&a, &b| {
+                let soi = |index: usize| self.attractors[index].sphere_of_influence;
+                soi(a).total_cmp(&soi(b))
+            })
+    }
+
+    /// Sum of the pulls of every body at `days` on a point at `position`. Inside a body the pull
+    /// is capped at its surface value.
+    pub fn acceleration(&self, position: DVec3, days: f64) -> DVec3 {
+        (0..self.attractors.len())
+            .map(|index| {
+                let body = &self.attractors[index];
+                let offset = self.state(index, days).0 - position;
+                let distance = offset.length().max(body.radius);
+                offset * (body.gm / (distance * distance * distance))
+            })
+            .sum()
+    }
+
+    /// Index of the body `position` is inside of at `days`, if any.
+    pub fn inside(&self, position: DVec3, days: f64) -> Option<usize> {
+        (0..self.attractors.len()).find(|&index| {
+            self.state(index, days).0.distance(position) < self.attractors[index].radius
+        })
+    }
+
+    /// Osculating orbit around body `index` of an object at `position` moving at `velocity`, both
+    /// heliocentric, relative to the body's own motion at `days`. `None` if the object moves
+    /// straight towards or away from the body.
+    pub fn orbit_around(
+        &self,
+        index: usize,
+        position: DVec3,
+        velocity: DVec3,
+        days: f64,
+    ) -> Option<OrbitalElements> {
+        let (center, center_velocity) = self.state(index, days);
+        OrbitalElements::from_state_vector(
+            position - center,
+            velocity - center_velocity,
+            self.attractors[index].gm,
+        )
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.attractors.iter().position(|body| body.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::system::solar_system;
+
+    #[test]
+    fn bodies_follow_their_orbits() {
+        let mut field = GravityField::new(&solar_system());
+        let earth = field.find("Earth").unwrap();
+        let moon = field.find("Moon").unwrap();
+        assert_eq!(field.attractors[moon].parent, Some(earth));
+        assert!(field.attractors[earth].sphere_of_influence > 0.05);
+
+        let start = field.attractors[earth].position;
+        field.set_time(365.256 / 4.0);
+        let quarter = field.attractors[earth].position;
+        assert!((start.angle_between(quarter) - FRAC_PI_2).abs() < 0.05);
+
+        // The moon keeps circling the earth wherever the earth goes.
+        let distance = field.attractors[moon].position - field.attractors[earth].position;
+        assert!((distance.length() - 6.0 / AU as f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn acceleration_is_the_sum_of_point_masses() {
+        let field = GravityField::new(&solar_system());
+        let days = 100.0;
+        let position = DVec3::new(0.3, -1.1, 0.05);
+        let expected: DVec3 = (0..field.attractors.len())
+            .map(|index| {
+                let offset = field.state(index, days).0 - position;
+                offset * field.attractors[index].gm / offset.length().powi(3)
+            })
+            .sum();
+        let acceleration = field.acceleration(position, days);
+        assert!(acceleration.abs_diff_eq(expected, 1e-12 * expected.length()));
+
+        // No jump where the earth's sphere of influence ends.
+        let earth = field.find("Earth").unwrap();
+        let (center, _) = field.state(earth, days);
+        let edge = field.attractors[earth].sphere_of_influence;
+        let inside = field.acceleration(center + DVec3::X * edge * 0.999, days);
+        let outside = field.acceleration(center + DVec3::X * edge * 1.001, days);
+        assert!((inside - outside).length() < 0.01 * inside.length());
+    }
+}
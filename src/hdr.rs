@@ -0,0 +1,301 @@
+//! High dynamic range rendering.
+//!
+//! The scene is drawn in linear light into a half-float target so bright objects like the sun
+//! can exceed 1.0. Before egui paints, [`HdrPipeline::resolve`] extracts the bright parts, blurs
+//! them with a dual Kawase filter over a chain of half-resolution targets, and tone maps the sum
+//! onto the default framebuffer.
+
+use anyhow::Result;
+use glium::{
+    Display, Program, Surface, VertexBuffer,
+    framebuffer::{DepthRenderBuffer, SimpleFrameBuffer},
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+    texture::{DepthFormat, MipmapsOption, Texture2d, UncompressedFloatFormat},
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction},
+};
+
+use crate::shaders::{ProgramDesc, ShaderLibrary};
+
+pub const BLOOM_PREFILTER_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "fullscreen.vert",
+    fragment: "bloom.frag",
+    defines: &[("BLOOM_PREFILTER", "1")],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const BLOOM_DOWNSAMPLE_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "fullscreen.vert",
+    fragment: "bloom.frag",
+    defines: &[("BLOOM_DOWNSAMPLE", "1")],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const BLOOM_UPSAMPLE_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "fullscreen.vert",
+    fragment: "bloom.frag",
+    defines: &[("BLOOM_UPSAMPLE", "1")],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const TONEMAP_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "fullscreen.vert",
+    fragment: "tonemap.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: true,
+};
+
+/// Number of half-resolution steps in the bloom chain. Stops early for small windows.
+const BLOOM_LEVELS: usize = 6;
+const HDR_FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F16F16F16F16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    Reinhard,
+    Aces,
+    Filmic,
+}
+
+impl ToneMapping {
+    pub const ALL: [ToneMapping; 3] = [Self::Reinhard, Self::Aces, Self::Filmic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Reinhard => "Reinhard",
+            Self::Aces => "ACES",
+            Self::Filmic => "Filmic (Hable)",
+        }
+    }
+
+    /// Value of the `operator` uniform in tonemap.frag.
+    fn shader_id(self) -> i32 {
+        match self {
+            Self::Reinhard => 0,
+            Self::Aces => 1,
+            Self::Filmic => 2,
+        }
+    }
+}
+
+pub struct HdrSettings {
+    /// Linear multiplier applied before tone mapping.
+    pub exposure: f32,
+    pub bloom_strength: f32,
+    /// Luminance above which pixels start to bloom.
+    pub bloom_threshold: f32,
+    pub tone_mapping: ToneMapping,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            bloom_strength: 0.15,
+            bloom_threshold: 1.0,
+            tone_mapping: ToneMapping::Aces,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct FullscreenVertex {
+    position: [f32; 2],
+}
+
+implement_vertex!(FullscreenVertex, position);
+
+pub struct HdrPipeline {
+    /// One triangle covering the whole viewport.
+    fullscreen: VertexBuffer<FullscreenVertex>,
+    targets: Option<Targets>,
+}
+
+/// Offscreen textures sized for the current window.
+struct Targets {
+    dimensions: (u32, u32),
+    color: Texture2d,
+    depth: DepthRenderBuffer,
+    /// Half resolution and smaller, largest first.
+    bloom: Vec<Texture2d>,
+}
+
+impl Targets {
+    fn new(display: &Display<WindowSurface>, (width, height): (u32, u32)) -> Result<Self> {
+        let mut bloom = Vec::with_capacity(BLOOM_LEVELS);
+        let (mut level_width, mut level_height) = (width / 2, height / 2);
+        while bloom.len() < BLOOM_LEVELS && level_width >= 4 && level_height >= 4 {
+            bloom.push(float_texture(display, level_width, level_height)?);
+            level_width /= 2;
+            level_height /= 2;
+        }
+        Ok(Self {
+            dimensions: (width, height),
+            color: float_texture(display, width, height)?,
+            depth: DepthRenderBuffer::new(display, DepthFormat::F32, width, height)?,
+            bloom,
+        })
+    }
+}
+
+impl HdrPipeline {
+    pub fn new(display: &Display<WindowSurface>) -> Result<Self> {
+        let vertices = [
+            FullscreenVertex {
+                position: [-1.0, -1.0],
+            },
+            FullscreenVertex {
+                position: [3.0, -1.0],
+            },
+            FullscreenVertex {
+                position: [-1.0, 3.0],
+            },
+        ];
+        Ok(Self {
+            fullscreen: VertexBuffer::new(display, &vertices)?,
+            targets: None,
+        })
+    }
+
+    /// Returns the HDR color and depth target for a window of `dimensions`, recreating the
+    /// textures when the size changed.
+    pub fn scene_target<'a>(
+        &'a mut self,
+        display: &Display<WindowSurface>,
+        dimensions: (u32, u32),
+    ) -> Result<SimpleFrameBuffer<'a>> {
+        let dimensions = (dimensions.0.max(1), dimensions.1.max(1));
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|targets| targets.dimensions != dimensions)
+        {
+            self.targets = Some(Targets::new(display, dimensions)?);
+        }
+        let targets = self.targets.as_ref().expect("targets created above");
+        Ok(SimpleFrameBuffer::with_depth_buffer(
+            display,
+            &targets.color,
+            &targets.depth,
+        )?)
+    }
+
+    /// Applies bloom and tone mapping to the scene drawn into [`HdrPipeline::scene_target`] and
+    /// writes the result to `output`. Expects the programs registered as `"bloom_prefilter"`,
+    /// `"bloom_downsample"`, `"bloom_upsample"` and `"tonemap"`.
+    pub fn resolve(
+        &self,
+        display: &Display<WindowSurface>,
+        output: &mut impl Surface,
+        shaders: &ShaderLibrary,
+        settings: &HdrSettings,
+    ) -> Result<()> {
+        let Some(targets) = &self.targets else {
+            return Ok(());
+        };
+        let indices = NoIndices(PrimitiveType::TrianglesList);
+        let replace = glium::DrawParameters::default();
+        let add = glium::DrawParameters {
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::One,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let bloom_enabled = settings.bloom_strength > 0.0 && !targets.bloom.is_empty();
+        if bloom_enabled {
+            let mut target = SimpleFrameBuffer::new(display, &targets.bloom[0])?;
+            target.draw(
+                &self.fullscreen,
+                indices,
+                shaders.get("bloom_prefilter"),
+                &uniform! {
+                    source: linear(&targets.color),
+                    texel_size: texel_size(&targets.color),
+                    threshold: settings.bloom_threshold,
+                },
+                &replace,
+            )?;
+            for pair in targets.bloom.windows(2) {
+                let mut target = SimpleFrameBuffer::new(display, &pair[1])?;
+                self.blur(
+                    &mut target,
+                    shaders.get("bloom_downsample"),
+                    &pair[0],
+                    &replace,
+                )?;
+            }
+            // Each level adds the blurred level below it, so the largest ends up with all of them.
+            for pair in targets.bloom.windows(2).rev() {
+                let mut target = SimpleFrameBuffer::new(display, &pair[0])?;
+                self.blur(&mut target, shaders.get("bloom_upsample"), &pair[1], &add)?;
+            }
+        }
+
+        let bloom = targets.bloom.first().unwrap_or(&targets.color);
+        output.draw(
+            &self.fullscreen,
+            indices,
+            shaders.get("tonemap"),
+            &uniform! {
+                scene: linear(&targets.color),
+                bloom: linear(bloom),
+                bloom_strength: if bloom_enabled { settings.bloom_strength } else { 0.0 },
+                exposure: settings.exposure,
+                operator: settings.tone_mapping.shader_id(),
+            },
+            &replace,
+        )?;
+        Ok(())
+    }
+
+    fn blur(
+        &self,
+        target: &mut SimpleFrameBuffer,
+        program: &Program,
+        source: &Texture2d,
+        params: &glium::DrawParameters,
+    ) -> Result<()> {
+        target.draw(
+            &self.fullscreen,
+            NoIndices(PrimitiveType::TrianglesList),
+            program,
+            &uniform! {
+                source: linear(source),
+                texel_size: texel_size(source),
+            },
+            params,
+        )?;
+        Ok(())
+    }
+}
+
+fn float_texture(display: &Display<WindowSurface>, width: u32, height: u32) -> Result<Texture2d> {
+    Ok(Texture2d::empty_with_format(
+        display,
+        HDR_FORMAT,
+        MipmapsOption::NoMipmap,
+        width,
+        height,
+    )?)
+}
+
+/// Bilinear sampler that clamps at the edges, so blurs do not wrap around the screen.
+fn linear(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture
+        .sampled()
+        .magnify_filter(MagnifySamplerFilter::Linear)
+        .minify_filter(MinifySamplerFilter::Linear)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
+
+fn texel_size(texture: &Texture2d) -> [f32; 2] {
+    [1.0 / texture.width() as f32, 1.0 / texture.height() as f32]
+}
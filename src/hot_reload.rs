@@ -0,0 +1,59 @@
+//! Development helper for editing assets while the app is running.
+//!
+//! With hot reload enabled the [`FileWatcher`] polls modification times of shader and texture
+//! files, and owners of those assets rebuild them when they change.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the modification time of a set of files.
+pub struct FileWatcher {
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Starts watching `path`. Files that are already watched keep their last seen state.
+    pub fn watch(&mut self, path: impl Into<PathBuf>) {
+        self.files
+            .entry(path.into())
+            .or_insert_with_key(|path| modified_time(path));
+    }
+
+    /// Returns the files that changed since the last poll. Checks the file system at most every
+    /// [`POLL_INTERVAL`] and returns nothing in between.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, last_modified) in &mut self.files {
+            let modified = modified_time(path);
+            if modified.is_some() && modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,461 @@
+//! Two-body orbits.
+//!
+//! Positions are heliocentric in the ecliptic frame, in astronomical units, and time is in days
+//! since the J2000 epoch. [`Orbit`] caches everything that does not change along an orbit so
+//! propagating many bodies only costs one Kepler solve each.
+//!
+//! [`OrbitalElements`] convert to and from state vectors for any central body, and cover
+//! hyperbolic orbits as well: there the semi-major axis is negative and the mean anomaly is the
+//! hyperbolic one, `e sinh H - H`. Exactly parabolic orbits are not representable and come out
+//! as very long ellipses or hyperbolas, and radial motion straight towards or away from the
+//! central body has no conic at all.
+
+use std::f64::consts::{PI, TAU};
+
+use glam::DVec3;
+
+/// Gravitational parameter of the sun in AU³/day² (the square of the Gaussian constant).
+pub const GM_SUN: f64 = 2.959_122_082_855_911e-4;
+
+/// Eccentricities and inclinations below this count as circular and equatorial, where the node
+/// and periapsis are undefined and set to zero.
+const DEGENERATE: f64 = 1e-9;
+
+/// Classical orbital elements. Angles in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitalElements {
+    /// In AU, negative for hyperbolic orbits.
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    /// Mean anomaly at the epoch.
+    pub mean_anomaly: f64,
+}
+
+impl OrbitalElements {
+    /// Unit vectors towards periapsis (P) and 90° ahead of it in the orbital plane (Q).
+    pub fn perifocal_basis(&self) -> (DVec3, DVec3) {
+        let (sin_node, cos_node) = self.longitude_of_ascending_node.sin_cos();
+        let (sin_peri, cos_peri) = self.argument_of_periapsis.sin_cos();
+        let (sin_inc, cos_inc) = self.inclination.sin_cos();
+        let p = DVec3::new(
+            cos_node * cos_peri - sin_node * sin_peri * cos_inc,
+            sin_node * cos_peri + cos_node * sin_peri * cos_inc,
+            sin_peri * sin_inc,
+        );
+        let q = DVec3::new(
+            -cos_node * sin_peri - sin_node * cos_peri * cos_inc,
+            -sin_node * sin_peri + cos_node * cos_peri * cos_inc,
+            cos_peri * sin_inc,
+        );
+        (p, q)
+    }
+
+    /// Mean motion in radians per day around a body with gravitational parameter `mu`.
+    pub fn mean_motion(&self, mu: f64) -> f64 {
+        (mu / self.semi_major_axis.abs().powi(3)).sqrt()
+    }
+
+    /// Osculating elements of a body at `position` moving at `velocity` relative to a central
+    /// body with gravitational parameter `mu`, with the mean anomaly at that instant.
+    ///
+    /// On equatorial orbits the node is put on the X axis, and on circular orbits periapsis is put
+    /// at the node, so the anomaly then measures the angle from there. A radial state, without
+    /// angular momentum, has no orbital plane and returns `None`.
+    pub fn from_state_vector(position: DVec3, velocity: DVec3, mu: f64) -> Option<Self> {
+        let r = position.length();
+        let angular_momentum = position.cross(velocity);
+        if angular_momentum.length() <= DEGENERATE * r * velocity.length() {
+            return None;
+        }
+        let normal = angular_momentum.normalize();
+        let node = DVec3::Z.cross(angular_momentum);
+        let eccentricity_vector = ((velocity.length_squared() - mu / r) * position
+            - position.dot(velocity) * velocity)
+            / mu;
+        let eccentricity = eccentricity_vector.length();
+        let energy = velocity.length_squared() / 2.0 - mu / r;
+
+        // Signed angle from `from` to `to` around the orbit normal.
+        let angle = |from: DVec3, to: DVec3| normal.dot(from.cross(to)).atan2(from.dot(to));
+        let equatorial = node.length() < DEGENERATE * angular_momentum.length();
+        let node_direction = if equatorial {
+            DVec3::X
+        } else {
+            node.normalize()
+        };
+        let circular = eccentricity < DEGENERATE;
+        let periapsis_direction = if circular {
+            node_direction
+        } else {
+            eccentricity_vector / eccentricity
+        };
+        let true_anomaly = angle(periapsis_direction, position);
+
+        Some(Self {
+            semi_major_axis: -mu / (2.0 * energy),
+            eccentricity,
+            inclination: normal.truncate().length().atan2(normal.z),
+            longitude_of_ascending_node: if equatorial {
+                0.0
+            } else {
+                node.y.atan2(node.x).rem_euclid(TAU)
+            },
+            argument_of_periapsis: angle(node_direction, periapsis_direction).rem_euclid(TAU),
+            mean_anomaly: true_to_mean_anomaly(true_anomaly, eccentricity),
+        })
+    }
+
+    /// Position and velocity at the epoch relative to a central body with gravitational
+    /// parameter `mu`.
+    pub fn state_vector(&self, mu: f64) -> (DVec3, DVec3) {
+        let e = self.eccentricity;
+        let true_anomaly = mean_to_true_anomaly(self.mean_anomaly, e);
+        let (p, q) = self.perifocal_basis();
+        let semi_latus_rectum = self.semi_latus_rectum();
+        let (sin, cos) = true_anomaly.sin_cos();
+        let r = semi_latus_rectum / (1.0 + e * cos);
+        let speed = (mu / semi_latus_rectum).sqrt();
+        ((p * cos + q * sin) * r, (q * (e + cos) - p * sin) * speed)
+    }
+
+    /// The same orbit with its epoch moved `days` later.
+    pub fn advanced(&self, days: f64, mu: f64) -> Self {
+        let mean_anomaly = self.mean_anomaly + self.mean_motion(mu) * days;
+        Self {
+            mean_anomaly: if self.eccentricity < 1.0 {
+                mean_anomaly.rem_euclid(TAU)
+            } else {
+                mean_anomaly
+            },
+            ..*self
+        }
+    }
+
+    pub fn semi_latus_rectum(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity * self.eccentricity)
+    }
+
+    /// Closest distance to the central body.
+    pub fn periapsis(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity)
+    }
+
+    /// Furthest distance from the central body, for bound orbits.
+    pub fn apoapsis(&self) -> Option<f64> {
+        (self.eccentricity < 1.0).then_some(self.semi_major_axis * (1.0 + self.eccentricity))
+    }
+
+    /// Orbital period in days, for bound orbits.
+    pub fn period(&self, mu: f64) -> Option<f64> {
+        (self.eccentricity < 1.0).then(|| TAU / self.mean_motion(mu))
+    }
+
+    /// Orbital energy per unit mass in AU²/day², negative for bound orbits.
+    pub fn specific_energy(&self, mu: f64) -> f64 {
+        -mu / (2.0 * self.semi_major_axis)
+    }
+
+    /// Speed left over far away from the central body in AU/day, for unbound orbits.
+    pub fn hyperbolic_excess_velocity(&self, mu: f64) -> Option<f64> {
+        (self.eccentricity > 1.0).then(|| (-mu / self.semi_major_axis).sqrt())
+    }
+
+    /// Points along the orbit relative to the central body, `segments + 1` of them from one end to
+    /// the other. Parts further out than `max_radius` are left out, which for bound orbits that
+    /// stay inside it means the whole ellipse, with the first point repeated at the end.
+    pub fn conic_points(&self, segments: usize, max_radius: f64) -> Vec<DVec3> {
+        let e = self.eccentricity;
+        let p = self.semi_latus_rectum();
+        // Largest true anomaly on either side of periapsis, where r reaches `max_radius`.
+        let limit = if self
+            .apoapsis()
+            .is_some_and(|apoapsis| apoapsis <= max_radius)
+        {
+            PI
+        } else {
+            ((p / max_radius - 1.0) / e).clamp(-1.0, 1.0).acos()
+        };
+        let (periapsis, ahead) = self.perifocal_basis();
+        (0..=segments)
+            .map(|segment| {
+                let anomaly = -limit + 2.0 * limit * segment as f64 / segments as f64;
+                let (sin, cos) = anomaly.sin_cos();
+                (periapsis * cos + ahead * sin) * (p / (1.0 + e * cos))
+            })
+            .collect()
+    }
+}
+
+/// Mean anomaly for `true_anomaly` on an orbit of eccentricity `e`, elliptic or hyperbolic.
+pub fn true_to_mean_anomaly(true_anomaly: f64, e: f64) -> f64 {
+    let (sin, cos) = true_anomaly.sin_cos();
+    if e < 1.0 {
+        let eccentric = ((1.0 - e * e).sqrt() * sin).atan2(e + cos);
+        (eccentric - e * eccentric.sin()).rem_euclid(TAU)
+    } else {
+        let hyperbolic =
+            2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * (true_anomaly / 2.0).tan()).atanh();
+        e * hyperbolic.sinh() - hyperbolic
+    }
+}
+
+/// True anomaly for `mean_anomaly` on an orbit of eccentricity `e`, elliptic or hyperbolic.
+pub fn mean_to_true_anomaly(mean_anomaly: f64, e: f64) -> f64 {
+    if e < 1.0 {
+        let eccentric = solve_kepler(mean_anomaly, e);
+        let (sin, cos) = eccentric.sin_cos();
+        ((1.0 - e * e).sqrt() * sin).atan2(cos - e)
+    } else {
+        let hyperbolic = solve_kepler_hyperbolic(mean_anomaly, e);
+        2.0 * (((e + 1.0) / (e - 1.0)).sqrt() * (hyperbolic / 2.0).tanh()).atan()
+    }
+}
+
+/// Hyperbolic anomaly `H` with `e sinh H - H = mean_anomaly` for `e` > 1, by Newton's method.
+pub fn solve_kepler_hyperbolic(mean_anomaly: f64, e: f64) -> f64 {
+    let mut anomaly = (mean_anomaly / e).asinh();
+    for _ in 0..64 {
+        let step = (e * anomaly.sinh() - anomaly - mean_anomaly) / (e * anomaly.cosh() - 1.0);
+        anomaly -= step;
+        if step.abs() < 1e-12 * (1.0 + anomaly.abs()) {
+            break;
+        }
+    }
+    anomaly
+}
+
+/// Eccentric anomaly for `mean_anomaly` on an ellipse of eccentricity `e` < 1, by Newton's
+/// method.
+pub fn solve_kepler(mean_anomaly: f64, e: f64) -> f64 {
+    let m = mean_anomaly.rem_euclid(TAU);
+    // Starting at π converges for every eccentricity; M is closer for nearly circular orbits.
+    let mut anomaly = if e < 0.8 { m } else { PI };
+    for _ in 0..32 {
+        let (sin, cos) = anomaly.sin_cos();
+        let step = (anomaly - e * sin - m) / (1.0 - e * cos);
+        anomaly -= step;
+        if step.abs() < 1e-12 {
+            break;
+        }
+    }
+    anomaly
+}
+
+/// An elliptic orbit prepared for fast propagation.
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    /// Towards periapsis, scaled by the semi-major axis.
+    p: DVec3,
+    /// In the orbital plane 90° ahead of `p`, scaled by the semi-minor axis.
+    q: DVec3,
+    semi_major_axis: f64,
+    eccentricity: f64,
+    mean_motion: f64,
+    mean_anomaly: f64,
+}
+
+impl Orbit {
+    pub fn new(elements: &OrbitalElements, mu: f64) -> Self {
+        let (p, q) = elements.perifocal_basis();
+        let a = elements.semi_major_axis;
+        let e = elements.eccentricity;
+        Self {
+            p: p * a,
+            q: q * a * (1.0 - e * e).sqrt(),
+            semi_major_axis: a,
+            eccentricity: e,
+            mean_motion: elements.mean_motion(mu),
+            mean_anomaly: elements.mean_anomaly,
+        }
+    }
+
+    /// Position `days` after the epoch.
+    pub fn position(&self, days: f64) -> DVec3 {
+        let anomaly = solve_kepler(
+            self.mean_anomaly + self.mean_motion * days,
+            self.eccentricity,
+        );
+        let (sin, cos) = anomaly.sin_cos();
+        self.p * (cos - self.eccentricity) + self.q * sin
+    }
+
+    /// Position and velocity (AU/day) `days` after the epoch.
+    pub fn state(&self, days: f64) -> (DVec3, DVec3) {
+        let anomaly = solve_kepler(
+            self.mean_anomaly + self.mean_motion * days,
+            self.eccentricity,
+        );
+        let (sin, cos) = anomaly.sin_cos();
+        let anomaly_rate = self.mean_motion / (1.0 - self.eccentricity * cos);
+        (
+            self.p * (cos - self.eccentricity) + self.q * sin,
+            (self.q * cos - self.p * sin) * anomaly_rate,
+        )
+    }
+
+    /// Largest distance from the central body.
+    pub fn aphelion(&self) -> f64 {
+        self.semi_major_axis * (1.0 + self.eccentricity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(a: f64, e: f64, i: f64, node: f64, peri: f64, mean: f64) -> OrbitalElements {
+        OrbitalElements {
+            semi_major_axis: a,
+            eccentricity: e,
+            inclination: i.to_radians(),
+            longitude_of_ascending_node: node.to_radians(),
+            argument_of_periapsis: peri.to_radians(),
+            mean_anomaly: mean.to_radians(),
+        }
+    }
+
+    /// Difference between two angles, wrapped to `[-π, π]`.
+    fn angle_difference(a: f64, b: f64) -> f64 {
+        (a - b + PI).rem_euclid(TAU) - PI
+    }
+
+    fn assert_same_elements(actual: &OrbitalElements, expected: &OrbitalElements) {
+        let message = format!("{actual:?} != {expected:?}");
+        let relative = (actual.semi_major_axis - expected.semi_major_axis).abs()
+            / expected.semi_major_axis.abs();
+        assert!(relative < 1e-12, "{message}");
+        assert!(
+            (actual.eccentricity - expected.eccentricity).abs() < 1e-12,
+            "{message}"
+        );
+        for (actual, expected) in [
+            (actual.inclination, expected.inclination),
+            (
+                actual.longitude_of_ascending_node,
+                expected.longitude_of_ascending_node,
+            ),
+            (actual.argument_of_periapsis, expected.argument_of_periapsis),
+            (actual.mean_anomaly, expected.mean_anomaly),
+        ] {
+            assert!(
+                angle_difference(actual, expected).abs() < 1e-10,
+                "{message}"
+            );
+        }
+    }
+
+    fn round_trip(elements: &OrbitalElements) {
+        let (position, velocity) = elements.state_vector(GM_SUN);
+        assert_same_elements(
+            &OrbitalElements::from_state_vector(position, velocity, GM_SUN).unwrap(),
+            elements,
+        );
+        let (again_position, again_velocity) =
+            OrbitalElements::from_state_vector(position, velocity, GM_SUN)
+                .unwrap()
+                .state_vector(GM_SUN);
+        assert!(again_position.abs_diff_eq(position, 1e-12 * position.length()));
+        assert!(again_velocity.abs_diff_eq(velocity, 1e-12 * velocity.length()));
+    }
+
+    #[test]
+    fn elliptic_round_trip() {
+        round_trip(&elements(1.523_7, 0.093_4, 1.85, 49.56, 286.5, 19.4));
+        round_trip(&elements(2.7, 0.3, 34.8, 173.1, 310.0, 250.0));
+    }
+
+    #[test]
+    fn hyperbolic_round_trip() {
+        round_trip(&elements(-1.27, 1.2, 122.7, 24.6, 241.8, 57.3));
+        round_trip(&elements(-0.5, 3.5, 10.0, 300.0, 45.0, -200.0));
+    }
+
+    #[test]
+    fn circular_equatorial_round_trip() {
+        // Node and periapsis are undefined and put at zero, so the anomaly carries the position.
+        round_trip(&elements(1.0, 0.0, 0.0, 0.0, 0.0, 123.0));
+        let position = DVec3::new(0.0, 2.0, 0.0);
+        let velocity = DVec3::new(-(GM_SUN / 2.0).sqrt(), 0.0, 0.0);
+        let elements = OrbitalElements::from_state_vector(position, velocity, GM_SUN).unwrap();
+        assert!((elements.semi_major_axis - 2.0).abs() < 1e-12);
+        assert!(elements.eccentricity < 1e-12);
+        assert_eq!(elements.longitude_of_ascending_node, 0.0);
+        assert_eq!(elements.argument_of_periapsis, 0.0);
+        assert!(angle_difference(elements.mean_anomaly, PI / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn eccentric_retrograde_round_trip() {
+        round_trip(&elements(17.8, 0.9, 162.2, 58.4, 111.3, 38.4));
+        round_trip(&elements(5.0, 0.9, 150.0, 200.0, 10.0, 359.0));
+    }
+
+    #[test]
+    fn bound_orbit_helpers() {
+        let earth = elements(1.0, 0.0167, 0.0, 0.0, 102.9, 0.0);
+        let year = earth.period(GM_SUN).unwrap();
+        assert!((year - 365.256_9).abs() < 1e-3, "{year}");
+        assert!((earth.periapsis() - 0.9833).abs() < 1e-12);
+        assert!((earth.apoapsis().unwrap() - 1.0167).abs() < 1e-12);
+        assert_eq!(earth.hyperbolic_excess_velocity(GM_SUN), None);
+        assert!(earth.specific_energy(GM_SUN) < 0.0);
+        // A full period later the body is back where it started.
+        let later = earth.advanced(year, GM_SUN);
+        assert!(angle_difference(later.mean_anomaly, earth.mean_anomaly).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hyperbolic_excess_velocity_matches_energy() {
+        // Leaving from 1 AU at one and a half times escape speed.
+        let position = DVec3::X;
+        let escape = (2.0 * GM_SUN).sqrt();
+        let velocity = DVec3::Y * escape * 1.5;
+        let elements = OrbitalElements::from_state_vector(position, velocity, GM_SUN).unwrap();
+        assert!(elements.eccentricity > 1.0);
+        assert_eq!(elements.apoapsis(), None);
+        assert_eq!(elements.period(GM_SUN), None);
+        // v∞² = v² - v_escape².
+        let expected = (velocity.length_squared() - escape * escape).sqrt();
+        let excess = elements.hyperbolic_excess_velocity(GM_SUN).unwrap();
+        assert!((excess - expected).abs() < 1e-12 * expected);
+        assert!((elements.specific_energy(GM_SUN) - excess * excess / 2.0).abs() < 1e-15);
+        assert!((elements.periapsis() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn radial_state_has_no_orbit() {
+        let position = DVec3::new(0.3, -0.4, 0.1);
+        for velocity in [position * 0.02, position * -0.01, DVec3::ZERO] {
+            assert_eq!(
+                OrbitalElements::from_state_vector(position, velocity, GM_SUN),
+                None
+            );
+        }
+        // Any sideways motion at all gives a very thin ellipse.
+        let sideways = position.cross(DVec3::Z) * 1e-6;
+        let elements = OrbitalElements::from_state_vector(position, sideways, GM_SUN).unwrap();
+        assert!(elements.eccentricity > 0.99 && elements.eccentricity < 1.0);
+        assert!(elements.semi_major_axis.is_finite());
+    }
+
+    #[test]
+    fn anomaly_conversions_invert() {
+        for e in [0.0f64, 0.3, 0.9, 0.999, 1.5, 5.0] {
+            for true_anomaly in [-2.0f64, -0.5, 0.0, 0.7, 1.9] {
+                // Hyperbolas only reach true anomalies short of their asymptotes.
+                if e > 1.0 && true_anomaly.abs() >= (-1.0 / e).acos() {
+                    continue;
+                }
+                let mean = true_to_mean_anomaly(true_anomaly, e);
+                let back = mean_to_true_anomaly(mean, e);
+                assert!(
+                    angle_difference(back, true_anomaly).abs() < 1e-9,
+                    "e {e}: {true_anomaly} -> {mean} -> {back}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Lambert's problem: the orbit that goes from one point to another in a given time.
+//!
+//! Solved with universal variables (Bate, Mueller & White; Curtis, algorithm 5.2), which covers
+//! elliptic and hyperbolic transfers alike. Only single-revolution transfers are found. For
+//! Curtis' example 5.2 (`r1 = (5000, 10000, 2100)` km, `r2 = (-14600, 2500, 7000)` km, one hour,
+//! around the Earth) it gives `v1 = (-5.9925, 1.9254, 3.2456)` km/s and
+//! `v2 = (-3.3125, -4.1966, -0.38529)` km/s like the book.
+
+use std::f64::consts::{PI, TAU};
+
+use glam::DVec3;
+
+/// Iterations of the bisection on the universal variable.
+const ITERATIONS: usize = 64;
+
+/// Velocities at `r1` and at `r2` of the orbit around a body with gravitational parameter `mu`
+/// that takes `time` to go from one to the other, going around the +Z axis for `prograde`
+/// transfers and against it otherwise. `None` if the points are (nearly) collinear with the
+/// central body, which leaves the orbital plane undefined, or if `time` is not positive.
+pub fn solve_lambert(
+    r1: DVec3,
+    r2: DVec3,
+    time: f64,
+    mu: f64,
+    prograde: bool,
+) -> Option<(DVec3, DVec3)> {
+    if time <= 0.0 {
+        return None;
+    }
+    let (r1_length, r2_length) = (r1.length(), r2.length());
+    let cos_angle = (r1.dot(r2) / (r1_length * r2_length)).clamp(-1.0, 1.0);
+    let mut angle = cos_angle.acos();
+    if (r1.cross(r2).z >= 0.0) != prograde {
+        angle = TAU - angle;
+    }
+    if angle.sin().abs() < 1e-9 {
+        return None;
+    }
+    let a = angle.sin() * (r1_length * r2_length / (1.0 - cos_angle)).sqrt();
+    let y = |z: f64| r1_length + r2_length + a * (z * stumpff_s(z) - 1.0) / stumpff_c(z).sqrt();
+    // Flight time for `z` minus the wanted one. It grows with `z`, and where `y` is negative
+    // there is no orbit because the transfer would have to be faster still. Towards z = 4π² the
+    // ellipse degenerates and the flight time goes to infinity; once `C(z)` underflows there, `y`
+    // is no longer meaningful (it diverges to -∞ for transfers over 180°, where `a` < 0).
+    let time_error = |z: f64| {
+        let c = stumpff_c(z);
+        if c <= 0.0 {
+            return f64::INFINITY;
+        }
+        let y = y(z);
+        if y < 0.0 {
+            return -time;
+        }
+        ((y / c).powf(1.5) * stumpff_s(z) + a * y.sqrt()) / mu.sqrt() - time
+    };
+
+    // Single-revolution ellipses have z < 4π², hyperbolas z < 0 without a bound.
+    let mut upper = 4.0 * PI * PI * (1.0 - 1e-12);
+    let mut lower = -4.0 * PI * PI;
+    while time_error(lower) > 0.0 {
+        lower *= 2.0;
+        if lower < -1e6 {
+            return None;
+        }
+    }
+    if time_error(upper) < 0.0 {
+        return None;
+    }
+    for _ in 0..ITERATIONS {
+        let middle = 0.5 * (lower + upper);
+        if time_error(middle) < 0.0 {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    let z = 0.5 * (lower + upper);
+    let y = y(z);
+    let f = 1.0 - y / r1_length;
+    let g = a * (y / mu).sqrt();
+    let g_dot = 1.0 - y / r2_length;
+    Some(((r2 - r1 * f) / g, (r2 * g_dot - r1) / g))
+}
+
+/// Stumpff functions `C(z)` and `S(z)`, with series close to zero where the closed forms lose
+/// precision. `1 - cos x` is written as `2 sin²(x/2)`, which stays accurate near z = 4π² where
+/// the cosine is almost 1.
+fn stumpff_c(z: f64) -> f64 {
+    if z > 1e-6 {
+        let half = (z.sqrt() / 2.0).sin();
+        2.0 * half * half / z
+    } else if z < -1e-6 {
+        ((-z).sqrt().cosh() - 1.0) / -z
+    } else {
+        1.0 / 2.0 - z / 24.0 + z * z / 720.0
+    }
+}
+
+fn stumpff_s(z: f64) -> f64 {
+    if z > 1e-6 {
+        let root = z.sqrt();
+        (root - root.sin()) / (root * root * root)
+    } else if z < -1e-6 {
+        let root = (-z).sqrt();
+        (root.sinh() - root) / (root * root * root)
+    } else {
+        1.0 / 6.0 - z / 120.0 + z * z / 5040.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kepler::{GM_SUN, OrbitalElements};
+
+    /// Gravitational parameter of the Earth in km³/s².
+    const GM_EARTH_KM: f64 = 398_600.0;
+
+    fn assert_close(actual: DVec3, expected: DVec3, tolerance: f64) {
+        assert!(
+            actual.abs_diff_eq(expected, tolerance),
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    /// Solves between two points of `elements`, `time` days apart, and compares with the
+    /// velocities of the orbit itself.
+    fn recovers_orbit(elements: OrbitalElements, time: f64, prograde: bool) {
+        let (r1, v1) = elements.state_vector(GM_SUN);
+        let (r2, v2) = elements.advanced(time, GM_SUN).state_vector(GM_SUN);
+        let (lambert_v1, lambert_v2) = solve_lambert(r1, r2, time, GM_SUN, prograde)
+            .unwrap_or_else(|| panic!("no transfer after {time} days"));
+        assert_close(lambert_v1, v1, 1e-10 * v1.length());
+        assert_close(lambert_v2, v2, 1e-10 * v2.length());
+    }
+
+    /// Transfer angle from `r1` to `r2` going around +Z, in degrees.
+    fn prograde_angle(elements: OrbitalElements, time: f64) -> f64 {
+        let (r1, _) = elements.state_vector(GM_SUN);
+        let (r2, _) = elements.advanced(time, GM_SUN).state_vector(GM_SUN);
+        let angle = r1.angle_between(r2).to_degrees();
+        if r1.cross(r2).z >= 0.0 {
+            angle
+        } else {
+            360.0 - angle
+        }
+    }
+
+    const ELLIPSE: OrbitalElements = OrbitalElements {
+        semi_major_axis: 1.3,
+        eccentricity: 0.2,
+        inclination: 0.1,
+        longitude_of_ascending_node: 0.5,
+        argument_of_periapsis: 1.0,
+        mean_anomaly: 0.3,
+    };
+
+    #[test]
+    fn curtis_example_5_2() {
+        let r1 = DVec3::new(5000.0, 10_000.0, 2100.0);
+        let r2 = DVec3::new(-14_600.0, 2500.0, 7000.0);
+        let (v1, v2) = solve_lambert(r1, r2, 3600.0, GM_EARTH_KM, true).unwrap();
+        assert_close(v1, DVec3::new(-5.9925, 1.9254, 3.2456), 1e-4);
+        assert_close(v2, DVec3::new(-3.3125, -4.1966, -0.385_29), 1e-4);
+    }
+
+    #[test]
+    fn vallado_example_7_5() {
+        let r1 = DVec3::new(15_945.34, 0.0, 0.0);
+        let r2 = DVec3::new(12_214.838_99, 10_249.467_31, 0.0);
+        let (v1, v2) = solve_lambert(r1, r2, 76.0 * 60.0, 398_600.441_8, true).unwrap();
+        assert_close(v1, DVec3::new(2.058_913, 2.915_965, 0.0), 1e-5);
+        assert_close(v2, DVec3::new(-3.451_565, 0.910_315, 0.0), 1e-5);
+    }
+
+    #[test]
+    fn transfers_below_180_degrees() {
+        for time in [50.0, 150.0, 250.0] {
+            assert!(prograde_angle(ELLIPSE, time) < 180.0);
+            recovers_orbit(ELLIPSE, time, true);
+        }
+    }
+
+    #[test]
+    fn transfers_above_180_degrees() {
+        for time in [320.0, 400.0, 500.0] {
+            assert!(prograde_angle(ELLIPSE, time) > 180.0);
+            recovers_orbit(ELLIPSE, time, true);
+        }
+    }
+
+    #[test]
+    fn retrograde_transfers() {
+        let retrograde = OrbitalElements {
+            inclination: 2.8,
+            ..ELLIPSE
+        };
+        for time in [100.0, 400.0] {
+            recovers_orbit(retrograde, time, false);
+        }
+    }
+
+    #[test]
+    fn hyperbolic_transfer() {
+        let hyperbola = OrbitalElements {
+            semi_major_axis: -2.0,
+            eccentricity: 1.5,
+            mean_anomaly: -0.05,
+            ..ELLIPSE
+        };
+        for time in [20.0, 60.0] {
+            recovers_orbit(hyperbola, time, true);
+        }
+        let (r1, _) = hyperbola.state_vector(GM_SUN);
+        let (r2, _) = hyperbola.advanced(60.0, GM_SUN).state_vector(GM_SUN);
+        let (v1, _) = solve_lambert(r1, r2, 60.0, GM_SUN, true).unwrap();
+        assert!(
+            OrbitalElements::from_state_vector(r1, v1, GM_SUN)
+                .unwrap()
+                .eccentricity
+                > 1.0
+        );
+    }
+
+    #[test]
+    fn rejects_degenerate_transfers() {
+        let r1 = DVec3::X;
+        assert!(solve_lambert(r1, DVec3::X * 2.0, 100.0, GM_SUN, true).is_none());
+        assert!(solve_lambert(r1, DVec3::NEG_X * 1.5, 100.0, GM_SUN, true).is_none());
+        assert!(solve_lambert(r1, DVec3::Y, 0.0, GM_SUN, true).is_none());
+        assert!(solve_lambert(r1, DVec3::Y, -10.0, GM_SUN, true).is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! The solar system viewer. The binary only opens the window and runs [`App`]; the n-body
+//! force calculations are exported as well so that they can be benchmarked.
+
+#[macro_use]
+extern crate glium;
+
+mod assets;
+mod asteroids;
+mod atmosphere;
+mod barnes_hut;
+mod body;
+mod camera;
+mod celestial;
+mod clock;
+mod color;
+mod comet;
+mod cubemap;
+mod eclipse;
+mod egui_setup;
+mod emitter;
+mod frustum;
+mod gravity;
+mod hdr;
+mod hot_reload;
+mod kepler;
+mod lambert;
+mod lod;
+mod maneuver;
+mod nbody;
+mod overlays;
+mod parallel;
+mod particles;
+mod planet;
+mod porkchop;
+mod random;
+mod rings;
+mod shaders;
+mod skybox;
+mod spacecraft;
+mod starfield;
+mod sun;
+mod system;
+mod trajectory;
+
+pub use barnes_hut::tree_accelerations;
+pub use egui_setup::{App, GliumAttributes};
+pub use nbody::{NBody, direct_accelerations};
@@ -0,0 +1,142 @@
+//! Level of detail for bodies.
+//!
+//! How a body is drawn depends on how large it appears: its radius projected onto the screen in
+//! pixels, from the camera's field of view and its distance. Close bodies get a dense sphere,
+//! small ones coarser spheres, then a ray-traced billboard impostor, and the tiniest a single
+//! point so they never vanish.
+
+/// Sphere resolutions as (segments, rings), densest first.
+pub const MESH_LEVELS: [(u32, u32); 3] = [(128, 64), (48, 24), (16, 8)];
+/// Smallest projected radius in pixels at which each mesh level is used.
+const MESH_MIN_RADIUS_PX: [f32; 3] = [150.0, 40.0, 8.0];
+/// Below this projected radius a point sprite replaces the impostor.
+const IMPOSTOR_MIN_RADIUS_PX: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lod {
+    /// Index into [`MESH_LEVELS`].
+    Mesh(usize),
+    Impostor,
+    Point,
+}
+
+/// Radius in pixels of a sphere of `radius` at `distance` from the camera, for a vertical field
+/// of view `fov_y` in radians and a viewport `viewport_height` pixels high. Infinite when the
+/// camera is inside the sphere.
+pub fn projected_radius_px(radius: f32, distance: f32, fov_y: f32, viewport_height: f32) -> f32 {
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    // Tangent of the angle between the center and the silhouette.
+    let tangent = radius / (distance * distance - radius * radius).sqrt();
+    tangent / (fov_y * 0.5).tan() * viewport_height * 0.5
+}
+
+/// Level for a body whose radius projects to `radius_px` pixels.
+pub fn select_lod(radius_px: f32) -> Lod {
+    MESH_MIN_RADIUS_PX
+        .iter()
+        .position(|&min| radius_px >= min)
+        .map_or(
+            if radius_px >= IMPOSTOR_MIN_RADIUS_PX {
+                Lod::Impostor
+            } else {
+                Lod::Point
+            },
+            Lod::Mesh,
+        )
+}
+
+/// Camera parameters needed to pick a level for each body in a frame.
+#[derive(Clone, Copy, Debug)]
+pub struct LodSelector {
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub viewport_height: f32,
+    /// Multiplies the projected size. Values above 1 keep detailed meshes further out.
+    pub bias: f32,
+}
+
+impl LodSelector {
+    pub fn select(&self, radius: f32, distance: f32) -> Lod {
+        select_lod(
+            projected_radius_px(radius, distance, self.fov_y, self.viewport_height) * self.bias,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    /// 90° field of view on a 1000 pixel viewport: `tan(fov / 2) = 1`, so a sphere covers
+    /// `500 * tangent` pixels.
+    const SELECTOR: LodSelector = LodSelector {
+        fov_y: FRAC_PI_2,
+        viewport_height: 1000.0,
+        bias: 1.0,
+    };
+
+    #[test]
+    fn projected_radius_follows_the_silhouette() {
+        // The silhouette of a unit sphere at distance 2 is 30° off center.
+        let radius = projected_radius_px(1.0, 2.0, FRAC_PI_2, 1000.0);
+        assert!((radius - 500.0 * 30f32.to_radians().tan()).abs() < 1e-3);
+        // Far away the size falls off with distance.
+        let near = projected_radius_px(1.0, 1000.0, FRAC_PI_2, 1000.0);
+        let far = projected_radius_px(1.0, 2000.0, FRAC_PI_2, 1000.0);
+        assert!((near / far - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn thresholds_pick_each_level() {
+        assert_eq!(select_lod(1000.0), Lod::Mesh(0));
+        assert_eq!(select_lod(100.0), Lod::Mesh(1));
+        assert_eq!(select_lod(10.0), Lod::Mesh(2));
+        assert_eq!(select_lod(5.0), Lod::Impostor);
+        assert_eq!(select_lod(1.0), Lod::Point);
+        assert_eq!(select_lod(0.0), Lod::Point);
+    }
+
+    #[test]
+    fn thresholds_are_inclusive() {
+        for (level, &min) in MESH_MIN_RADIUS_PX.iter().enumerate() {
+            assert_eq!(select_lod(min), Lod::Mesh(level));
+            let below = select_lod(min.next_down());
+            assert_ne!(below, Lod::Mesh(level));
+        }
+        assert_eq!(select_lod(IMPOSTOR_MIN_RADIUS_PX), Lod::Impostor);
+        assert_eq!(select_lod(IMPOSTOR_MIN_RADIUS_PX.next_down()), Lod::Point);
+        assert_eq!(select_lod(MESH_MIN_RADIUS_PX[2].next_down()), Lod::Impostor);
+    }
+
+    #[test]
+    fn camera_inside_the_sphere_gets_the_densest_mesh() {
+        assert_eq!(
+            projected_radius_px(1.0, 0.5, FRAC_PI_2, 1000.0),
+            f32::INFINITY
+        );
+        assert_eq!(SELECTOR.select(1.0, 0.5), Lod::Mesh(0));
+        assert_eq!(SELECTOR.select(1.0, 1.0), Lod::Mesh(0));
+        assert_eq!(SELECTOR.select(1.0, 0.0), Lod::Mesh(0));
+    }
+
+    #[test]
+    fn bias_scales_the_projected_size() {
+        // About 50 pixels: the middle mesh without bias.
+        let distance = 10.0;
+        assert_eq!(SELECTOR.select(1.0, distance), Lod::Mesh(1));
+        let detailed = LodSelector {
+            bias: 4.0,
+            ..SELECTOR
+        };
+        assert_eq!(detailed.select(1.0, distance), Lod::Mesh(0));
+        let coarse = LodSelector {
+            bias: 0.1,
+            ..SELECTOR
+        };
+        assert_eq!(coarse.select(1.0, distance), Lod::Impostor);
+    }
+}
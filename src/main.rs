@@ -10,17 +10,7 @@ use glium::{
 };
 use log::info;
 
-use crate::{
-    egui_setup::{App, GliumAttributes},
-    skybox::upload_sky_box,
-};
-
-mod camera;
-mod egui_setup;
-mod skybox;
-
-#[macro_use]
-extern crate glium;
+use solar_system::{App, GliumAttributes};
 
 fn create_display(
     event_loop: &EventLoop<()>,
@@ -45,8 +35,6 @@ fn main() -> Result<()> {
 
     let mut app = App::new(&event_loop, GliumAttributes { window, display });
 
-    upload_sky_box(&app);
-
     info!("Entering main loop...");
     event_loop.run_app(&mut app)?;
     Ok(())
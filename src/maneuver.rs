@@ -0,0 +1,148 @@
+//! Maneuver nodes: planned impulsive burns.
+//!
+//! A node holds the time left until the burn and its delta-v in the local orbital frame of the
+//! body the craft is orbiting at that moment:
+//!
+//! - prograde along the velocity,
+//! - normal along the orbit's angular momentum,
+//! - radial completing the right-handed frame, pointing away from the body on a circular orbit.
+//!
+//! Times are in days and velocities in AU/day, like in [`crate::kepler`].
+
+use std::f64::consts::{PI, TAU};
+
+use glam::DVec3;
+
+use crate::{gravity::GravityField, kepler::OrbitalElements};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ManeuverNode {
+    /// Days from now.
+    pub time: f64,
+    /// Prograde, normal and radial components.
+    pub delta_v: DVec3,
+}
+
+/// Velocity change in the ecliptic frame for a burn of `delta_v` (prograde, normal, radial) at
+/// `position` relative to the central body, moving at `velocity`.
+pub fn burn_vector(position: DVec3, velocity: DVec3, delta_v: DVec3) -> DVec3 {
+    let prograde = velocity.normalize_or_zero();
+    let normal = position.cross(velocity).normalize_or_zero();
+    let radial = prograde.cross(normal);
+    prograde * delta_v.x + normal * delta_v.y + radial * delta_v.z
+}
+
+/// Speed changes in AU/day for a Hohmann transfer from a circular orbit of radius `r1` to one of
+/// radius `r2` around a body with gravitational parameter `mu`: onto the transfer orbit at `r1`,
+/// and onto the circular orbit at `r2`. Both are positive going outwards and negative going
+/// inwards.
+pub fn hohmann_delta_v(mu: f64, r1: f64, r2: f64) -> (f64, f64) {
+    let semi_major_axis = 0.5 * (r1 + r2);
+    let speed = |r: f64| (mu * (2.0 / r - 1.0 / semi_major_axis)).sqrt();
+    (speed(r1) - (mu / r1).sqrt(), (mu / r2).sqrt() - speed(r2))
+}
+
+/// A Hohmann transfer to a body orbiting the craft's central body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HohmannTransfer {
+    /// The burn onto the transfer orbit.
+    pub departure: ManeuverNode,
+    /// Prograde speed change in AU/day that would match the target's orbit on arrival. By then
+    /// the craft is in the target's sphere of influence, so it is not planned as a node.
+    pub arrival_delta_v: f64,
+    /// Days from the departure burn to arrival.
+    pub duration: f64,
+}
+
+/// Hohmann transfer from a craft at `position` moving at `velocity` to the body `target`, which
+/// must orbit the body the craft is orbiting. The burn waits until the target is placed so that
+/// it reaches the point opposite the burn just as the transfer orbit's apoapsis (or periapsis,
+/// going inwards) does. The craft's and the target's orbits are assumed to be close to circular
+/// and in the same plane.
+pub fn hohmann_transfer(
+    field: &GravityField,
+    position: DVec3,
+    velocity: DVec3,
+    target: usize,
+) -> Option<HohmannTransfer> {
+    let center = field.dominant(position, field.days)?;
+    let body = &field.attractors[center];
+    if field.attractors[target].parent != Some(center) {
+        return None;
+    }
+    let (center_position, center_velocity) = field.state(center, field.days);
+    let (relative, relative_velocity) = (position - center_position, velocity - center_velocity);
+    let elements = OrbitalElements::from_state_vector(relative, relative_velocity, body.gm)?;
+    if elements.eccentricity >= 1.0 {
+        return None;
+    }
+
+    let r1 = relative.length();
+    let (target_position, target_velocity) = field.state(target, field.days);
+    let to_target = target_position - center_position;
+    let r2 = to_target.length();
+    let (departure_delta_v, arrival_delta_v) = hohmann_delta_v(body.gm, r1, r2);
+    let duration = PI * ((r1 + r2).powi(3) / (8.0 * body.gm)).sqrt();
+
+    // Angles around the craft's orbit normal. The target, `phase` ahead of the craft now, has to
+    // be half a turn minus its travel during the transfer ahead at the burn.
+    let normal = relative.cross(relative_velocity).normalize();
+    let phase = normal
+        .dot(relative.cross(to_target))
+        .atan2(relative.dot(to_target));
+    let craft_rate = elements.mean_motion(body.gm);
+    let target_rate = normal
+        .dot(to_target.cross(target_velocity - center_velocity))
+        .signum()
+        * (body.gm / r2.powi(3)).sqrt();
+    let lead = PI - target_rate * duration;
+    let closing = craft_rate - target_rate;
+    let wait = ((phase - lead) * closing.signum()).rem_euclid(TAU) / closing.abs();
+    Some(HohmannTransfer {
+        departure: ManeuverNode {
+            time: wait,
+            delta_v: DVec3::new(departure_delta_v, 0.0, 0.0),
+        },
+        arrival_delta_v,
+        duration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kepler::GM_SUN,
+        spacecraft::{KM_PER_AU, SECONDS_PER_DAY},
+    };
+
+    #[test]
+    fn earth_to_mars_hohmann_matches_the_textbook() {
+        let km_per_s = KM_PER_AU / SECONDS_PER_DAY;
+        let (departure, arrival) = hohmann_delta_v(GM_SUN, 1.0, 1.524);
+        assert!(
+            (departure * km_per_s - 2.94).abs() < 0.01,
+            "{}",
+            departure * km_per_s
+        );
+        assert!(
+            (arrival * km_per_s - 2.65).abs() < 0.01,
+            "{}",
+            arrival * km_per_s
+        );
+
+        // Coming back takes the same burns the other way round.
+        let (back_departure, back_arrival) = hohmann_delta_v(GM_SUN, 1.524, 1.0);
+        assert!((back_departure + arrival).abs() < 1e-12);
+        assert!((back_arrival + departure).abs() < 1e-12);
+    }
+
+    #[test]
+    fn burn_vector_follows_the_orbital_frame() {
+        let position = DVec3::new(2.0, 0.0, 0.0);
+        let velocity = DVec3::new(0.0, 0.5, 0.0);
+        let burn = burn_vector(position, velocity, DVec3::new(1.0, 2.0, 3.0));
+        // Prograde +Y, normal +Z and radial out along +X.
+        assert!(burn.abs_diff_eq(DVec3::new(3.0, 1.0, 2.0), 1e-12));
+    }
+}
@@ -0,0 +1,689 @@
+//! A free n-body sandbox.
+//!
+//! Unlike the built-in system, where only the craft and small bodies move, every body here pulls
+//! on every other one and moves. Bodies are spheres and can hit each other: after each drift of
+//! the integrator, touching pairs that are still approaching are found and resolved with the
+//! chosen [`CollisionOutcome`], and every collision is logged.
+//!
+//! Candidate pairs come from a spatial hash with cells as wide as the largest body, so only bodies
+//! in neighbouring cells are ever compared and the broad phase stays linear in the body count.
+//!
+//! Gravity is summed directly over all pairs, or for large body counts approximated with a
+//! Barnes-Hut tree (see [`crate::barnes_hut`]).
+//!
+//! Units are heliocentric ecliptic AU and days like in [`crate::kepler`], with masses as
+//! gravitational parameters in AU³/day².
+
+use std::collections::{HashMap, VecDeque};
+
+use glam::{DVec3, IVec3};
+
+use crate::{
+    barnes_hut::tree_accelerations,
+    celestial::ecliptic_to_scene,
+    frustum::Frustum,
+    kepler::{GM_SUN, OrbitalElements},
+    particles::ParticleInstance,
+    random::Rng,
+    system::AU,
+};
+
+/// Collisions kept in the log.
+const MAX_EVENTS: usize = 200;
+/// Fragments a body breaks into.
+const FRAGMENTS: usize = 4;
+/// Bodies smaller than this in AU do not break up further and merge instead.
+const MIN_FRAGMENT_RADIUS: f64 = 5e-4;
+/// Collisions between bodies of more different masses than this always merge, so small bodies
+/// hitting the star or a planet are swallowed instead of breaking it up.
+const MIN_FRAGMENT_MASS_RATIO: f64 = 0.01;
+const MAX_STEPS_PER_FRAME: usize = 16;
+/// Total gravitational parameter, Plummer radius in AU and height above the ecliptic in AU of the
+/// star cluster.
+const CLUSTER_GM: f64 = 200.0 * GM_SUN;
+const CLUSTER_SCALE: f64 = 4.0;
+const CLUSTER_HEIGHT: f64 = 30.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionOutcome {
+    /// The bodies become one, conserving mass, momentum and volume.
+    Merge,
+    /// The bodies bounce off each other without losing energy.
+    Bounce,
+    /// The bodies merge and the result breaks up into smaller pieces flying apart.
+    Fragment,
+}
+
+impl CollisionOutcome {
+    pub const ALL: [Self; 3] = [Self::Merge, Self::Bounce, Self::Fragment];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CollisionOutcome::Merge => "Merge",
+            CollisionOutcome::Bounce => "Bounce",
+            CollisionOutcome::Fragment => "Fragment",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForceMethod {
+    /// Every pair, exact and O(n²).
+    Direct,
+    /// Octree approximation, O(n log n).
+    BarnesHut,
+}
+
+impl ForceMethod {
+    pub const ALL: [Self; 2] = [Self::Direct, Self::BarnesHut];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ForceMethod::Direct => "Direct summation",
+            ForceMethod::BarnesHut => "Barnes-Hut",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NBody {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    /// Gravitational parameter in AU³/day².
+    pub gm: f64,
+    /// In AU.
+    pub radius: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    /// Simulation days since the sandbox started.
+    pub time: f64,
+    pub outcome: CollisionOutcome,
+    /// Gravitational parameters of the two bodies, the larger first.
+    pub masses: (f64, f64),
+    /// In AU/day.
+    pub impact_speed: f64,
+    /// Bodies left by the collision.
+    pub remnants: usize,
+}
+
+pub struct NBodySimulation {
+    pub bodies: Vec<NBody>,
+    /// Index of the star at the sun's place, which the solar system's sun already shows. It keeps
+    /// its place in the bodies it swallows and is forgotten if it breaks up.
+    pub star: Option<usize>,
+    pub force_method: ForceMethod,
+    /// Opening angle for [`ForceMethod::BarnesHut`].
+    pub theta: f64,
+    /// Days since the sandbox started.
+    pub time: f64,
+    /// Integration step in days.
+    pub step: f64,
+    pub outcome: CollisionOutcome,
+    /// Most recent last.
+    pub events: VecDeque<CollisionEvent>,
+    pub collisions: usize,
+    rng: Rng,
+}
+
+impl NBodySimulation {
+    /// A star with `count` planetesimals on nearly circular orbits between 0.6 and 2 AU around
+    /// it, with radii exaggerated so that they meet often.
+    pub fn protoplanetary_disk(count: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut bodies = vec![NBody {
+            position: DVec3::ZERO,
+            velocity: DVec3::ZERO,
+            gm: GM_SUN,
+            radius: 0.08,
+        }];
+        for _ in 0..count {
+            let elements = OrbitalElements {
+                semi_major_axis: rng.range(0.6, 2.0),
+                eccentricity: rng.range(0.0, 0.08),
+                inclination: rng.range(0.0, 0.03),
+                longitude_of_ascending_node: rng.range(0.0, std::f64::consts::TAU),
+                argument_of_periapsis: rng.range(0.0, std::f64::consts::TAU),
+                mean_anomaly: rng.range(0.0, std::f64::consts::TAU),
+            };
+            let (position, velocity) = elements.state_vector(GM_SUN);
+            let radius = rng.range(0.001, 0.004);
+            bodies.push(NBody {
+                position,
+                velocity,
+                // Rocky density scaled up with the radii, roughly an Earth mass at 0.004 AU.
+                gm: 9e-10 * (radius / 0.004).powi(3),
+                radius,
+            });
+        }
+        Self::new(bodies, Some(0), rng)
+    }
+
+    /// `count` equal stars in a Plummer sphere, sampled as in Aarseth, Hénon & Wielen (1974),
+    /// floating [`CLUSTER_HEIGHT`] above the sun.
+    pub fn star_cluster(count: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let gm = CLUSTER_GM / count.max(1) as f64;
+        let center = DVec3::Z * CLUSTER_HEIGHT;
+        let bodies = (0..count)
+            .map(|_| {
+                let mass_fraction = rng.range(1e-3, 0.999);
+                let r = CLUSTER_SCALE / (mass_fraction.powf(-2.0 / 3.0) - 1.0).sqrt();
+                // Speed as a fraction of the local escape speed, by rejection from q² (1 - q²)^3.5.
+                let q = loop {
+                    let (q, y) = (rng.next_f64(), rng.range(0.0, 0.1));
+                    if y < q * q * (1.0 - q * q).powf(3.5) {
+                        break q;
+                    }
+                };
+                let escape_speed =
+                    (2.0 * CLUSTER_GM).sqrt() * (r * r + CLUSTER_SCALE * CLUSTER_SCALE).powf(-0.25);
+                NBody {
+                    position: center + random_direction(&mut rng) * r,
+                    velocity: random_direction(&mut rng) * (q * escape_speed),
+                    gm,
+                    radius: 0.02,
+                }
+            })
+            .collect();
+        Self::new(bodies, None, rng)
+    }
+
+    fn new(bodies: Vec<NBody>, star: Option<usize>, rng: Rng) -> Self {
+        Self {
+            force_method: if bodies.len() > 1000 {
+                ForceMethod::BarnesHut
+            } else {
+                ForceMethod::Direct
+            },
+            bodies,
+            star,
+            theta: 0.5,
+            time: 0.0,
+            step: 0.5,
+            outcome: CollisionOutcome::Merge,
+            events: VecDeque::new(),
+            collisions: 0,
+            rng,
+        }
+    }
+
+    pub fn accelerations(&self) -> Vec<DVec3> {
+        match self.force_method {
+            ForceMethod::Direct => direct_accelerations(&self.bodies),
+            ForceMethod::BarnesHut => tree_accelerations(&self.bodies, self.theta),
+        }
+    }
+
+    /// Advances the simulation by `dt_days`, in steps of at most [`NBodySimulation::step`].
+    pub fn update(&mut self, dt_days: f64) {
+        if dt_days <= 0.0 {
+            return;
+        }
+        let steps = (dt_days / self.step).ceil().min(MAX_STEPS_PER_FRAME as f64) as usize;
+        let step = dt_days / steps as f64;
+        for _ in 0..steps {
+            self.leapfrog(step);
+        }
+    }
+
+    /// One kick-drift-kick step, resolving collisions after the drift.
+    fn leapfrog(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        for (body, acceleration) in self.bodies.iter_mut().zip(&accelerations) {
+            body.velocity += *acceleration * (dt / 2.0);
+            body.position += body.velocity * dt;
+        }
+        self.time += dt;
+        self.resolve_collisions();
+        let accelerations = self.accelerations();
+        for (body, acceleration) in self.bodies.iter_mut().zip(&accelerations) {
+            body.velocity += *acceleration * (dt / 2.0);
+        }
+    }
+
+    fn resolve_collisions(&mut self) {
+        let pairs = colliding_pairs(&self.bodies);
+        if pairs.is_empty() {
+            return;
+        }
+        // A body takes part in at most one collision per step; the rest wait for the next one.
+        let mut involved = vec![false; self.bodies.len()];
+        let mut removed = vec![false; self.bodies.len()];
+        let mut created = Vec::new();
+        for (i, j) in pairs {
+            if involved[i] || involved[j] {
+                continue;
+            }
+            involved[i] = true;
+            involved[j] = true;
+            let (a, b) = (self.bodies[i], self.bodies[j]);
+            let impact_speed = (a.velocity - b.velocity).length();
+            let outcome = match self.outcome {
+                CollisionOutcome::Fragment
+                    if a.gm.min(b.gm) < MIN_FRAGMENT_MASS_RATIO * a.gm.max(b.gm) =>
+                {
+                    CollisionOutcome::Merge
+                }
+                outcome => outcome,
+            };
+            let remnants = match outcome {
+                CollisionOutcome::Bounce => {
+                    (self.bodies[i], self.bodies[j]) = bounce(a, b);
+                    2
+                }
+                CollisionOutcome::Merge => {
+                    let (kept, gone) = if self.star == Some(j) { (j, i) } else { (i, j) };
+                    self.bodies[kept] = merge(a, b);
+                    removed[gone] = true;
+                    1
+                }
+                CollisionOutcome::Fragment => {
+                    let fragments = fragment(a, b, &mut self.rng);
+                    if self.star == Some(i) || self.star == Some(j) {
+                        self.star = None;
+                    }
+                    removed[i] = true;
+                    removed[j] = true;
+                    let count = fragments.len();
+                    created.extend(fragments);
+                    count
+                }
+            };
+            self.collisions += 1;
+            if self.events.len() == MAX_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(CollisionEvent {
+                time: self.time,
+                outcome,
+                masses: (a.gm.max(b.gm), a.gm.min(b.gm)),
+                impact_speed,
+                remnants,
+            });
+        }
+        self.star = self
+            .star
+            .map(|star| star - removed[..star].iter().filter(|&&gone| gone).count());
+        let mut index = 0;
+        self.bodies.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+        self.bodies.extend(created);
+    }
+
+    /// Appends every body but the star to `instances` if it is inside `frustum`.
+    pub fn collect_particles(&self, frustum: &Frustum, instances: &mut Vec<ParticleInstance>) {
+        for (_, body) in self
+            .bodies
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| Some(index) != self.star)
+        {
+            let position = ecliptic_to_scene(body.position) * AU;
+            let radius = body.radius as f32 * AU;
+            if !frustum.intersects_sphere(position, radius) {
+                continue;
+            }
+            instances.push(ParticleInstance {
+                particle_position: position.to_array(),
+                particle_color: [1.0, 0.75, 0.5],
+                particle_size: radius,
+            });
+        }
+    }
+}
+
+/// Pull of every body on every other one, by direct summation. Bodies closer than their radii
+/// feel the pull from the surface, which keeps passing bodies from flinging each other away.
+pub fn direct_accelerations(bodies: &[NBody]) -> Vec<DVec3> {
+    let mut accelerations = vec![DVec3::ZERO; bodies.len()];
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let offset = bodies[j].position - bodies[i].position;
+            let distance = offset.length().max(bodies[i].radius + bodies[j].radius);
+            let pull = offset / (distance * distance * distance);
+            accelerations[i] += pull * bodies[j].gm;
+            accelerations[j] -= pull * bodies[i].gm;
+        }
+    }
+    accelerations
+}
+
+/// Pairs of bodies that touch and are still moving towards each other, found with a spatial hash
+/// whose cells are as wide as the largest body. Each pair is listed once, lower index first.
+pub fn colliding_pairs(bodies: &[NBody]) -> Vec<(usize, usize)> {
+    let cell_size = 2.0
+        * bodies
+            .iter()
+            .map(|body| body.radius)
+            .fold(f64::MIN_POSITIVE, f64::max);
+    let cell_of = |position: DVec3| (position / cell_size).floor().as_ivec3();
+    let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (index, body) in bodies.iter().enumerate() {
+        cells.entry(cell_of(body.position)).or_default().push(index);
+    }
+
+    let mut pairs = Vec::new();
+    for (i, body) in bodies.iter().enumerate() {
+        let cell = cell_of(body.position);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(others) = cells.get(&(cell + IVec3::new(x, y, z))) else {
+                        continue;
+                    };
+                    for &j in others.iter().filter(|&&j| j > i) {
+                        let other = &bodies[j];
+                        let offset = other.position - body.position;
+                        let approaching = offset.dot(other.velocity - body.velocity) < 0.0;
+                        if approaching && offset.length() < body.radius + other.radius {
+                            pairs.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pairs
+}
+
+fn random_direction(rng: &mut Rng) -> DVec3 {
+    let z = rng.range(-1.0, 1.0);
+    let angle = rng.range(0.0, std::f64::consts::TAU);
+    let r = (1.0 - z * z).sqrt();
+    DVec3::new(r * angle.cos(), r * angle.sin(), z)
+}
+
+/// One body with the mass, momentum and volume of both, at their center of mass.
+fn merge(a: NBody, b: NBody) -> NBody {
+    let gm = a.gm + b.gm;
+    NBody {
+        position: (a.position * a.gm + b.position * b.gm) / gm,
+        velocity: (a.velocity * a.gm + b.velocity * b.gm) / gm,
+        gm,
+        radius: (a.radius.powi(3) + b.radius.powi(3)).cbrt(),
+    }
+}
+
+/// Elastic collision: the velocity components along the line between the centers are exchanged
+/// as for a head-on collision, and the bodies are pushed apart until they just touch.
+fn bounce(mut a: NBody, mut b: NBody) -> (NBody, NBody) {
+    let normal = (b.position - a.position).normalize_or(DVec3::X);
+    let approach = (a.velocity - b.velocity).dot(normal);
+    let total = a.gm + b.gm;
+    a.velocity -= normal * (2.0 * b.gm / total * approach);
+    b.velocity += normal * (2.0 * a.gm / total * approach);
+    let overlap = a.radius + b.radius - a.position.distance(b.position);
+    a.position -= normal * (overlap * b.gm / total);
+    b.position += normal * (overlap * a.gm / total);
+    (a, b)
+}
+
+/// Mutual potential energy of `bodies`, softened like [`direct_accelerations`].
+fn potential_energy(bodies: &[NBody]) -> f64 {
+    let mut energy = 0.0;
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let distance = bodies[i]
+                .position
+                .distance(bodies[j].position)
+                .max(bodies[i].radius + bodies[j].radius);
+            energy -= bodies[i].gm * bodies[j].gm / distance;
+        }
+    }
+    energy
+}
+
+/// Breaks `a` and `b` into [`FRAGMENTS`] equal pieces flying apart in opposite pairs, which
+/// conserves mass, momentum and volume. The pieces start touching around the center of mass and
+/// fly apart with the kinetic energy of the impact plus the potential energy freed by packing
+/// them tighter than the two bodies were, so the collision conserves energy too. Bodies too small
+/// to break, or without the energy to fly apart, merge instead.
+fn fragment(a: NBody, b: NBody, rng: &mut Rng) -> Vec<NBody> {
+    let merged = merge(a, b);
+    let radius = merged.radius / (FRAGMENTS as f64).cbrt();
+    if radius < MIN_FRAGMENT_RADIUS {
+        return vec![merged];
+    }
+    let axis = DVec3::new(
+        rng.range(-1.0, 1.0),
+        rng.range(-1.0, 1.0),
+        rng.range(-1.0, 1.0),
+    )
+    .normalize_or(DVec3::X);
+    let (first, second) = axis.any_orthonormal_pair();
+    let directions = [axis, -axis, first, -first, second, -second];
+    // Pieces along perpendicular directions touch at this distance from the center.
+    let offset = std::f64::consts::SQRT_2 * radius;
+    let mut pieces: Vec<_> = directions
+        .into_iter()
+        .take(FRAGMENTS)
+        .map(|direction| NBody {
+            position: merged.position + direction * offset,
+            velocity: merged.velocity,
+            gm: merged.gm / FRAGMENTS as f64,
+            radius,
+        })
+        .collect();
+
+    let reduced_gm = a.gm * b.gm / merged.gm;
+    let impact_energy = 0.5 * reduced_gm * a.velocity.distance_squared(b.velocity);
+    let energy = impact_energy + potential_energy(&[a, b]) - potential_energy(&pieces);
+    if energy <= 0.0 {
+        return vec![merged];
+    }
+    let speed = (2.0 * energy / merged.gm).sqrt();
+    for (piece, direction) in pieces.iter_mut().zip(directions) {
+        piece.velocity += direction * speed;
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(position: DVec3, velocity: DVec3, gm: f64, radius: f64) -> NBody {
+        NBody {
+            position,
+            velocity,
+            gm,
+            radius,
+        }
+    }
+
+    /// Bodies packed into a small box around the origin, so that many of them overlap.
+    fn random_bodies(count: usize, seed: u64) -> Vec<NBody> {
+        let mut rng = Rng::new(seed);
+        (0..count)
+            .map(|_| {
+                let mut coordinate = || rng.range(-0.5, 0.5);
+                let position = DVec3::new(coordinate(), coordinate(), coordinate());
+                let velocity = DVec3::new(coordinate(), coordinate(), coordinate());
+                let gm = rng.range(1e-10, 1e-8);
+                let radius = rng.range(0.002, 0.04);
+                body(position, velocity, gm, radius)
+            })
+            .collect()
+    }
+
+    fn total_gm(bodies: &[NBody]) -> f64 {
+        bodies.iter().map(|body| body.gm).sum()
+    }
+
+    fn total_momentum(bodies: &[NBody]) -> DVec3 {
+        bodies.iter().map(|body| body.velocity * body.gm).sum()
+    }
+
+    fn total_volume(bodies: &[NBody]) -> f64 {
+        bodies.iter().map(|body| body.radius.powi(3)).sum()
+    }
+
+    fn assert_conserved(before: &[NBody], after: &[NBody]) {
+        let gm = total_gm(before);
+        assert!((total_gm(after) - gm).abs() < 1e-12 * gm);
+        let momentum = total_momentum(before);
+        let scale = before
+            .iter()
+            .map(|body| body.velocity.length() * body.gm)
+            .sum::<f64>();
+        assert!(total_momentum(after).abs_diff_eq(momentum, 1e-12 * scale));
+    }
+
+    #[test]
+    fn colliding_pairs_match_brute_force() {
+        for seed in 0..5 {
+            let bodies = random_bodies(400, seed);
+            let mut expected = Vec::new();
+            for i in 0..bodies.len() {
+                for j in i + 1..bodies.len() {
+                    let (a, b) = (&bodies[i], &bodies[j]);
+                    let offset = b.position - a.position;
+                    let approaching = offset.dot(b.velocity - a.velocity) < 0.0;
+                    if approaching && offset.length() < a.radius + b.radius {
+                        expected.push((i, j));
+                    }
+                }
+            }
+            let mut pairs = colliding_pairs(&bodies);
+            pairs.sort_unstable();
+            assert!(!expected.is_empty());
+            assert_eq!(pairs, expected, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn separating_bodies_do_not_collide() {
+        let a = body(DVec3::ZERO, DVec3::NEG_X, 1e-9, 0.01);
+        let b = body(DVec3::X * 0.01, DVec3::X, 1e-9, 0.01);
+        assert!(colliding_pairs(&[a, b]).is_empty());
+        let b = body(DVec3::X * 0.01, DVec3::NEG_X * 2.0, 1e-9, 0.01);
+        assert_eq!(colliding_pairs(&[a, b]), [(0, 1)]);
+    }
+
+    #[test]
+    fn merge_conserves_mass_momentum_and_volume() {
+        let a = body(DVec3::ZERO, DVec3::new(1.0, 2.0, 0.0), 3e-9, 0.02);
+        let b = body(DVec3::X * 0.03, DVec3::new(-1.0, 0.0, 4.0), 1e-9, 0.015);
+        let merged = merge(a, b);
+        assert_conserved(&[a, b], &[merged]);
+        assert!((total_volume(&[merged]) - total_volume(&[a, b])).abs() < 1e-18);
+        assert!(merged.position.abs_diff_eq(DVec3::X * 0.0075, 1e-15));
+    }
+
+    #[test]
+    fn bounce_conserves_momentum_and_energy() {
+        let a = body(DVec3::ZERO, DVec3::new(1.0, 0.5, 0.0), 3e-9, 0.02);
+        let b = body(
+            DVec3::new(0.03, 0.01, 0.0),
+            DVec3::new(-1.0, 0.0, 0.2),
+            1e-9,
+            0.015,
+        );
+        let (after_a, after_b) = bounce(a, b);
+        assert_conserved(&[a, b], &[after_a, after_b]);
+        let energy = |bodies: [NBody; 2]| {
+            bodies
+                .iter()
+                .map(|body| body.gm * body.velocity.length_squared())
+                .sum::<f64>()
+        };
+        assert!((energy([after_a, after_b]) - energy([a, b])).abs() < 1e-12 * energy([a, b]));
+        // They end up touching and moving apart.
+        let offset = after_b.position - after_a.position;
+        assert!((offset.length() - (a.radius + b.radius)).abs() < 1e-12);
+        assert!(offset.dot(after_b.velocity - after_a.velocity) > 0.0);
+        assert!(colliding_pairs(&[after_a, after_b]).is_empty());
+    }
+
+    fn total_energy(bodies: &[NBody]) -> f64 {
+        let kinetic = bodies
+            .iter()
+            .map(|body| 0.5 * body.gm * body.velocity.length_squared())
+            .sum::<f64>();
+        kinetic + potential_energy(bodies)
+    }
+
+    #[test]
+    fn fragments_conserve_mass_momentum_volume_and_energy() {
+        let mut rng = Rng::new(3);
+        let a = body(DVec3::ONE, DVec3::new(0.1, -0.2, 0.3), 3e-9, 0.008);
+        let b = body(
+            DVec3::ONE + DVec3::new(0.012, 0.0, 0.0),
+            DVec3::new(-0.2, 0.1, 0.3),
+            1e-9,
+            0.006,
+        );
+        let pieces = fragment(a, b, &mut rng);
+        assert_eq!(pieces.len(), FRAGMENTS);
+        assert_conserved(&[a, b], &pieces);
+        assert!((total_volume(&pieces) - total_volume(&[a, b])).abs() < 1e-18);
+        let energy = total_energy(&[a, b]);
+        assert!((total_energy(&pieces) - energy).abs() < 1e-12 * energy.abs());
+        // The pieces touch without overlapping and fly apart.
+        for i in 0..pieces.len() {
+            for j in i + 1..pieces.len() {
+                let distance = pieces[i].position.distance(pieces[j].position);
+                assert!(distance > 2.0 * pieces[i].radius * (1.0 - 1e-12));
+            }
+        }
+        assert!(colliding_pairs(&pieces).is_empty());
+
+        // Without any impact speed the pieces still fly apart on the energy freed by packing them.
+        let resting = body(b.position, a.velocity, b.gm, b.radius);
+        let gentle = fragment(a, resting, &mut rng);
+        assert_eq!(gentle.len(), FRAGMENTS);
+        let energy = total_energy(&[a, resting]);
+        assert!((total_energy(&gentle) - energy).abs() < 1e-12 * energy.abs());
+        assert!(colliding_pairs(&gentle).is_empty());
+
+        // Too small to break.
+        let pebble = body(DVec3::ONE, DVec3::ZERO, 1e-12, MIN_FRAGMENT_RADIUS);
+        assert_eq!(fragment(pebble, pebble, &mut rng).len(), 1);
+    }
+
+    #[test]
+    fn collisions_conserve_mass_and_momentum_for_every_outcome() {
+        for outcome in CollisionOutcome::ALL {
+            let bodies = random_bodies(300, 11);
+            let mut simulation = NBodySimulation::new(bodies.clone(), None, Rng::new(5));
+            simulation.outcome = outcome;
+            simulation.resolve_collisions();
+            assert!(simulation.collisions > 0, "{}", outcome.name());
+            assert_eq!(simulation.events.len(), simulation.collisions);
+            assert_conserved(&bodies, &simulation.bodies);
+            let remnants: usize = simulation.events.iter().map(|event| event.remnants).sum();
+            let expected = bodies.len() - 2 * simulation.collisions + remnants;
+            assert_eq!(simulation.bodies.len(), expected, "{}", outcome.name());
+        }
+    }
+
+    #[test]
+    fn lopsided_collisions_merge_instead_of_fragmenting() {
+        let big = body(DVec3::ZERO, DVec3::ZERO, 1e-8, 0.02);
+        let small = body(DVec3::X * 0.025, DVec3::NEG_X, 1e-11, 0.01);
+        let mut simulation = NBodySimulation::new(vec![big, small], None, Rng::new(1));
+        simulation.outcome = CollisionOutcome::Fragment;
+        simulation.resolve_collisions();
+        assert_eq!(simulation.bodies.len(), 1);
+        assert_eq!(simulation.events[0].outcome, CollisionOutcome::Merge);
+    }
+
+    #[test]
+    fn star_is_tracked_through_collisions() {
+        let star = body(DVec3::ZERO, DVec3::ZERO, 1e-6, 0.05);
+        let planetesimal = body(DVec3::X * 0.06, DVec3::NEG_X, 1e-10, 0.02);
+        let a = body(DVec3::Y, DVec3::X, 1e-9, 0.01);
+        let b = body(DVec3::Y + DVec3::X * 0.015, DVec3::NEG_X, 1e-9, 0.01);
+        // The pair before the star merges into one body and the star swallows the body before it.
+        let bodies = vec![a, b, planetesimal, star];
+        let mut simulation = NBodySimulation::new(bodies, Some(3), Rng::new(2));
+        simulation.resolve_collisions();
+        assert_eq!(simulation.bodies.len(), 2);
+        assert_eq!(simulation.star, Some(1));
+        assert_eq!(simulation.bodies[1].gm, star.gm + planetesimal.gm);
+    }
+}
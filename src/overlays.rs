@@ -0,0 +1,442 @@
+//! Teaching overlays: constellation stick figures, coordinate grids on the celestial sphere and
+//! the ecliptic plane.
+//!
+//! Sphere overlays are drawn like the stars, with a rotation-only view on the far plane. The
+//! ecliptic plane is real geometry through the origin and uses the full view. Labels are painted
+//! by egui at the projected screen position of their direction.
+
+use std::{collections::HashMap, fs};
+
+use anyhow::{Context, Result, anyhow};
+use glam::{DVec3, Mat4, Vec3, Vec4};
+use glium::{Display, Surface, glutin::surface::WindowSurface};
+use log::info;
+
+use crate::{
+    celestial::{
+        ecliptic_to_scene, equatorial_to_ecliptic, galactic_to_equatorial, spherical_to_cartesian,
+    },
+    shaders::ProgramDesc,
+    starfield::Star,
+};
+
+const CONSTELLATIONS_PATH: &str = "assets/catalog/constellations.csv";
+
+pub const OVERLAY_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "overlay.vert",
+    fragment: "overlay.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+/// Radius of the translucent ecliptic disc in scene units.
+const ECLIPTIC_PLANE_RADIUS: f32 = 5000.0;
+
+/// Angular length of the straight pieces grid circles are made of.
+const GRID_SEGMENT_DEG: f64 = 2.0;
+
+const CONSTELLATION_COLOR: [f32; 4] = [0.45, 0.6, 1.0, 0.6];
+const EQUATORIAL_COLOR: [f32; 4] = [0.3, 0.75, 0.9, 0.3];
+const ECLIPTIC_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 0.3];
+const GALACTIC_COLOR: [f32; 4] = [0.8, 0.45, 0.95, 0.3];
+const ECLIPTIC_PLANE_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 0.06];
+
+/// Which overlays are shown, edited from the settings panel.
+pub struct OverlaySettings {
+    pub constellations: bool,
+    pub equatorial_grid: bool,
+    pub ecliptic_grid: bool,
+    pub galactic_grid: bool,
+    pub ecliptic_plane: bool,
+    pub labels: bool,
+}
+
+impl OverlaySettings {
+    fn shows(&self, kind: OverlayKind) -> bool {
+        match kind {
+            OverlayKind::Constellations => self.constellations,
+            OverlayKind::EquatorialGrid => self.equatorial_grid,
+            OverlayKind::EclipticGrid => self.ecliptic_grid,
+            OverlayKind::GalacticGrid => self.galactic_grid,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OverlayKind {
+    Constellations,
+    EquatorialGrid,
+    EclipticGrid,
+    GalacticGrid,
+}
+
+#[derive(Copy, Clone)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    /// sRGB with straight alpha.
+    pub color: [f32; 4],
+}
+
+implement_vertex!(LineVertex, position, color);
+
+/// A constellation figure from the catalog, with segments as pairs of Hipparcos numbers.
+#[derive(Clone, Debug)]
+pub struct Constellation {
+    pub name: String,
+    pub segments: Vec<(u32, u32)>,
+}
+
+/// Parses the constellation CSV. Lines starting with `#` are comments and the first remaining
+/// line is the header.
+pub fn parse_constellations(csv: &str) -> Result<Vec<Constellation>> {
+    csv.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .skip(1)
+        .map(|(number, line)| {
+            parse_constellation(line).with_context(|| format!("line {}", number + 1))
+        })
+        .collect()
+}
+
+fn parse_constellation(line: &str) -> Result<Constellation> {
+    let fields: Vec<_> = line.split(',').map(str::trim).collect();
+    let [_abbr, name, segments] = fields[..] else {
+        return Err(anyhow!("expected 3 fields, got {}", fields.len()));
+    };
+    let segments = segments
+        .split_whitespace()
+        .map(|segment| {
+            let (a, b) = segment
+                .split_once('-')
+                .ok_or_else(|| anyhow!("malformed segment {segment:?}"))?;
+            Ok((a.parse()?, b.parse()?))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Constellation {
+        name: name.to_owned(),
+        segments,
+    })
+}
+
+struct Label {
+    text: String,
+    direction: Vec3,
+    kind: OverlayKind,
+}
+
+pub struct Overlays {
+    constellation_lines: glium::VertexBuffer<LineVertex>,
+    equatorial_grid: glium::VertexBuffer<LineVertex>,
+    ecliptic_grid: glium::VertexBuffer<LineVertex>,
+    galactic_grid: glium::VertexBuffer<LineVertex>,
+    ecliptic_plane: glium::VertexBuffer<LineVertex>,
+    labels: Vec<Label>,
+}
+
+impl Overlays {
+    /// Builds all overlays. Constellation figures are resolved against `stars`; segments whose
+    /// stars are missing from the catalog are skipped.
+    pub fn init(display: &Display<WindowSurface>, stars: &[Star]) -> Result<Self> {
+        let csv = fs::read_to_string(CONSTELLATIONS_PATH)
+            .with_context(|| format!("cannot read {CONSTELLATIONS_PATH}"))?;
+        let constellations = parse_constellations(&csv)?;
+        let directions: HashMap<u32, Vec3> = stars
+            .iter()
+            .map(|star| (star.hip, star.direction()))
+            .collect();
+
+        let mut labels = Vec::new();
+        let mut constellation_lines = Vec::new();
+        for constellation in &constellations {
+            let mut centroid = Vec3::ZERO;
+            for (a, b) in &constellation.segments {
+                let (Some(&a), Some(&b)) = (directions.get(a), directions.get(b)) else {
+                    continue;
+                };
+                centroid += a + b;
+                constellation_lines.push(line_vertex(a, CONSTELLATION_COLOR));
+                constellation_lines.push(line_vertex(b, CONSTELLATION_COLOR));
+            }
+            if centroid != Vec3::ZERO {
+                labels.push(Label {
+                    text: constellation.name.clone(),
+                    direction: centroid.normalize(),
+                    kind: OverlayKind::Constellations,
+                });
+            }
+        }
+        info!(
+            "Loaded {} constellation figures from {CONSTELLATIONS_PATH}",
+            constellations.len()
+        );
+
+        let equatorial = |v| ecliptic_to_scene(equatorial_to_ecliptic(v));
+        let ecliptic = ecliptic_to_scene;
+        let galactic = |v| ecliptic_to_scene(equatorial_to_ecliptic(galactic_to_equatorial(v)));
+
+        for hour in (0..24).step_by(2) {
+            labels.push(grid_label(
+                format!("{hour}h"),
+                equatorial,
+                hour as f64 * 15.0,
+                0.0,
+                OverlayKind::EquatorialGrid,
+            ));
+        }
+        for dec in [-60, -30, 30, 60] {
+            labels.push(grid_label(
+                format!("{dec:+}°"),
+                equatorial,
+                0.0,
+                dec as f64,
+                OverlayKind::EquatorialGrid,
+            ));
+        }
+        for longitude in (0..360).step_by(30) {
+            labels.push(grid_label(
+                format!("λ {longitude}°"),
+                ecliptic,
+                longitude as f64,
+                0.0,
+                OverlayKind::EclipticGrid,
+            ));
+        }
+        for longitude in (0..360).step_by(60) {
+            labels.push(grid_label(
+                format!("l {longitude}°"),
+                galactic,
+                longitude as f64,
+                0.0,
+                OverlayKind::GalacticGrid,
+            ));
+        }
+
+        Ok(Self {
+            constellation_lines: glium::VertexBuffer::new(display, &constellation_lines)?,
+            equatorial_grid: glium::VertexBuffer::new(
+                display,
+                &sphere_grid(equatorial, 15.0, 15.0, EQUATORIAL_COLOR),
+            )?,
+            ecliptic_grid: glium::VertexBuffer::new(
+                display,
+                &sphere_grid(ecliptic, 30.0, 15.0, ECLIPTIC_COLOR),
+            )?,
+            galactic_grid: glium::VertexBuffer::new(
+                display,
+                &sphere_grid(galactic, 30.0, 15.0, GALACTIC_COLOR),
+            )?,
+            ecliptic_plane: glium::VertexBuffer::new(display, &ecliptic_disc())?,
+            labels,
+        })
+    }
+
+    /// Draws the enabled overlays. `sky_view` is the view without translation used for
+    /// everything on the celestial sphere.
+    pub fn draw(
+        &self,
+        frame: &mut impl Surface,
+        program: &glium::Program,
+        view: Mat4,
+        sky_view: Mat4,
+        perspective: Mat4,
+        settings: &OverlaySettings,
+    ) -> Result<()> {
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLessOrEqual,
+                write: false,
+                ..Default::default()
+            },
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        if settings.ecliptic_plane {
+            let uniforms = uniform! {
+                view: view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                on_sky: false,
+            };
+            frame.draw(
+                &self.ecliptic_plane,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                program,
+                &uniforms,
+                &params,
+            )?;
+        }
+
+        let uniforms = uniform! {
+            view: sky_view.to_cols_array_2d(),
+            perspective: perspective.to_cols_array_2d(),
+            on_sky: true,
+        };
+        let layers = [
+            (OverlayKind::EquatorialGrid, &self.equatorial_grid),
+            (OverlayKind::EclipticGrid, &self.ecliptic_grid),
+            (OverlayKind::GalacticGrid, &self.galactic_grid),
+            (OverlayKind::Constellations, &self.constellation_lines),
+        ];
+        for (kind, vertex_buffer) in layers {
+            if !settings.shows(kind) {
+                continue;
+            }
+            frame.draw(
+                vertex_buffer,
+                glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+                program,
+                &uniforms,
+                &params,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Paints the labels of the enabled overlays behind all egui windows.
+    /// `sky_view_projection` is `perspective * sky_view`.
+    pub fn paint_labels(
+        &self,
+        ctx: &egui::Context,
+        sky_view_projection: Mat4,
+        settings: &OverlaySettings,
+    ) {
+        if !settings.labels {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let screen = ctx.screen_rect();
+        for label in &self.labels {
+            if !settings.shows(label.kind) {
+                continue;
+            }
+            let Some(position) = project_to_screen(sky_view_projection, label.direction, screen)
+            else {
+                continue;
+            };
+            let color = match label.kind {
+                OverlayKind::Constellations => CONSTELLATION_COLOR,
+                OverlayKind::EquatorialGrid => EQUATORIAL_COLOR,
+                OverlayKind::EclipticGrid => ECLIPTIC_COLOR,
+                OverlayKind::GalacticGrid => GALACTIC_COLOR,
+            };
+            let [r, g, b, _] = color.map(|c| (c * 255.0) as u8);
+            painter.text(
+                position,
+                egui::Align2::CENTER_CENTER,
+                &label.text,
+                egui::FontId::proportional(13.0),
+                egui::Color32::from_rgba_unmultiplied(r, g, b, 200),
+            );
+        }
+    }
+}
+
+/// Projects a point to egui screen coordinates, or `None` if it is behind the camera or off
+/// screen.
+pub fn project_to_screen(
+    view_projection: Mat4,
+    point: Vec3,
+    screen: egui::Rect,
+) -> Option<egui::Pos2> {
+    let clip = view_projection * Vec4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+        return None;
+    }
+    Some(egui::pos2(
+        screen.left() + (ndc.x + 1.0) * 0.5 * screen.width(),
+        screen.top() + (1.0 - ndc.y) * 0.5 * screen.height(),
+    ))
+}
+
+fn line_vertex(position: Vec3, color: [f32; 4]) -> LineVertex {
+    LineVertex {
+        position: position.to_array(),
+        color,
+    }
+}
+
+fn grid_label(
+    text: String,
+    to_scene: impl Fn(DVec3) -> Vec3,
+    longitude_deg: f64,
+    latitude_deg: f64,
+    kind: OverlayKind,
+) -> Label {
+    Label {
+        text,
+        direction: to_scene(spherical_to_cartesian(
+            longitude_deg.to_radians(),
+            latitude_deg.to_radians(),
+        )),
+        kind,
+    }
+}
+
+/// Line list for a longitude/latitude grid in the frame `to_scene` converts from. Meridians stop
+/// short of the poles to keep them from piling up. The equator is drawn brighter.
+pub fn sphere_grid(
+    to_scene: impl Fn(DVec3) -> Vec3,
+    longitude_step_deg: f64,
+    latitude_step_deg: f64,
+    color: [f32; 4],
+) -> Vec<LineVertex> {
+    let point = |longitude: f64, latitude: f64| {
+        to_scene(spherical_to_cartesian(
+            longitude.to_radians(),
+            latitude.to_radians(),
+        ))
+    };
+    let mut vertices = Vec::new();
+    let mut segment = |a: Vec3, b: Vec3, color: [f32; 4]| {
+        vertices.push(line_vertex(a, color));
+        vertices.push(line_vertex(b, color));
+    };
+
+    let pole_gap = latitude_step_deg;
+    let mut longitude = 0.0;
+    while longitude < 360.0 {
+        let mut latitude = -90.0 + pole_gap;
+        while latitude < 90.0 - pole_gap {
+            let next = (latitude + GRID_SEGMENT_DEG).min(90.0 - pole_gap);
+            segment(point(longitude, latitude), point(longitude, next), color);
+            latitude = next;
+        }
+        longitude += longitude_step_deg;
+    }
+
+    let mut latitude = -90.0 + latitude_step_deg;
+    while latitude < 90.0 {
+        let color = if latitude == 0.0 {
+            [color[0], color[1], color[2], (color[3] * 2.5).min(1.0)]
+        } else {
+            color
+        };
+        let mut longitude = 0.0;
+        while longitude < 360.0 {
+            let next = longitude + GRID_SEGMENT_DEG;
+            segment(point(longitude, latitude), point(next, latitude), color);
+            longitude = next;
+        }
+        latitude += latitude_step_deg;
+    }
+    vertices
+}
+
+/// Triangle list for a translucent disc in the ecliptic plane around the origin.
+fn ecliptic_disc() -> Vec<LineVertex> {
+    const SEGMENTS: usize = 128;
+    let rim = |i: usize| {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        Vec3::new(angle.cos(), 0.0, angle.sin()) * ECLIPTIC_PLANE_RADIUS
+    };
+    (0..SEGMENTS)
+        .flat_map(|i| [Vec3::ZERO, rim(i), rim(i + 1)])
+        .map(|position| line_vertex(position, ECLIPTIC_PLANE_COLOR))
+        .collect()
+}
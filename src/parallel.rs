@@ -0,0 +1,64 @@
+//! Splitting work on slices across threads.
+
+use std::{num::NonZero, thread};
+
+/// Calls `work` on consecutive chunks of `items` from scoped threads, one chunk per available
+/// core. `work` gets the index of the chunk's first item in `items` along with the chunk. Chunks
+/// are at least `min_batch` items long, so cheap work on few items does not pay for threads it
+/// cannot use.
+pub fn parallel_chunks_mut<T, F>(items: &mut [T], min_batch: usize, work: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let batch = batch_size(items.len(), min_batch);
+    let work = &work;
+    thread::scope(|scope| {
+        for (index, chunk) in items.chunks_mut(batch).enumerate() {
+            scope.spawn(move || work(index * batch, chunk));
+        }
+    });
+}
+
+/// Items per chunk when `len` items are split over the available cores.
+fn batch_size(len: usize, min_batch: usize) -> usize {
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    len.div_ceil(threads).max(min_batch).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn splits_into_exact_chunks() {
+        let threads = thread::available_parallelism().map_or(1, NonZero::get);
+        for (len, min_batch) in [(0, 1), (1, 1), (7, 1), (1000, 1), (1000, 64), (10, 0)] {
+            let batch = batch_size(len, min_batch);
+            assert!(batch >= min_batch.max(1));
+            // Either every core gets a chunk or the chunks are as small as allowed.
+            assert!(batch == min_batch.max(1) || batch == len.div_ceil(threads));
+
+            let chunks = Mutex::new(Vec::new());
+            let mut items = vec![usize::MAX; len];
+            parallel_chunks_mut(&mut items, min_batch, |start, chunk| {
+                chunks.lock().unwrap().push((start, chunk.len()));
+                for (offset, item) in chunk.iter_mut().enumerate() {
+                    *item = start + offset;
+                }
+            });
+            let mut chunks = chunks.into_inner().unwrap();
+            chunks.sort_unstable();
+            let expected: Vec<_> = (0..len.div_ceil(batch))
+                .map(|index| (index * batch, batch.min(len - index * batch)))
+                .collect();
+            assert_eq!(
+                chunks, expected,
+                "{len} items, at least {min_batch} per chunk"
+            );
+            assert!(items.iter().enumerate().all(|(index, &item)| index == item));
+        }
+    }
+}
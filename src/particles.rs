@@ -0,0 +1,123 @@
+//! Additive particle rendering.
+//!
+//! Particles are camera-facing quads with a soft Gaussian falloff, instanced from one buffer that
+//! is refilled every frame. They add light without writing depth, so they have to be drawn after
+//! every opaque pass and the sky, and the order in which they are drawn does not matter.
+
+use anyhow::{Result, anyhow};
+use glam::Mat4;
+use glium::{
+    Display, DrawParameters, Program, Surface, VertexBuffer,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+};
+
+use crate::{
+    shaders::ProgramDesc,
+    sun::{QUAD_CORNERS, QuadVertex},
+};
+
+pub const PARTICLE_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "particle.vert",
+    fragment: "particle.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+/// Smallest apparent diameter of a particle in pixels.
+const MIN_SIZE_PX: f32 = 2.0;
+
+/// One particle as it is drawn.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ParticleInstance {
+    /// In scene units.
+    pub particle_position: [f32; 3],
+    /// Linear HDR color, already multiplied by the particle's brightness.
+    pub particle_color: [f32; 3],
+    /// Radius in scene units.
+    pub particle_size: f32,
+}
+
+implement_vertex!(
+    ParticleInstance,
+    particle_position,
+    particle_color,
+    particle_size
+);
+
+pub struct ParticleRenderer {
+    quad: VertexBuffer<QuadVertex>,
+    instances: VertexBuffer<ParticleInstance>,
+}
+
+impl ParticleRenderer {
+    pub fn new(display: &Display<WindowSurface>) -> Result<Self> {
+        let quad = QUAD_CORNERS.map(|corner| QuadVertex { corner });
+        Ok(Self {
+            quad: VertexBuffer::new(display, &quad)?,
+            instances: VertexBuffer::empty_dynamic(display, 1024)?,
+        })
+    }
+
+    /// Draws `particles` with additive blending. The instance buffer grows to the largest batch
+    /// seen so far. `pixel_angle` is the angle one pixel covers at the center of the screen.
+    #[expect(clippy::too_many_arguments, reason = "per-frame render state")]
+    pub fn draw(
+        &mut self,
+        display: &Display<WindowSurface>,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+        pixel_angle: f32,
+        particles: &[ParticleInstance],
+    ) -> Result<()> {
+        if particles.is_empty() {
+            return Ok(());
+        }
+        if particles.len() > self.instances.len() {
+            let capacity = particles.len().next_power_of_two();
+            self.instances = VertexBuffer::empty_dynamic(display, capacity)?;
+        }
+        let instances = self
+            .instances
+            .slice(0..particles.len())
+            .ok_or_else(|| anyhow!("particle buffer is too small"))?;
+        instances.write(particles);
+
+        let uniforms = uniform! {
+            view: view.to_cols_array_2d(),
+            perspective: perspective.to_cols_array_2d(),
+            min_size_angle: 0.5 * MIN_SIZE_PX * pixel_angle,
+        };
+        let params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::One,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        frame.draw(
+            (
+                &self.quad,
+                instances
+                    .per_instance()
+                    .map_err(|_| anyhow!("instanced drawing is not supported"))?,
+            ),
+            NoIndices(PrimitiveType::TrianglesList),
+            program,
+            &uniforms,
+            &params,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,801 @@
+//! Textured, lit spheres for the bodies of the system.
+//!
+//! Every body shares a set of UV sphere meshes, one per level of detail. Its textures are named in the [`BodyDefinition`] and
+//! decoded by the [`AssetLoader`] like the skybox faces. Color textures are uploaded as sRGB so
+//! the GPU linearizes them when sampling, data textures (normals, specular masks) stay linear, and
+//! all of them get a full mip chain. Slots without a texture, or whose file failed to load, use a
+//! 1x1 neutral fallback so the shader never has to branch on what is bound.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use glam::{Mat3, Mat4, Quat, Vec3};
+use glium::{
+    Display, DrawParameters, IndexBuffer, Program, Surface, VertexBuffer,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+    texture::{MipmapsOption, RawImage2d, SrgbTexture2d, Texture2d},
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior},
+};
+use image::RgbaImage;
+use log::{info, warn};
+
+use crate::{
+    assets::{AssetData, AssetId, AssetLoader},
+    atmosphere::Atmosphere,
+    body::BodyDefinition,
+    celestial::ecliptic_to_scene,
+    eclipse::{Eclipse, MAX_OCCLUDERS, find_eclipses, nearest_occluders},
+    frustum::{CullStats, Frustum},
+    gravity::GravityField,
+    lod::{Lod, LodSelector, MESH_LEVELS},
+    rings::RingMesh,
+    shaders::{ProgramDesc, ShaderLibrary},
+    sun::{QUAD_CORNERS, QuadVertex, STAR_INTENSITY},
+    system::AU,
+};
+
+pub const PLANET_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "planet.vert",
+    fragment: "planet.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const IMPOSTOR_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "impostor.vert",
+    fragment: "impostor.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const BODY_POINT_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "body_point.vert",
+    fragment: "body_point.frag",
+    defines: &[],
+    uses_point_size: true,
+    outputs_srgb: false,
+};
+
+/// Diameter of bodies drawn as points, in pixels.
+const POINT_SIZE: f32 = 2.0;
+const TEXTURE_ANISOTROPY: u16 = 16;
+
+#[derive(Copy, Clone)]
+pub struct PlanetVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    /// Points east, along increasing texture `u`.
+    tangent: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+implement_vertex!(PlanetVertex, position, normal, tangent, tex_coord);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TextureSlot {
+    Albedo,
+    Normal,
+    Specular,
+    Night,
+    /// Radial strip of the ring system.
+    Ring,
+}
+
+impl TextureSlot {
+    const ALL: [TextureSlot; 5] = [
+        Self::Albedo,
+        Self::Normal,
+        Self::Specular,
+        Self::Night,
+        Self::Ring,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn path(self, body: &BodyDefinition) -> Option<&str> {
+        let textures = &body.textures;
+        match self {
+            Self::Albedo => textures.albedo.as_deref(),
+            Self::Normal => textures.normal.as_deref(),
+            Self::Specular => textures.specular.as_deref(),
+            Self::Night => textures.night.as_deref(),
+            Self::Ring => body
+                .rings
+                .as_ref()
+                .and_then(|rings| rings.texture.as_deref()),
+        }
+    }
+
+    /// Color of the 1x1 texture used when the slot is empty.
+    fn fallback(self) -> [u8; 4] {
+        match self {
+            Self::Albedo | Self::Ring => [255, 255, 255, 255],
+            Self::Normal => [128, 128, 255, 255],
+            Self::Specular | Self::Night => [0, 0, 0, 255],
+        }
+    }
+
+    fn is_srgb(self) -> bool {
+        matches!(self, Self::Albedo | Self::Night | Self::Ring)
+    }
+}
+
+/// A texture in either encoding; the shader samples both the same way.
+enum BodyTexture {
+    Srgb(SrgbTexture2d),
+    Linear(Texture2d),
+}
+
+impl BodyTexture {
+    fn new(display: &Display<WindowSurface>, slot: TextureSlot, image: RgbaImage) -> Result<Self> {
+        let dimensions = image.dimensions();
+        // Equirectangular maps have the north pole in the first row, GL expects the bottom row.
+        let image = RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dimensions);
+        Ok(if slot.is_srgb() {
+            Self::Srgb(SrgbTexture2d::with_mipmaps(
+                display,
+                image,
+                MipmapsOption::AutoGeneratedMipmaps,
+            )?)
+        } else {
+            Self::Linear(Texture2d::with_mipmaps(
+                display,
+                image,
+                MipmapsOption::AutoGeneratedMipmaps,
+            )?)
+        })
+    }
+
+    fn fallback(display: &Display<WindowSurface>, slot: TextureSlot) -> Result<Self> {
+        let image = RgbaImage::from_pixel(1, 1, image::Rgba(slot.fallback()));
+        Self::new(display, slot, image)
+    }
+}
+
+struct Planet {
+    body: BodyDefinition,
+    /// Indexed by [`TextureSlot::index`].
+    textures: [BodyTexture; 5],
+    /// Textures still being decoded, indexed like `textures`.
+    pending: [Option<AssetId>; 5],
+    ring_mesh: Option<RingMesh>,
+}
+
+impl Planet {
+    fn rotation(&self) -> Quat {
+        Quat::from_rotation_x(self.body.axial_tilt_deg.to_radians())
+    }
+
+    fn model_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(self.body.radius),
+            self.rotation(),
+            self.body.position,
+        )
+    }
+
+    /// Normal of the equatorial plane, which holds the rings.
+    fn ring_normal(&self) -> Vec3 {
+        self.rotation() * Vec3::Y
+    }
+
+    fn request(&mut self, assets: &mut AssetLoader, slot: TextureSlot) {
+        if let Some(id) = self.pending[slot.index()] {
+            assets.discard(id);
+        }
+        self.pending[slot.index()] = slot.path(&self.body).map(|path| assets.load_image(path));
+    }
+}
+
+/// Sphere at one of the [`MESH_LEVELS`].
+struct SphereMesh {
+    vertex_buffer: VertexBuffer<PlanetVertex>,
+    index_buffer: IndexBuffer<u32>,
+}
+
+/// Shared state for drawing the bodies of one frame.
+struct DrawContext<'a> {
+    shaders: &'a ShaderLibrary,
+    view: Mat4,
+    perspective: Mat4,
+    camera_position: Vec3,
+    light_position: Vec3,
+    light_color: [f32; 3],
+    light_radius: f32,
+    sampler: SamplerBehavior,
+}
+
+/// Depth tested and written, with back faces culled for closed meshes.
+fn opaque_params(cull: bool) -> DrawParameters<'static> {
+    DrawParameters {
+        depth: glium::Depth {
+            test: glium::DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        backface_culling: if cull {
+            glium::draw_parameters::BackfaceCullingMode::CullClockwise
+        } else {
+            glium::draw_parameters::BackfaceCullingMode::CullingDisabled
+        },
+        ..Default::default()
+    }
+}
+
+pub struct Planets {
+    /// Indexed like [`MESH_LEVELS`].
+    meshes: Vec<SphereMesh>,
+    /// Billboard for impostors; its first vertex doubles as the point for point sprites.
+    quad: VertexBuffer<QuadVertex>,
+    planets: Vec<Planet>,
+}
+
+impl Planets {
+    /// Creates the sphere mesh and queues the textures of every body on `assets`. The program is
+    /// expected to be registered in the shader library as `"planet"` from [`PLANET_PROGRAM`].
+    pub fn init(
+        display: &Display<WindowSurface>,
+        assets: &mut AssetLoader,
+        bodies: &[BodyDefinition],
+    ) -> Result<Self> {
+        info!("Initializing {} bodies...", bodies.len());
+        let mut planets = Vec::with_capacity(bodies.len());
+        for body in bodies {
+            let mut planet = Planet {
+                body: body.clone(),
+                textures: [
+                    BodyTexture::fallback(display, TextureSlot::Albedo)?,
+                    BodyTexture::fallback(display, TextureSlot::Normal)?,
+                    BodyTexture::fallback(display, TextureSlot::Specular)?,
+                    BodyTexture::fallback(display, TextureSlot::Night)?,
+                    BodyTexture::fallback(display, TextureSlot::Ring)?,
+                ],
+                pending: [None; 5],
+                ring_mesh: body
+                    .rings
+                    .as_ref()
+                    .map(|rings| RingMesh::new(display, rings))
+                    .transpose()?,
+            };
+            for slot in TextureSlot::ALL {
+                planet.request(assets, slot);
+            }
+            planets.push(planet);
+        }
+        let meshes = MESH_LEVELS
+            .iter()
+            .map(|&(segments, rings)| {
+                let (vertices, indices) = uv_sphere(segments, rings);
+                Ok(SphereMesh {
+                    vertex_buffer: VertexBuffer::new(display, &vertices)?,
+                    index_buffer: IndexBuffer::new(
+                        display,
+                        PrimitiveType::TrianglesList,
+                        &indices,
+                    )?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            meshes,
+            quad: VertexBuffer::new(display, &QUAD_CORNERS.map(|corner| QuadVertex { corner }))?,
+            planets,
+        })
+    }
+
+    /// Uploads textures that finished decoding. Slots whose file fails to load keep their current
+    /// texture, which is the fallback unless an earlier load succeeded.
+    pub fn update(&mut self, display: &Display<WindowSurface>, assets: &mut AssetLoader) {
+        for planet in &mut self.planets {
+            for slot in TextureSlot::ALL {
+                let Some(id) = planet.pending[slot.index()] else {
+                    continue;
+                };
+                let Some(result) = assets.take(id) else {
+                    continue;
+                };
+                planet.pending[slot.index()] = None;
+                let uploaded = result.and_then(|data| {
+                    let AssetData::Image(image) = data else {
+                        bail!("not an image");
+                    };
+                    BodyTexture::new(display, slot, image)
+                });
+                match uploaded {
+                    Ok(texture) => planet.textures[slot.index()] = texture,
+                    Err(e) => warn!(
+                        "Keeping current {slot:?} texture for {}: {e:#}",
+                        planet.body.name
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Texture files that should be watched for hot reloading.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        self.planets
+            .iter()
+            .flat_map(|planet| {
+                TextureSlot::ALL
+                    .into_iter()
+                    .filter_map(|slot| slot.path(&planet.body).map(PathBuf::from))
+            })
+            .collect()
+    }
+
+    /// Reloads every texture slot that uses `path`.
+    pub fn on_file_changed(&mut self, assets: &mut AssetLoader, path: &Path) {
+        for planet in &mut self.planets {
+            for slot in TextureSlot::ALL {
+                if slot
+                    .path(&planet.body)
+                    .is_some_and(|p| Path::new(p) == path)
+                {
+                    planet.request(assets, slot);
+                }
+            }
+        }
+    }
+
+    /// Moves every body the gravity field knows to where the field has it now.
+    pub fn move_bodies(&mut self, field: &GravityField) {
+        for planet in &mut self.planets {
+            if let Some(index) = field.find(&planet.body.name) {
+                planet.body.position = ecliptic_to_scene(field.attractors[index].position) * AU;
+            }
+        }
+    }
+
+    /// The first emissive body, which lights the others.
+    pub fn bodies(&self) -> impl Iterator<Item = &BodyDefinition> {
+        self.planets.iter().map(|planet| &planet.body)
+    }
+
+    pub fn light_source(&self) -> Option<&BodyDefinition> {
+        self.planets
+            .iter()
+            .map(|planet| &planet.body)
+            .find(|body| body.emissive)
+    }
+
+    pub fn light_position(&self) -> Vec3 {
+        self.light_source().map_or(Vec3::ZERO, |body| body.position)
+    }
+
+    pub fn light_radius(&self) -> f32 {
+        self.light_source().map_or(0.0, |body| body.radius)
+    }
+
+    /// Eclipses currently happening between the bodies.
+    pub fn eclipses(&self) -> Vec<Eclipse> {
+        find_eclipses(self.planets.iter().map(|planet| &planet.body))
+    }
+
+    /// Nearest bodies that may shadow `planet`, packed for the `occluders` uniform array.
+    fn occluders(&self, planet: &Planet) -> ([[f32; 4]; MAX_OCCLUDERS], i32) {
+        let nearest = nearest_occluders(self.planets.iter().map(|p| &p.body), &planet.body);
+        let mut packed = [[0.0; 4]; MAX_OCCLUDERS];
+        for (slot, body) in packed.iter_mut().zip(&nearest) {
+            *slot = body.position.extend(body.radius).to_array();
+        }
+        (packed, nearest.len() as i32)
+    }
+
+    pub fn light_color(&self) -> [f32; 3] {
+        self.light_source()
+            .map_or([1.0; 3], BodyDefinition::emission_color)
+    }
+
+    /// Draws every body at the level of detail `lod` picks for it. Meshes use the `"star"`
+    /// program for emissive bodies and `"planet"` for the rest; small bodies use `"impostor"` and
+    /// `"body_point"`. `time` in seconds animates star surfaces.
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "camera state is passed like the other renderers"
+    )]
+    pub fn draw(
+        &self,
+        frame: &mut impl Surface,
+        shaders: &ShaderLibrary,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+        time: f32,
+        lod: &LodSelector,
+        frustum: &Frustum,
+    ) -> Result<CullStats> {
+        let context = DrawContext {
+            shaders,
+            view,
+            perspective,
+            camera_position,
+            light_position: self.light_position(),
+            light_color: self.light_color(),
+            light_radius: self.light_radius(),
+            sampler: SamplerBehavior {
+                magnify_filter: MagnifySamplerFilter::Linear,
+                minify_filter: MinifySamplerFilter::LinearMipmapLinear,
+                max_anisotropy: TEXTURE_ANISOTROPY,
+                ..Default::default()
+            },
+        };
+
+        let mut stats = CullStats::default();
+        for planet in &self.planets {
+            if !stats.record(frustum.intersects_sphere(planet.body.position, planet.body.radius)) {
+                continue;
+            }
+            let distance = camera_position.distance(planet.body.position);
+            match lod.select(planet.body.radius, distance) {
+                Lod::Mesh(level) if planet.body.emissive => {
+                    self.draw_star(frame, &context, planet, &self.meshes[level], time)?;
+                }
+                // Stars stay spheres at any distance, the bloom keeps them visible.
+                _ if planet.body.emissive => {
+                    let coarsest = self.meshes.last().expect("at least one mesh level");
+                    self.draw_star(frame, &context, planet, coarsest, time)?;
+                }
+                Lod::Mesh(level) => {
+                    self.draw_mesh(frame, &context, planet, &self.meshes[level])?;
+                }
+                Lod::Impostor => self.draw_impostor(frame, &context, planet)?,
+                Lod::Point => self.draw_point(frame, &context, planet)?,
+            }
+        }
+        Ok(stats)
+    }
+
+    fn draw_star(
+        &self,
+        frame: &mut impl Surface,
+        context: &DrawContext,
+        planet: &Planet,
+        mesh: &SphereMesh,
+        time: f32,
+    ) -> Result<()> {
+        let uniforms = uniform! {
+            model: planet.model_matrix().to_cols_array_2d(),
+            view: context.view.to_cols_array_2d(),
+            perspective: context.perspective.to_cols_array_2d(),
+            star_color: planet.body.emission_color(),
+            intensity: STAR_INTENSITY,
+            time: time,
+            camera_position: context.camera_position.to_array(),
+        };
+        frame.draw(
+            &mesh.vertex_buffer,
+            &mesh.index_buffer,
+            context.shaders.get("star"),
+            &uniforms,
+            &opaque_params(true),
+        )?;
+        Ok(())
+    }
+
+    fn draw_mesh(
+        &self,
+        frame: &mut impl Surface,
+        context: &DrawContext,
+        planet: &Planet,
+        mesh: &SphereMesh,
+    ) -> Result<()> {
+        let sampler = context.sampler;
+        let [albedo, normal, specular, night, ring] = &planet.textures;
+        let (occluders, occluder_count) = self.occluders(planet);
+        let rings = planet.body.rings.as_ref();
+        let radius = planet.body.radius;
+        let uniforms = uniform! {
+            model: planet.model_matrix().to_cols_array_2d(),
+            view: context.view.to_cols_array_2d(),
+            perspective: context.perspective.to_cols_array_2d(),
+            tint: planet.body.color,
+            light_position: context.light_position.to_array(),
+            light_color: context.light_color,
+            light_radius: context.light_radius,
+            occluder_count: occluder_count,
+            camera_position: context.camera_position.to_array(),
+            has_rings: rings.is_some(),
+            center: planet.body.position.to_array(),
+            ring_normal: planet.ring_normal().to_array(),
+            ring_inner: rings.map_or(0.0, |rings| rings.inner_radius * radius),
+            ring_outer: rings.map_or(0.0, |rings| rings.outer_radius * radius),
+            ring_opacity: rings.map_or(0.0, |rings| rings.opacity),
+        };
+        let uniforms = uniforms
+            .add("occluders[0]", occluders[0])
+            .add("occluders[1]", occluders[1])
+            .add("occluders[2]", occluders[2])
+            .add("occluders[3]", occluders[3])
+            .add("ring_map", sampled(ring, sampler))
+            .add("albedo_map", sampled(albedo, sampler))
+            .add("normal_map", sampled(normal, sampler))
+            .add("specular_map", sampled(specular, sampler))
+            .add("night_map", sampled(night, sampler));
+        frame.draw(
+            &mesh.vertex_buffer,
+            &mesh.index_buffer,
+            context.shaders.get("planet"),
+            &uniforms,
+            &opaque_params(true),
+        )?;
+        Ok(())
+    }
+
+    fn draw_impostor(
+        &self,
+        frame: &mut impl Surface,
+        context: &DrawContext,
+        planet: &Planet,
+    ) -> Result<()> {
+        let albedo = &planet.textures[TextureSlot::Albedo.index()];
+        let uniforms = uniform! {
+            center: planet.body.position.to_array(),
+            radius: planet.body.radius,
+            view: context.view.to_cols_array_2d(),
+            perspective: context.perspective.to_cols_array_2d(),
+            rotation: Mat3::from_quat(planet.rotation()).to_cols_array_2d(),
+            tint: planet.body.color,
+            light_position: context.light_position.to_array(),
+            light_color: context.light_color,
+        };
+        let uniforms = uniforms.add("albedo_map", sampled(albedo, context.sampler));
+        frame.draw(
+            &self.quad,
+            NoIndices(PrimitiveType::TrianglesList),
+            context.shaders.get("impostor"),
+            &uniforms,
+            &opaque_params(false),
+        )?;
+        Ok(())
+    }
+
+    fn draw_point(
+        &self,
+        frame: &mut impl Surface,
+        context: &DrawContext,
+        planet: &Planet,
+    ) -> Result<()> {
+        let position = planet.body.position;
+        let to_light = (context.light_position - position).normalize_or_zero();
+        let to_camera = (context.camera_position - position).normalize_or_zero();
+        let albedo = &planet.textures[TextureSlot::Albedo.index()];
+        let uniforms = uniform! {
+            center: position.to_array(),
+            view: context.view.to_cols_array_2d(),
+            perspective: context.perspective.to_cols_array_2d(),
+            point_size: POINT_SIZE,
+            tint: planet.body.color,
+            light_color: context.light_color,
+            phase: 0.5 * (1.0 + to_light.dot(to_camera)),
+        };
+        let uniforms = uniforms.add("albedo_map", sampled(albedo, context.sampler));
+        frame.draw(
+            self.quad.slice(0..1).expect("quad has vertices"),
+            NoIndices(PrimitiveType::Points),
+            context.shaders.get("body_point"),
+            &uniforms,
+            &opaque_params(false),
+        )?;
+        Ok(())
+    }
+
+    /// Draws the ring systems with alpha blending. Like the atmospheres they do not write depth
+    /// and must come after the sky.
+    pub fn draw_rings(
+        &self,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+        frustum: &Frustum,
+    ) -> Result<CullStats> {
+        let params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        let sampler = SamplerBehavior {
+            magnify_filter: MagnifySamplerFilter::Linear,
+            minify_filter: MinifySamplerFilter::LinearMipmapLinear,
+            max_anisotropy: TEXTURE_ANISOTROPY,
+            ..Default::default()
+        };
+        let light_position = self.light_position();
+        let light_color = self.light_color();
+        let light_radius = self.light_radius();
+
+        let mut stats = CullStats::default();
+        for planet in &self.planets {
+            let (Some(rings), Some(mesh)) = (&planet.body.rings, &planet.ring_mesh) else {
+                continue;
+            };
+            let extent = planet.body.radius * rings.outer_radius;
+            if !stats.record(frustum.intersects_sphere(planet.body.position, extent)) {
+                continue;
+            }
+            let uniforms = uniform! {
+                model: planet.model_matrix().to_cols_array_2d(),
+                view: view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                tint: rings.color,
+                opacity: rings.opacity,
+                center: planet.body.position.to_array(),
+                radius: planet.body.radius,
+                ring_normal: planet.ring_normal().to_array(),
+                light_position: light_position.to_array(),
+                light_color: light_color,
+                light_radius: light_radius,
+                camera_position: camera_position.to_array(),
+            };
+            let uniforms = uniforms.add(
+                "ring_map",
+                sampled(&planet.textures[TextureSlot::Ring.index()], sampler),
+            );
+            frame.draw(
+                &mesh.vertex_buffer,
+                &mesh.index_buffer,
+                program,
+                &uniforms,
+                &params,
+            )?;
+        }
+        Ok(stats)
+    }
+
+    /// Draws the atmosphere shells with additive blending. Call after [`Planets::draw`] so the
+    /// depth buffer hides shells behind other bodies.
+    pub fn draw_atmospheres(
+        &self,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+        frustum: &Frustum,
+    ) -> Result<CullStats> {
+        let light_position = self.light_position();
+        let mut stats = CullStats::default();
+        for planet in &self.planets {
+            let Some(atmosphere) = &planet.body.atmosphere else {
+                continue;
+            };
+            let (center, radius) = (planet.body.position, planet.body.radius);
+            if !stats.record(frustum.intersects_sphere(center, radius * (1.0 + atmosphere.height)))
+            {
+                continue;
+            }
+            // From inside the shell only its far side is visible, and everything else seen
+            // through it, including the ground, lies behind the scattering air.
+            let inside = atmosphere.contains(center, radius, camera_position);
+            let params = DrawParameters {
+                depth: glium::Depth {
+                    test: if inside {
+                        glium::DepthTest::Overwrite
+                    } else {
+                        glium::DepthTest::IfLess
+                    },
+                    write: false,
+                    ..Default::default()
+                },
+                blend: glium::Blend {
+                    color: glium::BlendingFunction::Addition {
+                        source: glium::LinearBlendingFactor::One,
+                        destination: glium::LinearBlendingFactor::One,
+                    },
+                    ..Default::default()
+                },
+                backface_culling: if inside {
+                    glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise
+                } else {
+                    glium::draw_parameters::BackfaceCullingMode::CullClockwise
+                },
+                ..Default::default()
+            };
+            let uniforms = uniform! {
+                model: atmosphere.shell_matrix(center, radius).to_cols_array_2d(),
+                view: view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                center: center.to_array(),
+                radius: radius,
+                camera_position: camera_position.to_array(),
+                light_position: light_position.to_array(),
+                height: atmosphere.height,
+                rayleigh_scale_height: atmosphere.rayleigh_scale_height,
+                mie_scale_height: atmosphere.mie_scale_height,
+                rayleigh: atmosphere.rayleigh,
+                mie: atmosphere.mie,
+                mie_g: atmosphere.mie_g,
+                sun_intensity: atmosphere.sun_intensity,
+            };
+            frame.draw(
+                &self.meshes[0].vertex_buffer,
+                &self.meshes[0].index_buffer,
+                program,
+                &uniforms,
+                &params,
+            )?;
+        }
+        Ok(stats)
+    }
+
+    /// Atmospheres by body name, for live tweaking.
+    pub fn atmospheres_mut(&mut self) -> impl Iterator<Item = (&str, &mut Atmosphere)> {
+        self.planets.iter_mut().filter_map(|planet| {
+            let body = &mut planet.body;
+            body.atmosphere
+                .as_mut()
+                .map(|atmosphere| (body.name.as_str(), atmosphere))
+        })
+    }
+}
+
+/// Either kind of sampled texture, so all four slots fit in one uniform list.
+enum SampledTexture<'a> {
+    Srgb(Sampler<'a, SrgbTexture2d>),
+    Linear(Sampler<'a, Texture2d>),
+}
+
+impl glium::uniforms::AsUniformValue for SampledTexture<'_> {
+    fn as_uniform_value(&self) -> glium::uniforms::UniformValue<'_> {
+        match self {
+            Self::Srgb(sampler) => sampler.as_uniform_value(),
+            Self::Linear(sampler) => sampler.as_uniform_value(),
+        }
+    }
+}
+
+fn sampled(texture: &BodyTexture, behavior: SamplerBehavior) -> SampledTexture<'_> {
+    match texture {
+        BodyTexture::Srgb(texture) => SampledTexture::Srgb(Sampler(texture, behavior)),
+        BodyTexture::Linear(texture) => SampledTexture::Linear(Sampler(texture, behavior)),
+    }
+}
+
+/// Unit sphere with the poles on ±Y. `u` runs eastward from longitude 0 on +X and `v` from the
+/// south pole to the north pole. The seam and pole rows are duplicated so every vertex has a
+/// single texture coordinate.
+fn uv_sphere(segments: u32, rings: u32) -> (Vec<PlanetVertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1)) as usize);
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let latitude = (v - 0.5) * std::f32::consts::PI;
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let longitude = u * std::f32::consts::TAU;
+            let (sin_lon, cos_lon) = longitude.sin_cos();
+            let normal = [cos_lat * cos_lon, sin_lat, -cos_lat * sin_lon];
+            vertices.push(PlanetVertex {
+                position: normal,
+                normal,
+                tangent: [-sin_lon, 0.0, -cos_lon],
+                tex_coord: [u, v],
+            });
+        }
+    }
+
+    let row = segments + 1;
+    let mut indices = Vec::with_capacity((segments * rings * 6) as usize);
+    for ring in 0..rings {
+        for segment in 0..segments {
+            // Counter-clockwise seen from outside: east is to the right, north is up.
+            let a = ring * row + segment;
+            let b = a + 1;
+            let c = b + row;
+            let d = a + row;
+            indices.extend([a, b, c, a, c, d]);
+        }
+    }
+    (vertices, indices)
+}
@@ -0,0 +1,452 @@
+//! Porkchop plots of transfer windows between two planets.
+//!
+//! Every cell of the plot is a Lambert transfer from the departure planet on one date to the
+//! arrival planet on a later one, both on their mean orbits from [`planet_orbits`]. A cell's
+//! color is the total delta-v: the hyperbolic excess speed leaving the first planet plus the one
+//! arriving at the second, so the cheap windows show up as the blue centers of the "porkchops".
+//! The cells are solved on a background thread, and the plot keeps the planets and dates it was
+//! solved for while the inputs are edited.
+//!
+//! Picking a cell keeps that transfer for the 3D view. The scene's planets move on the same mean
+//! orbits, so at the departure and arrival dates they sit at the ends of the arc; the planner can
+//! ask for the scene to be shown at those dates.
+
+use std::{
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use glam::DVec3;
+
+use crate::{
+    clock::format_date,
+    kepler::{GM_SUN, OrbitalElements},
+    lambert::solve_lambert,
+    parallel::parallel_chunks_mut,
+    spacecraft::{KM_PER_AU, SECONDS_PER_DAY},
+    system::{PlanetOrbit, planet_orbits},
+};
+
+/// Cells along each axis.
+const RESOLUTION: usize = 120;
+/// Points along a drawn transfer arc.
+const ARC_SEGMENTS: usize = 256;
+/// Kilometres per second in one AU/day.
+const KM_S_PER_AU_DAY: f64 = KM_PER_AU / SECONDS_PER_DAY;
+/// Delta-v above the cheapest cell, as a multiple of it, where the color scale reaches red and
+/// saturates.
+const COLOR_RANGE: f64 = 1.5;
+
+/// A transfer picked from the plot.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub from: &'static str,
+    pub to: &'static str,
+    /// Days since J2000.
+    pub departure: f64,
+    pub arrival: f64,
+    /// Heliocentric ecliptic AU at departure and arrival.
+    pub departure_position: DVec3,
+    pub arrival_position: DVec3,
+    /// In AU/day.
+    departure_velocity: DVec3,
+    /// Hyperbolic excess speeds in km/s.
+    pub departure_delta_v: f64,
+    pub arrival_delta_v: f64,
+}
+
+impl Transfer {
+    /// Solves the transfer between `from` and `to` leaving at `departure` and arriving at
+    /// `arrival`, in days since J2000.
+    pub fn new(from: &PlanetOrbit, to: &PlanetOrbit, departure: f64, arrival: f64) -> Option<Self> {
+        let (r1, planet_v1) = state_at(&from.elements, departure);
+        let (r2, planet_v2) = state_at(&to.elements, arrival);
+        let time = arrival - departure;
+        let (v1, v2) = solve_lambert(r1, r2, time, GM_SUN, true)?;
+        Some(Self {
+            from: from.name,
+            to: to.name,
+            departure,
+            arrival,
+            departure_position: r1,
+            arrival_position: r2,
+            departure_velocity: v1,
+            departure_delta_v: (v1 - planet_v1).length() * KM_S_PER_AU_DAY,
+            arrival_delta_v: (planet_v2 - v2).length() * KM_S_PER_AU_DAY,
+        })
+    }
+
+    /// The transfer arc in heliocentric ecliptic AU, empty for a degenerate radial solution.
+    pub fn path(&self) -> Vec<DVec3> {
+        let Some(elements) = OrbitalElements::from_state_vector(
+            self.departure_position,
+            self.departure_velocity,
+            GM_SUN,
+        ) else {
+            return Vec::new();
+        };
+        let time = self.arrival - self.departure;
+        (0..=ARC_SEGMENTS)
+            .map(|segment| {
+                let days = time * segment as f64 / ARC_SEGMENTS as f64;
+                elements.advanced(days, GM_SUN).state_vector(GM_SUN).0
+            })
+            .collect()
+    }
+
+    pub fn total_delta_v(&self) -> f64 {
+        self.departure_delta_v + self.arrival_delta_v
+    }
+}
+
+/// Heliocentric position and velocity on a planet's mean orbit `days` after J2000.
+fn state_at(elements: &OrbitalElements, days: f64) -> (DVec3, DVec3) {
+    elements.advanced(days, GM_SUN).state_vector(GM_SUN)
+}
+
+/// Planets and dates covered by a plot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotRange {
+    /// Indices into [`Porkchop::orbits`].
+    pub from: usize,
+    pub to: usize,
+    /// First departure date in days since J2000 and the span of departure dates in days.
+    pub departure_start: f64,
+    pub departure_span: f64,
+    /// Same for the arrival dates.
+    pub arrival_start: f64,
+    pub arrival_span: f64,
+}
+
+impl PlotRange {
+    /// Departure and arrival dates of the center of cell `(column, row)`.
+    fn dates(&self, column: usize, row: usize) -> (f64, f64) {
+        let fraction = |index: usize| (index as f64 + 0.5) / RESOLUTION as f64;
+        (
+            self.departure_start + self.departure_span * fraction(column),
+            self.arrival_start + self.arrival_span * fraction(row),
+        )
+    }
+
+    /// Total delta-v in km/s of every cell, row by row from the earliest arrival, `None` where
+    /// there is no transfer. Solves a batch of cells per thread.
+    fn solve(&self, from: &PlanetOrbit, to: &PlanetOrbit) -> Vec<Option<f64>> {
+        let mut delta_v = vec![None; RESOLUTION * RESOLUTION];
+        parallel_chunks_mut(&mut delta_v, 1, |start, cells| {
+            for (offset, cell) in cells.iter_mut().enumerate() {
+                let index = start + offset;
+                let (departure, arrival) = self.dates(index % RESOLUTION, index / RESOLUTION);
+                *cell = Transfer::new(from, to, departure, arrival)
+                    .map(|transfer| transfer.total_delta_v());
+            }
+        });
+        delta_v
+    }
+}
+
+/// A solved plot.
+struct Plot {
+    range: PlotRange,
+    /// Indexed like [`PlotRange::solve`] returns them.
+    delta_v: Vec<Option<f64>>,
+    texture: egui::TextureHandle,
+}
+
+impl Plot {
+    fn new(ctx: &egui::Context, range: PlotRange, delta_v: Vec<Option<f64>>) -> Self {
+        let cheapest = cheapest(&delta_v).map_or(0.0, |(_, delta_v)| delta_v);
+        // Image rows go down, arrival dates go up.
+        let pixels = (0..RESOLUTION)
+            .rev()
+            .flat_map(|row| (0..RESOLUTION).map(move |column| row * RESOLUTION + column))
+            .map(|cell| match delta_v[cell] {
+                Some(delta_v) => color_scale((delta_v - cheapest) / (COLOR_RANGE * cheapest)),
+                None => egui::Color32::from_gray(20),
+            })
+            .collect();
+        let image = egui::ColorImage {
+            size: [RESOLUTION, RESOLUTION],
+            pixels,
+        };
+        Self {
+            range,
+            delta_v,
+            texture: ctx.load_texture("porkchop", image, egui::TextureOptions::NEAREST),
+        }
+    }
+}
+
+/// Index and total delta-v of the cheapest cell.
+fn cheapest(delta_v: &[Option<f64>]) -> Option<(usize, f64)> {
+    delta_v
+        .iter()
+        .enumerate()
+        .filter_map(|(cell, delta_v)| Some((cell, (*delta_v)?)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+pub struct Porkchop {
+    pub orbits: Vec<PlanetOrbit>,
+    /// What the next plot covers, as edited in the window.
+    pub range: PlotRange,
+    plot: Option<Plot>,
+    /// Plot being solved in the background.
+    solving: Option<Receiver<(PlotRange, Vec<Option<f64>>)>>,
+    pub selected: Option<Transfer>,
+}
+
+impl Porkchop {
+    /// Earth to Mars, around the 2005 window.
+    pub fn new() -> Self {
+        let orbits = planet_orbits();
+        let find = |name: &str| orbits.iter().position(|orbit| orbit.name == name);
+        Self {
+            range: PlotRange {
+                from: find("Earth").unwrap_or(0),
+                to: find("Mars").unwrap_or(0),
+                departure_start: 1950.0,
+                departure_span: 250.0,
+                arrival_start: 2100.0,
+                arrival_span: 400.0,
+            },
+            orbits,
+            plot: None,
+            solving: None,
+            selected: None,
+        }
+    }
+
+    /// Starts solving the plot for the current range on a background thread. The current plot
+    /// stays up until the new one is done.
+    pub fn generate(&mut self) {
+        let range = self.range;
+        let (from, to) = (
+            self.orbits[range.from].clone(),
+            self.orbits[range.to].clone(),
+        );
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // The window may have closed meanwhile, then nobody is waiting for the result.
+            let _ = sender.send((range, range.solve(&from, &to)));
+        });
+        self.solving = Some(receiver);
+    }
+
+    /// Takes the plot from the background thread once it is done.
+    fn receive(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.solving else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok((range, delta_v)) => {
+                self.plot = Some(Plot::new(ctx, range, delta_v));
+                self.solving = None;
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint(),
+            Err(TryRecvError::Disconnected) => self.solving = None,
+        }
+    }
+
+    fn select(&mut self, range: PlotRange, cell: usize) {
+        let (departure, arrival) = range.dates(cell % RESOLUTION, cell / RESOLUTION);
+        self.selected = Transfer::new(
+            &self.orbits[range.from],
+            &self.orbits[range.to],
+            departure,
+            arrival,
+        );
+    }
+
+    /// The plot window: planets and date ranges, the plot itself, and the picked transfer.
+    /// Returns a date in days since J2000 the scene should be shown at, when asked for one.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<f64> {
+        self.receive(ctx);
+        let mut show_date = None;
+        egui::Window::new("Transfer planner")
+            .open(open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let range = &mut self.range;
+                ui.horizontal(|ui| {
+                    for (label, index) in [("From", &mut range.from), ("To", &mut range.to)] {
+                        egui::ComboBox::from_label(label)
+                            .selected_text(self.orbits[*index].name)
+                            .show_ui(ui, |ui| {
+                                for (option, orbit) in self.orbits.iter().enumerate() {
+                                    ui.selectable_value(index, option, orbit.name);
+                                }
+                            });
+                    }
+                });
+                for (label, start, span) in [
+                    (
+                        "Departure",
+                        &mut range.departure_start,
+                        &mut range.departure_span,
+                    ),
+                    ("Arrival", &mut range.arrival_start, &mut range.arrival_span),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        ui.add(egui::DragValue::new(start).speed(5.0).prefix("day "));
+                        ui.label(format_date(*start));
+                        ui.add(
+                            egui::DragValue::new(span)
+                                .range(1.0..=20_000.0)
+                                .speed(5.0)
+                                .prefix("+")
+                                .suffix(" days"),
+                        );
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let solving = self.solving.is_some();
+                    if ui
+                        .add_enabled(!solving, egui::Button::new("Generate"))
+                        .clicked()
+                    {
+                        self.generate();
+                    }
+                    if solving {
+                        ui.spinner();
+                    }
+                    if let Some(plot) = &self.plot
+                        && let Some((cell, _)) = cheapest(&plot.delta_v)
+                        && ui.button("Pick cheapest").clicked()
+                    {
+                        self.select(plot.range, cell);
+                    }
+                    if self.selected.is_some() && ui.button("Clear").clicked() {
+                        self.selected = None;
+                    }
+                });
+
+                let mut picked = None;
+                if let Some(plot) = &self.plot {
+                    let size = egui::vec2(360.0, 360.0);
+                    let response = ui.add(
+                        egui::Image::new(&plot.texture)
+                            .fit_to_exact_size(size)
+                            .sense(egui::Sense::click()),
+                    );
+                    let cell = response.hover_pos().map(|position| {
+                        let relative = (position - response.rect.min) / response.rect.size();
+                        let column = (relative.x * RESOLUTION as f32) as usize;
+                        let row = ((1.0 - relative.y) * RESOLUTION as f32) as usize;
+                        row.min(RESOLUTION - 1) * RESOLUTION + column.min(RESOLUTION - 1)
+                    });
+                    if let Some(cell) = cell {
+                        let (departure, arrival) =
+                            plot.range.dates(cell % RESOLUTION, cell / RESOLUTION);
+                        let delta_v = plot.delta_v.get(cell).copied().flatten();
+                        response.clone().on_hover_text(format!(
+                            "{} → {}\nDepart {}\nArrive {} ({:.0} days)\n{}",
+                            self.orbits[plot.range.from].name,
+                            self.orbits[plot.range.to].name,
+                            format_date(departure),
+                            format_date(arrival),
+                            arrival - departure,
+                            delta_v.map_or("No transfer".to_owned(), |delta_v| {
+                                format!("Δv {delta_v:.2} km/s")
+                            }),
+                        ));
+                        if response.clicked() {
+                            picked = Some((plot.range, cell));
+                        }
+                    }
+                    ui.label(format!(
+                        "Departure date → (x), arrival date → (y). Blue is the cheapest, red \
+                         {:.1}× that or more, black no transfer",
+                        1.0 + COLOR_RANGE
+                    ));
+                }
+                if let Some((range, cell)) = picked {
+                    self.select(range, cell);
+                }
+
+                if let Some(transfer) = &self.selected {
+                    ui.separator();
+                    ui.monospace(format!(
+                        "{} → {}: depart {}, arrive {} ({:.0} days)",
+                        transfer.from,
+                        transfer.to,
+                        format_date(transfer.departure),
+                        format_date(transfer.arrival),
+                        transfer.arrival - transfer.departure
+                    ));
+                    ui.monospace(format!(
+                        "Δv {:.2} + {:.2} = {:.2} km/s",
+                        transfer.departure_delta_v,
+                        transfer.arrival_delta_v,
+                        transfer.total_delta_v()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Show departure").clicked() {
+                            show_date = Some(transfer.departure);
+                        }
+                        if ui.button("Show arrival").clicked() {
+                            show_date = Some(transfer.arrival);
+                        }
+                    });
+                }
+            });
+        show_date
+    }
+}
+
+/// Color for `t` in `0..=1` going from dark blue through green and yellow to red, staying red
+/// beyond.
+fn color_scale(t: f64) -> egui::Color32 {
+    let stops = [
+        [20.0, 20.0, 110.0],
+        [30.0, 150.0, 200.0],
+        [60.0, 200.0, 80.0],
+        [240.0, 220.0, 60.0],
+        [220.0, 60.0, 40.0],
+    ];
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let index = (position as usize).min(stops.len() - 2);
+    let fraction = position - index as f64;
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let (a, b) = (stops[index][channel], stops[index + 1][channel]);
+        (a + (b - a) * fraction) as u8
+    });
+    egui::Color32::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mars_reconnaissance_orbiter_window() {
+        // Launched 2005-08-12 with a C3 of 16.4 km²/s², arrived 2006-03-10.
+        let (departure, arrival) = (2049.5, 2259.5);
+        assert_eq!(format_date(departure), "2005-08-12");
+        assert_eq!(format_date(arrival), "2006-03-10");
+        let orbits = planet_orbits();
+        let find = |name: &str| orbits.iter().find(|orbit| orbit.name == name).unwrap();
+        let transfer = Transfer::new(find("Earth"), find("Mars"), departure, arrival).unwrap();
+        let expected = 16.4f64.sqrt();
+        assert!(
+            (transfer.departure_delta_v - expected).abs() < 0.1,
+            "{} km/s",
+            transfer.departure_delta_v
+        );
+        // Near the Hohmann transfer's 2.65 km/s, Mars' eccentric orbit and the 210 day flight
+        // raise it a little.
+        assert!((2.6..3.2).contains(&transfer.arrival_delta_v));
+
+        let path = transfer.path();
+        assert!(path[0].abs_diff_eq(transfer.departure_position, 1e-9));
+        assert!(path[ARC_SEGMENTS].abs_diff_eq(transfer.arrival_position, 1e-6));
+    }
+
+    #[test]
+    fn color_scale_saturates_at_red() {
+        let red = color_scale(1.0);
+        assert_eq!(color_scale(1.7), red);
+        assert!(red.r() > 200 && red.g() < 100);
+        assert!(color_scale(0.0).b() > color_scale(0.0).r());
+    }
+}
@@ -0,0 +1,70 @@
+//! Ring systems.
+//!
+//! Rings are a flat annulus in the planet's equatorial plane, textured with a radial strip whose
+//! alpha channel is the opacity. They are lit from the side facing the star, and a little light
+//! leaks through thin parts to the other side. The planet shadows the rings here, while the
+//! planet shader looks up the same strip to darken the surface below the rings.
+
+use anyhow::Result;
+use glium::{
+    Display, IndexBuffer, VertexBuffer, glutin::surface::WindowSurface, index::PrimitiveType,
+};
+
+use crate::{body::Rings, shaders::ProgramDesc};
+
+pub const RINGS_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "rings.vert",
+    fragment: "rings.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+const RING_SEGMENTS: u32 = 256;
+
+#[derive(Copy, Clone)]
+pub struct RingVertex {
+    /// In planet radii, in the XZ plane.
+    position: [f32; 3],
+    /// 0 at the inner edge, 1 at the outer edge.
+    radial: f32,
+}
+
+implement_vertex!(RingVertex, position, radial);
+
+pub struct RingMesh {
+    pub vertex_buffer: VertexBuffer<RingVertex>,
+    pub index_buffer: IndexBuffer<u32>,
+}
+
+impl RingMesh {
+    pub fn new(display: &Display<WindowSurface>, rings: &Rings) -> Result<Self> {
+        let (vertices, indices) = annulus(rings.inner_radius, rings.outer_radius, RING_SEGMENTS);
+        Ok(Self {
+            vertex_buffer: VertexBuffer::new(display, &vertices)?,
+            index_buffer: IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices)?,
+        })
+    }
+}
+
+/// Flat ring in the XZ plane between `inner` and `outer`, as a strip of `segments` quads.
+pub fn annulus(inner: f32, outer: f32, segments: u32) -> (Vec<RingVertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity(2 * (segments + 1) as usize);
+    for segment in 0..=segments {
+        let angle = segment as f32 / segments as f32 * std::f32::consts::TAU;
+        let (sin, cos) = angle.sin_cos();
+        for (radius, radial) in [(inner, 0.0), (outer, 1.0)] {
+            vertices.push(RingVertex {
+                position: [cos * radius, 0.0, -sin * radius],
+                radial,
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity(6 * segments as usize);
+    for segment in 0..segments {
+        let a = 2 * segment;
+        indices.extend([a, a + 1, a + 3, a, a + 3, a + 2]);
+    }
+    (vertices, indices)
+}
@@ -0,0 +1,596 @@
+//! Shader library.
+//!
+//! Programs are registered once under a name and looked up by that name when drawing. Sources
+//! are run through a small preprocessor first:
+//!
+//! - `#include "path"` pastes a file from `assets/shaders` in place. Every file is included at
+//!   most once per program and include cycles are reported as errors.
+//! - Defines passed in a [`ProgramDesc`] are inserted right after the `#version` line, so one set
+//!   of sources can be compiled into several variants.
+//!
+//! The preprocessor keeps a map from output lines back to the file and line they came from, which
+//! is used to rewrite driver compile logs so errors point at the real file.
+//!
+//! Release builds use the copies of the shaders baked into the binary. When shaders are read from
+//! disk, [`ShaderLibrary::reload_file`] recompiles every program that depends on a changed file and
+//! keeps the previous program if compilation fails.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use glium::{Display, Program, glutin::surface::WindowSurface, program::ProgramCreationInput};
+use log::{error, info};
+
+const SHADER_DIR: &str = "assets/shaders";
+
+/// Shader files baked into the binary, keyed by their path relative to [`SHADER_DIR`].
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    ("skybox.vert", include_str!("../assets/shaders/skybox.vert")),
+    ("skybox.frag", include_str!("../assets/shaders/skybox.frag")),
+    (
+        "overlay.vert",
+        include_str!("../assets/shaders/overlay.vert"),
+    ),
+    (
+        "overlay.frag",
+        include_str!("../assets/shaders/overlay.frag"),
+    ),
+    ("stars.vert", include_str!("../assets/shaders/stars.vert")),
+    ("stars.frag", include_str!("../assets/shaders/stars.frag")),
+    (
+        "fullscreen.vert",
+        include_str!("../assets/shaders/fullscreen.vert"),
+    ),
+    ("bloom.frag", include_str!("../assets/shaders/bloom.frag")),
+    (
+        "tonemap.frag",
+        include_str!("../assets/shaders/tonemap.frag"),
+    ),
+    ("planet.vert", include_str!("../assets/shaders/planet.vert")),
+    ("planet.frag", include_str!("../assets/shaders/planet.frag")),
+    (
+        "impostor.vert",
+        include_str!("../assets/shaders/impostor.vert"),
+    ),
+    (
+        "impostor.frag",
+        include_str!("../assets/shaders/impostor.frag"),
+    ),
+    (
+        "body_point.vert",
+        include_str!("../assets/shaders/body_point.vert"),
+    ),
+    (
+        "body_point.frag",
+        include_str!("../assets/shaders/body_point.frag"),
+    ),
+    ("star.frag", include_str!("../assets/shaders/star.frag")),
+    ("corona.vert", include_str!("../assets/shaders/corona.vert")),
+    ("corona.frag", include_str!("../assets/shaders/corona.frag")),
+    (
+        "lens_flare.vert",
+        include_str!("../assets/shaders/lens_flare.vert"),
+    ),
+    (
+        "lens_flare.frag",
+        include_str!("../assets/shaders/lens_flare.frag"),
+    ),
+    ("rings.vert", include_str!("../assets/shaders/rings.vert")),
+    ("rings.frag", include_str!("../assets/shaders/rings.frag")),
+    (
+        "atmosphere.vert",
+        include_str!("../assets/shaders/atmosphere.vert"),
+    ),
+    (
+        "atmosphere.frag",
+        include_str!("../assets/shaders/atmosphere.frag"),
+    ),
+    (
+        "asteroid.vert",
+        include_str!("../assets/shaders/asteroid.vert"),
+    ),
+    (
+        "asteroid.frag",
+        include_str!("../assets/shaders/asteroid.frag"),
+    ),
+    ("ship.vert", include_str!("../assets/shaders/ship.vert")),
+    (
+        "particle.vert",
+        include_str!("../assets/shaders/particle.vert"),
+    ),
+    (
+        "particle.frag",
+        include_str!("../assets/shaders/particle.frag"),
+    ),
+    (
+        "include/depth.glsl",
+        include_str!("../assets/shaders/include/depth.glsl"),
+    ),
+    (
+        "include/color.glsl",
+        include_str!("../assets/shaders/include/color.glsl"),
+    ),
+    (
+        "include/eclipse.glsl",
+        include_str!("../assets/shaders/include/eclipse.glsl"),
+    ),
+    (
+        "include/noise.glsl",
+        include_str!("../assets/shaders/include/noise.glsl"),
+    ),
+    (
+        "include/lighting.glsl",
+        include_str!("../assets/shaders/include/lighting.glsl"),
+    ),
+];
+
+/// Original location of one line of preprocessed shader code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLine {
+    /// Index into [`Preprocessed::files`].
+    pub file: usize,
+    /// 1-based line number, `0` for lines generated by the preprocessor.
+    pub line: u32,
+}
+
+/// Output of [`preprocess`].
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessed {
+    pub code: String,
+    /// Every file that went into `code`, the root file first.
+    pub files: Vec<String>,
+    /// One entry per line of `code`.
+    pub lines: Vec<SourceLine>,
+}
+
+impl Preprocessed {
+    /// Location of the 1-based output line `line` as `file:line`.
+    pub fn location(&self, line: u32) -> Option<String> {
+        let source = self.lines.get(line.checked_sub(1)? as usize)?;
+        let file = &self.files[source.file];
+        Some(if source.line == 0 {
+            format!("{file}:<generated>")
+        } else {
+            format!("{file}:{}", source.line)
+        })
+    }
+
+    /// Rewrites the line references in a driver compile log to `file:line`.
+    ///
+    /// Drivers report errors in the first source string as `0:LINE` (Mesa, AMD, Intel) or
+    /// `0(LINE)` (NVIDIA). References that cannot be mapped are left alone.
+    pub fn map_error_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.map_error_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_error_line(&self, line: &str) -> String {
+        for (start, _) in line.match_indices('0') {
+            if start > 0 && !line[..start].ends_with([' ', '\t']) {
+                continue;
+            }
+            let rest = &line[start + 1..];
+            let (digits, close) = if let Some(rest) = rest.strip_prefix(':') {
+                (rest, "")
+            } else if let Some(rest) = rest.strip_prefix('(') {
+                (rest, ")")
+            } else {
+                continue;
+            };
+            let len = digits.bytes().take_while(u8::is_ascii_digit).count();
+            if len == 0 || !digits[len..].starts_with(close) {
+                continue;
+            }
+            let Some(location) = digits[..len].parse().ok().and_then(|n| self.location(n)) else {
+                continue;
+            };
+            let end = start + 2 + len + close.len();
+            return format!("{}{location}{}", &line[..start], &line[end..]);
+        }
+        line.to_owned()
+    }
+}
+
+/// Expands `#include` directives and inserts `defines` after the `#version` line of `file`.
+///
+/// `read` returns the contents of a shader file given its path relative to the shader directory.
+pub fn preprocess(
+    file: &str,
+    defines: &[(&str, &str)],
+    read: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<Preprocessed> {
+    let mut output = Preprocessed::default();
+    let mut stack = Vec::new();
+    let source = read(file)?;
+    expand(file, &source, defines, read, &mut stack, &mut output)?;
+    Ok(output)
+}
+
+fn expand(
+    file: &str,
+    source: &str,
+    defines: &[(&str, &str)],
+    read: &mut dyn FnMut(&str) -> Result<String>,
+    stack: &mut Vec<String>,
+    output: &mut Preprocessed,
+) -> Result<()> {
+    let file_index = output.files.len();
+    output.files.push(file.to_owned());
+    stack.push(file.to_owned());
+
+    // Defines go after `#version`, which has to stay the first statement. Files without one get
+    // them at the top, but only the root file: included snippets see the root's defines already.
+    let is_root = stack.len() == 1;
+    let has_version = source
+        .lines()
+        .any(|line| line.trim_start().starts_with("#version"));
+    if is_root && !has_version {
+        push_defines(defines, file_index, output);
+    }
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number as u32 + 1;
+        let directive = line.trim_start();
+
+        if let Some(rest) = directive.strip_prefix("#include") {
+            let path = parse_include_path(rest)
+                .ok_or_else(|| anyhow!("{file}:{number}: malformed #include: {directive}"))?;
+            if stack.iter().any(|parent| parent == path) {
+                bail!(
+                    "{file}:{number}: include cycle: {} -> {path}",
+                    stack.join(" -> ")
+                );
+            }
+            if output.files.iter().any(|included| included == path) {
+                continue;
+            }
+            let included =
+                read(path).with_context(|| format!("{file}:{number}: cannot include {path:?}"))?;
+            expand(path, &included, defines, read, stack, output)?;
+            continue;
+        }
+
+        output.code.push_str(line);
+        output.code.push('\n');
+        output.lines.push(SourceLine {
+            file: file_index,
+            line: number,
+        });
+
+        if is_root && directive.starts_with("#version") {
+            push_defines(defines, file_index, output);
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+fn push_defines(defines: &[(&str, &str)], file: usize, output: &mut Preprocessed) {
+    for (name, value) in defines {
+        output.code.push_str(&format!("#define {name} {value}\n"));
+        output.lines.push(SourceLine { file, line: 0 });
+    }
+}
+
+fn parse_include_path(rest: &str) -> Option<&str> {
+    let rest = rest.trim();
+    let path = rest.strip_prefix('"')?.strip_suffix('"')?;
+    (!path.is_empty()).then_some(path)
+}
+
+/// Sources and compile-time defines of a program in the [`ShaderLibrary`].
+#[derive(Clone, Copy)]
+pub struct ProgramDesc {
+    /// Vertex shader path relative to `assets/shaders`.
+    pub vertex: &'static str,
+    /// Fragment shader path relative to `assets/shaders`.
+    pub fragment: &'static str,
+    pub defines: &'static [(&'static str, &'static str)],
+    /// Set when the vertex shader writes `gl_PointSize`.
+    pub uses_point_size: bool,
+    /// Set when the fragment shader encodes its output to sRGB itself. Otherwise glium enables
+    /// `GL_FRAMEBUFFER_SRGB` so that linear output is encoded when drawing to an sRGB target.
+    pub outputs_srgb: bool,
+}
+
+struct ProgramEntry {
+    desc: ProgramDesc,
+    program: Program,
+    /// Every file the program was built from, used to decide what to rebuild on changes.
+    files: Vec<String>,
+    /// Mapped log of the last failed compilation, cleared by the next successful one.
+    error: Option<String>,
+}
+
+/// Named shader programs compiled through [`preprocess`].
+pub struct ShaderLibrary {
+    programs: HashMap<&'static str, ProgramEntry>,
+    from_disk: bool,
+}
+
+impl ShaderLibrary {
+    /// With `from_disk` sources are read from `assets/shaders` instead of the baked-in copies.
+    pub fn new(from_disk: bool) -> Self {
+        Self {
+            programs: HashMap::new(),
+            from_disk,
+        }
+    }
+
+    /// Compiles and registers a program under `name`.
+    pub fn load(
+        &mut self,
+        display: &Display<WindowSurface>,
+        name: &'static str,
+        desc: ProgramDesc,
+    ) -> Result<()> {
+        let (program, files) = compile(display, &desc, self.from_disk)
+            .or_else(|e| {
+                if !self.from_disk {
+                    return Err(e);
+                }
+                error!("Shader {name} failed to compile from disk, using baked-in copy: {e:#}");
+                compile(display, &desc, false)
+            })
+            .with_context(|| format!("failed to build shader program {name}"))?;
+        self.programs.insert(
+            name,
+            ProgramEntry {
+                desc,
+                program,
+                files,
+                error: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns the program registered as `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no program with that name has been loaded.
+    pub fn get(&self, name: &str) -> &Program {
+        &self
+            .programs
+            .get(name)
+            .unwrap_or_else(|| panic!("shader program {name} has not been loaded"))
+            .program
+    }
+
+    /// All files on disk that the registered programs are built from.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self
+            .programs
+            .values()
+            .flat_map(|entry| {
+                entry
+                    .files
+                    .iter()
+                    .map(|file| Path::new(SHADER_DIR).join(file))
+            })
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Recompiles every program built from `path` using the sources on disk. Programs that fail
+    /// keep their previous version and report the error through [`ShaderLibrary::errors`].
+    pub fn reload_file(&mut self, display: &Display<WindowSurface>, path: &Path) {
+        let Ok(relative) = path.strip_prefix(SHADER_DIR) else {
+            return;
+        };
+        for (name, entry) in &mut self.programs {
+            if !entry.files.iter().any(|file| Path::new(file) == relative) {
+                continue;
+            }
+            match compile(display, &entry.desc, true) {
+                Ok((program, files)) => {
+                    info!("Reloaded shader program {name}");
+                    entry.program = program;
+                    entry.files = files;
+                    entry.error = None;
+                }
+                Err(e) => {
+                    error!("Failed to reload shader program {name}: {e:#}");
+                    entry.error = Some(format!("{e:#}"));
+                }
+            }
+        }
+    }
+
+    /// Programs whose last reload failed, with the mapped compile log.
+    pub fn errors(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.programs
+            .iter()
+            .filter_map(|(name, entry)| entry.error.as_deref().map(|error| (*name, error)))
+    }
+}
+
+/// Preprocesses and compiles `desc`, returning the program and every file it was built from.
+fn compile(
+    display: &Display<WindowSurface>,
+    desc: &ProgramDesc,
+    from_disk: bool,
+) -> Result<(Program, Vec<String>)> {
+    let mut read = |file: &str| read_shader(file, from_disk);
+    let vertex = preprocess(desc.vertex, desc.defines, &mut read)?;
+    let fragment = preprocess(desc.fragment, desc.defines, &mut read)?;
+
+    let input = ProgramCreationInput::SourceCode {
+        vertex_shader: &vertex.code,
+        tessellation_control_shader: None,
+        tessellation_evaluation_shader: None,
+        geometry_shader: None,
+        fragment_shader: &fragment.code,
+        transform_feedback_varyings: None,
+        outputs_srgb: desc.outputs_srgb,
+        uses_point_size: desc.uses_point_size,
+    };
+    let program = Program::new(display, input).map_err(|e| match e {
+        glium::ProgramCreationError::CompilationError(log, glium::program::ShaderType::Vertex) => {
+            anyhow!("vertex shader:\n{}", vertex.map_error_log(&log))
+        }
+        glium::ProgramCreationError::CompilationError(
+            log,
+            glium::program::ShaderType::Fragment,
+        ) => anyhow!("fragment shader:\n{}", fragment.map_error_log(&log)),
+        e => anyhow!("{e}"),
+    })?;
+
+    let mut files = vertex.files;
+    files.extend(fragment.files);
+    files.sort();
+    files.dedup();
+    Ok((program, files))
+}
+
+fn read_shader(file: &str, from_disk: bool) -> Result<String> {
+    if from_disk {
+        let path = Path::new(SHADER_DIR).join(file);
+        return fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path.display()));
+    }
+    EMBEDDED_SHADERS
+        .iter()
+        .find(|(name, _)| *name == file)
+        .map(|(_, source)| (*source).to_owned())
+        .ok_or_else(|| anyhow!("shader {file} is not embedded in the binary"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Preprocesses `file` from an in-memory set of sources.
+    fn run(files: &[(&str, &str)], file: &str, defines: &[(&str, &str)]) -> Result<Preprocessed> {
+        preprocess(file, defines, &mut |path| {
+            files
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(_, source)| (*source).to_owned())
+                .ok_or_else(|| anyhow!("no file {path}"))
+        })
+    }
+
+    #[test]
+    fn expands_nested_includes() {
+        let files = [
+            (
+                "main.frag",
+                "#version 330\n#include \"a.glsl\"\nvoid main() {}",
+            ),
+            ("a.glsl", "// a\n#include \"b.glsl\"\n// a end"),
+            ("b.glsl", "// b"),
+        ];
+        let output = run(&files, "main.frag", &[]).unwrap();
+        assert_eq!(
+            output.code,
+            "#version 330\n// a\n// b\n// a end\nvoid main() {}\n"
+        );
+        assert_eq!(output.files, ["main.frag", "a.glsl", "b.glsl"]);
+        assert_eq!(output.location(3).as_deref(), Some("b.glsl:1"));
+        assert_eq!(output.location(4).as_deref(), Some("a.glsl:3"));
+        assert_eq!(output.location(5).as_deref(), Some("main.frag:3"));
+        assert_eq!(output.location(6), None);
+    }
+
+    #[test]
+    fn includes_each_file_once() {
+        let files = [
+            (
+                "main.frag",
+                "#include \"a.glsl\"\n#include \"b.glsl\"\n#include \"a.glsl\"",
+            ),
+            ("a.glsl", "// a"),
+            ("b.glsl", "#include \"a.glsl\"\n// b"),
+        ];
+        let output = run(&files, "main.frag", &[]).unwrap();
+        assert_eq!(output.code, "// a\n// b\n");
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let files = [
+            ("main.frag", "#include \"a.glsl\""),
+            ("a.glsl", "#include \"b.glsl\""),
+            ("b.glsl", "\n#include \"a.glsl\""),
+        ];
+        let error = run(&files, "main.frag", &[]).unwrap_err().to_string();
+        assert!(error.starts_with("b.glsl:2: include cycle"), "{error}");
+        assert!(
+            error.contains("main.frag -> a.glsl -> b.glsl -> a.glsl"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn reports_malformed_and_missing_includes() {
+        for directive in ["#include a.glsl", "#include \"\"", "#include \"a.glsl"] {
+            let files = [("main.frag", directive)];
+            let error = run(&files, "main.frag", &[]).unwrap_err().to_string();
+            assert!(
+                error.starts_with("main.frag:1: malformed #include"),
+                "{error}"
+            );
+        }
+        let files = [("main.frag", "// main\n#include \"missing.glsl\"")];
+        let error = run(&files, "main.frag", &[]).unwrap_err().to_string();
+        assert!(error.starts_with("main.frag:2: cannot include"), "{error}");
+    }
+
+    #[test]
+    fn inserts_defines_after_version() {
+        let files = [
+            ("main.frag", "// header\n#version 330\n#include \"a.glsl\""),
+            ("a.glsl", "// a"),
+        ];
+        let output = run(&files, "main.frag", &[("SAMPLES", "4"), ("HDR", "1")]).unwrap();
+        assert_eq!(
+            output.code,
+            "// header\n#version 330\n#define SAMPLES 4\n#define HDR 1\n// a\n"
+        );
+        assert_eq!(output.location(3).as_deref(), Some("main.frag:<generated>"));
+        assert_eq!(output.location(5).as_deref(), Some("a.glsl:1"));
+    }
+
+    #[test]
+    fn inserts_defines_at_the_top_without_version() {
+        let files = [
+            ("main.frag", "// main\n#include \"a.glsl\""),
+            ("a.glsl", "// a"),
+        ];
+        let output = run(&files, "main.frag", &[("HDR", "1")]).unwrap();
+        assert_eq!(output.code, "#define HDR 1\n// main\n// a\n");
+    }
+
+    #[test]
+    fn maps_driver_logs() {
+        let files = [
+            (
+                "main.frag",
+                "#version 330\n#include \"a.glsl\"\nvoid main() {}",
+            ),
+            ("a.glsl", "// a\nfloat broken"),
+        ];
+        let output = run(&files, "main.frag", &[("HDR", "1")]).unwrap();
+        let mesa = "0:4(1): error: syntax error, unexpected end of file";
+        let nvidia = "0(5) : error C0000: syntax error";
+        assert_eq!(
+            output.map_error_log(&format!("{mesa}\n{nvidia}")),
+            "a.glsl:2(1): error: syntax error, unexpected end of file\n\
+             main.frag:3 : error C0000: syntax error"
+        );
+        // Lines past the end and numbers not in driver format stay untouched.
+        let unmapped = "0:99(1): error: 10(2) is not 0.5";
+        assert_eq!(output.map_error_log(unmapped), unmapped);
+    }
+}
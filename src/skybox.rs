@@ -1,33 +1,66 @@
-use anyhow::Result;
-use std::{fs::File, io::BufReader};
+use anyhow::{Result, bail, ensure};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use glium::{Display, Surface, Texture2d, glutin::surface::WindowSurface, texture::Cubemap};
-use log::{ info};
+use glium::{
+    Display, Surface,
+    glutin::surface::WindowSurface,
+    texture::{Cubemap, MipmapsOption, RawImage2d},
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler},
+};
+use image::RgbaImage;
+use log::info;
 
-use crate::egui_setup::App;
+use crate::{
+    assets::{AssetData, AssetId, AssetLoader},
+    cubemap::{CubeFace, FaceConvention},
+    shaders::ProgramDesc,
+};
+
+/// Maximum anisotropy requested for the skybox sampler. Drivers clamp this to what they support.
+const SKYBOX_ANISOTROPY: u16 = 16;
+
+/// The bundled galaxy pack was exported from Unity.
+pub const SKYBOX_CONVENTION: FaceConvention = FaceConvention::Unity;
+
+pub const SKYBOX_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "skybox.vert",
+    fragment: "skybox.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+const SKYBOX_BASE_PATH: &str = "assets/skybox/";
+const SKYBOX_FACE_FILENAMES: [&str; 6] = [
+    "GalaxyTex_PositiveX.png", // Positive X
+    "GalaxyTex_NegativeX.png", // Negative X
+    "GalaxyTex_PositiveY.png", // Positive Y
+    "GalaxyTex_NegativeY.png", // Negative Y
+    "GalaxyTex_PositiveZ.png", // Positive Z
+    "GalaxyTex_NegativeZ.png", // Negative Z
+];
 
 pub struct Skybox {
-    pub textures: Vec<Texture2d>,
     pub vertex_buffer: glium::VertexBuffer<SkyboxVertex>,
-    pub cubemap_program: glium::Program,
-    pub cubemap: Cubemap,
+    /// `None` until the first set of faces has finished loading.
+    pub cubemap: Option<Cubemap>,
+    pub convention: FaceConvention,
     pub index_buffer: glium::IndexBuffer<u16>,
+    pending: Option<PendingFaces>,
 }
 
 impl Skybox {
-    pub fn init(display: &Display<WindowSurface>, cam_dist: f32) -> Self {
+    /// Creates the skybox and queues its faces on `assets`. The program is expected to be
+    /// registered in the shader library as `"skybox"` from [`SKYBOX_PROGRAM`].
+    pub fn init(display: &Display<WindowSurface>, assets: &mut AssetLoader) -> Self {
         info!("Initializing skybox...");
-        Skybox {
-            textures: load_skybox_textures(display),
-            vertex_buffer: create_skybox_vb(&display, cam_dist).unwrap(),
-            cubemap_program: glium::Program::from_source(
-                display,
-                include_str!("../assets/shaders/skybox.vert"),
-                include_str!("../assets/shaders/skybox.frag"),
-                None,
-            )
-            .unwrap(),
-            cubemap: Cubemap::empty(display, 2048).unwrap(), // Placeholder, will be filled later
+        let mut skybox = Skybox {
+            vertex_buffer: create_skybox_vb(display).unwrap(),
+            cubemap: None,
+            convention: SKYBOX_CONVENTION,
             index_buffer: glium::IndexBuffer::new(
                 display,
                 glium::index::PrimitiveType::TrianglesList,
@@ -42,140 +75,185 @@ impl Skybox {
                 ],
             )
             .unwrap(),
+            pending: None,
+        };
+        skybox.request_faces(assets, SKYBOX_CONVENTION);
+        skybox
+    }
+
+    /// Starts loading the skybox faces for `convention`. The current cubemap stays in use until
+    /// all six faces have been uploaded. Faces still pending from an earlier request are dropped.
+    pub fn request_faces(&mut self, assets: &mut AssetLoader, convention: FaceConvention) {
+        if let Some(pending) = self.pending.take() {
+            pending.discard(assets);
         }
+        info!("Loading skybox textures ({} faces)...", convention.name());
+        let ids = SKYBOX_FACE_FILENAMES
+            .map(|filename| Some(assets.load_image(format!("{SKYBOX_BASE_PATH}{filename}"))));
+        self.pending = Some(PendingFaces {
+            convention,
+            ids,
+            cubemap: None,
+            time_start: Instant::now(),
+        });
     }
-}
 
-pub fn upload_sky_box(app: &App) {
-    let dest_rect1 = glium::BlitTarget {
-        left: 0,
-        bottom: 0,
-        width: 2048,
-        height: 2048,
-    };
+    /// Uploads any faces that finished decoding. Once all six are on the GPU the new cubemap
+    /// gets its mipmaps and replaces the current one.
+    pub fn update(
+        &mut self,
+        display: &Display<WindowSurface>,
+        assets: &mut AssetLoader,
+    ) -> Result<()> {
+        let Some(pending) = &mut self.pending else {
+            return Ok(());
+        };
+        match pending.receive(display, assets) {
+            Ok(false) => Ok(()),
+            Ok(true) => {
+                let pending = self.pending.take().expect("pending faces checked above");
+                let cubemap = pending.cubemap.expect("all faces have been uploaded");
+                // SAFETY: every face of level 0 has been written and the cubemap is owned by
+                // this display's context.
+                unsafe { cubemap.generate_mipmaps() };
+                info!(
+                    "Loaded {0}x{0} skybox cubemap with {1} mip levels in {2:.2?}",
+                    cubemap.dimensions(),
+                    cubemap.get_mipmap_levels(),
+                    pending.time_start.elapsed()
+                );
+                self.cubemap = Some(cubemap);
+                self.convention = pending.convention;
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(pending) = self.pending.take() {
+                    pending.discard(assets);
+                }
+                Err(e)
+            }
+        }
+    }
 
-    let display = &app.glium_attributes.display;
-    let skybox = &app.skybox;
+    /// Texture files that should be watched for hot reloading.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        SKYBOX_FACE_FILENAMES
+            .iter()
+            .map(|filename| Path::new(SKYBOX_BASE_PATH).join(filename))
+            .collect()
+    }
 
-    let framebuffer1 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::PositiveX),
-    )
-    .unwrap();
-    let framebuffer2 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::NegativeX),
-    )
-    .unwrap();
-    let framebuffer3 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::PositiveY),
-    )
-    .unwrap();
-    let framebuffer4 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::NegativeY),
-    )
-    .unwrap();
-    let framebuffer5 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::PositiveZ),
-    )
-    .unwrap();
-    let framebuffer6 = glium::framebuffer::SimpleFrameBuffer::new(
-        display,
-        skybox
-            .cubemap
-            .main_level()
-            .image(glium::texture::CubeLayer::NegativeZ),
-    )
-    .unwrap();
+    /// Reloads the faces if `path` is one of the skybox textures.
+    pub fn on_file_changed(&mut self, assets: &mut AssetLoader, path: &Path) {
+        if SKYBOX_FACE_FILENAMES
+            .iter()
+            .any(|filename| path == Path::new(SKYBOX_BASE_PATH).join(filename))
+        {
+            self.request_faces(assets, self.requested_convention());
+        }
+    }
 
-    let tex_posx = &skybox.textures[0];
-    let tex_negx = &skybox.textures[1];
-    let tex_posy = &skybox.textures[2];
-    let tex_negy = &skybox.textures[3];
-    let tex_posz = &skybox.textures[4];
-    let tex_negz = &skybox.textures[5];
+    /// Face convention that is in use or currently being loaded.
+    pub fn requested_convention(&self) -> FaceConvention {
+        self.pending
+            .as_ref()
+            .map_or(self.convention, |pending| pending.convention)
+    }
 
-    tex_posx.as_surface().blit_whole_color_to(
-        &framebuffer1,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
-    tex_negx.as_surface().blit_whole_color_to(
-        &framebuffer2,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
-    tex_negy.as_surface().blit_whole_color_to(
-        &framebuffer3,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
-    tex_posy.as_surface().blit_whole_color_to(
-        &framebuffer4,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
-    tex_posz.as_surface().blit_whole_color_to(
-        &framebuffer5,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
-    tex_negz.as_surface().blit_whole_color_to(
-        &framebuffer6,
-        &dest_rect1,
-        glium::uniforms::MagnifySamplerFilter::Linear,
-    );
+    /// Trilinear, anisotropic sampler for the cubemap to avoid shimmering of distant stars.
+    pub fn sampled(&self) -> Option<Sampler<'_, Cubemap>> {
+        self.cubemap.as_ref().map(|cubemap| {
+            cubemap
+                .sampled()
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+                .anisotropy(SKYBOX_ANISOTROPY)
+        })
+    }
 }
 
-fn load_skybox_textures(display: &Display<WindowSurface>) -> Vec<Texture2d> {
-    info!("Loading skybox textures...");
-    let base_path = "assets/skybox/";
-    let face_filenames = [
-        "GalaxyTex_PositiveX.png",  // Positive X
-        "GalaxyTex_NegativeX.png",   // Negative X
-        "GalaxyTex_PositiveY.png",    // Positive Y
-        "GalaxyTex_NegativeY.png", // Negative Y
-        "GalaxyTex_PositiveZ.png",  // Positive Z
-        "GalaxyTex_NegativeZ.png",   // Negative Z
-    ];
+/// Skybox faces that are still being decoded. Faces are blitted into a mipmapped cubemap as soon
+/// as they arrive; its size is taken from the first face. Each face goes through a short-lived
+/// `Texture2d` that is dropped right after the blit, so only the cubemap stays resident in VRAM.
+struct PendingFaces {
+    convention: FaceConvention,
+    /// Asset ids indexed by source face, cleared once the face has been uploaded.
+    ids: [Option<AssetId>; 6],
+    cubemap: Option<Cubemap>,
+    time_start: Instant,
+}
 
-    let time_start = std::time::Instant::now();
+impl PendingFaces {
+    /// Returns `true` once every face has been uploaded.
+    fn receive(
+        &mut self,
+        display: &Display<WindowSurface>,
+        assets: &mut AssetLoader,
+    ) -> Result<bool> {
+        for source in CubeFace::ALL {
+            let Some(id) = self.ids[source.index()] else {
+                continue;
+            };
+            let Some(result) = assets.take(id) else {
+                continue;
+            };
+            self.ids[source.index()] = None;
+            let AssetData::Image(image) = result? else {
+                bail!("skybox face {source:?} is not an image");
+            };
+            self.upload(display, source, image)?;
+        }
+        Ok(self.ids.iter().all(Option::is_none))
+    }
 
-    let images = face_filenames
-        .iter()
-        .map(|filename| {
-            let path = format!("{}{}", base_path, filename);
-            let reader = BufReader::new(File::open(&path).unwrap());
-            let image = image::load(reader, image::ImageFormat::Png)
-                .unwrap()
-                .to_rgba8();
-            let image_dimensions = image.dimensions();
-            let image =
-                glium::texture::RawImage2d::from_raw_rgba_reversed(&image, image_dimensions);
-            glium::Texture2d::new(display, image).unwrap()
-        })
-        .collect();
+    /// Tells the loader that the faces that have not arrived yet are no longer needed.
+    fn discard(self, assets: &mut AssetLoader) {
+        for id in self.ids.into_iter().flatten() {
+            assets.discard(id);
+        }
+    }
 
-    info!("Loaded skybox textures in {:.2?}", time_start.elapsed());
-    images
+    fn upload(
+        &mut self,
+        display: &Display<WindowSurface>,
+        source: CubeFace,
+        image: RgbaImage,
+    ) -> Result<()> {
+        let size = match &self.cubemap {
+            Some(cubemap) => cubemap.dimensions(),
+            None => image.width(),
+        };
+        ensure!(
+            image.dimensions() == (size, size),
+            "skybox face {} is {:?}, expected a square {size}x{size} image",
+            SKYBOX_FACE_FILENAMES[source.index()],
+            image.dimensions()
+        );
+        if self.cubemap.is_none() {
+            self.cubemap = Some(Cubemap::empty_with_mipmaps(
+                display,
+                MipmapsOption::EmptyMipmaps,
+                size,
+            )?);
+        }
+        let cubemap = self.cubemap.as_ref().expect("cubemap allocated above");
+
+        let target = CubeFace::ALL
+            .into_iter()
+            .find(|&face| self.convention.mapping(face).source == source)
+            .expect("every source face is mapped to exactly one target face");
+        let image = self.convention.mapping(target).orientation.apply(image);
+        let image = RawImage2d::from_raw_rgba(image.into_raw(), (size, size));
+        let texture = glium::Texture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap)?;
+        let framebuffer = glium::framebuffer::SimpleFrameBuffer::new(
+            display,
+            cubemap.main_level().image(target.layer()),
+        )?;
+        texture
+            .as_surface()
+            .fill(&framebuffer, MagnifySamplerFilter::Nearest);
+        Ok(())
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -185,11 +263,10 @@ pub struct SkyboxVertex {
 
 implement_vertex!(SkyboxVertex, position);
 
-fn create_skybox_vb(
-    display: &Display<WindowSurface>,
-    cam_dist: f32,
-) -> Result<glium::VertexBuffer<SkyboxVertex>> {
-    let side2 = cam_dist / 2.0;
+fn create_skybox_vb(display: &Display<WindowSurface>) -> Result<glium::VertexBuffer<SkyboxVertex>> {
+    // Only directions matter for the cubemap lookup, so a unit cube is enough. The vertex shader
+    // pushes it onto the far plane.
+    let side2 = 1.0;
     let skybox_vertices = [
         // Front
         SkyboxVertex {
@@ -0,0 +1,477 @@
+//! A piloted spacecraft.
+//!
+//! The craft's [`Flight`] moves under its own thrust and the gravity of every body (see
+//! [`GravityField`]) in simulation time, so time warp speeds up coasting along an orbit. Its attitude is flown in real
+//! time: control inputs accelerate its rotation, the rotation keeps going until countered, and
+//! the optional stability assist damps it out. Touching a body sets the craft down on the surface,
+//! where it rides along with the body until it thrusts away again.
+//!
+//! The state is heliocentric ecliptic in AU and AU/day like the orbit module, and the attitude is
+//! a rotation in the scene frame. The camera either sits in the cockpit or follows behind.
+
+use anyhow::Result;
+use glam::{DVec3, Mat4, Quat, Vec3};
+use glium::{Display, DrawParameters, Program, Surface, glutin::surface::WindowSurface};
+
+use crate::{
+    assets::MeshData,
+    asteroids::RockMesh,
+    celestial::{ecliptic_to_scene, scene_to_ecliptic},
+    emitter::{Emitter, Placement, exhaust},
+    gravity::GravityField,
+    kepler::OrbitalElements,
+    maneuver::{ManeuverNode, burn_vector},
+    shaders::ProgramDesc,
+    system::AU,
+};
+
+pub const SHIP_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "ship.vert",
+    fragment: "asteroid.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+/// Kilometres per AU and seconds per day, for the HUD.
+pub const KM_PER_AU: f64 = 1.495_978_707e8;
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+/// Length of the craft in scene units.
+const SHIP_LENGTH: f32 = 0.2;
+/// Angular acceleration in radians per second² at full control input.
+const ANGULAR_ACCELERATION: f32 = 3.0;
+/// Rate at which the stability assist damps rotation, per second.
+const STABILITY_DAMPING: f32 = 4.0;
+/// Longest integration step in days, shortened further close to a body.
+const MAX_STEP: f64 = 0.05;
+const MAX_STEPS_PER_FRAME: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Cockpit,
+    Chase,
+}
+
+impl CameraMode {
+    pub const ALL: [Self; 2] = [Self::Cockpit, Self::Chase];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Cockpit => "Cockpit",
+            CameraMode::Chase => "Chase",
+        }
+    }
+}
+
+/// Pilot input for one frame. Every component is in `-1..=1`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShipControls {
+    /// Thrust along the craft's right, up and forward axes.
+    pub translation: Vec3,
+    /// Pitch, yaw and roll.
+    pub rotation: Vec3,
+    /// Multiplies the thrust.
+    pub boost: bool,
+}
+
+/// Where the craft is and where it is going, integrated in simulation time.
+#[derive(Clone, Debug, Default)]
+pub struct Flight {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    /// Body the craft is resting on.
+    pub landed_on: Option<usize>,
+    /// Planned burns, executed in order of time when the clock reaches them.
+    pub maneuvers: Vec<ManeuverNode>,
+}
+
+impl Flight {
+    /// A circular prograde orbit `altitude` AU above body `index` at the field's time, starting
+    /// on the side facing the sun.
+    pub fn circular(field: &GravityField, index: usize, altitude: f64) -> Self {
+        let body = &field.attractors[index];
+        let sunward = -body.position;
+        let elements = OrbitalElements {
+            semi_major_axis: body.radius + altitude,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: sunward.y.atan2(sunward.x),
+            mean_anomaly: 0.0,
+        };
+        let (position, velocity) = elements.state_vector(body.gm);
+        Self {
+            position: body.position + position,
+            velocity: body.velocity + velocity,
+            ..Default::default()
+        }
+    }
+
+    /// Leapfrog integration over the `dt_days` leading up to the field's time, under gravity and
+    /// a constant `thrust` in AU/day², in steps short against the local orbital time scale that
+    /// end exactly on maneuver nodes.
+    pub fn advance(&mut self, field: &GravityField, thrust: DVec3, dt_days: f64) {
+        self.maneuvers.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let mut days = field.days - dt_days;
+        let mut remaining = dt_days;
+        let mut steps = 0;
+        while remaining > 0.0 && steps < MAX_STEPS_PER_FRAME {
+            let mut step = self.step_size(field, days).min(remaining);
+            if let Some(node) = self.maneuvers.first() {
+                step = step.min(node.time.max(0.0));
+            }
+            let acceleration = field.acceleration(self.position, days) + thrust;
+            self.velocity += acceleration * (step / 2.0);
+            self.position += self.velocity * step;
+            days += step;
+            let acceleration = field.acceleration(self.position, days) + thrust;
+            self.velocity += acceleration * (step / 2.0);
+            self.land(field, days);
+            for node in &mut self.maneuvers {
+                node.time -= step;
+            }
+            if self.maneuvers.first().is_some_and(|node| node.time <= 0.0) {
+                let node = self.maneuvers.remove(0);
+                self.burn(field, node.delta_v, days);
+            }
+            remaining -= step;
+            steps += 1;
+        }
+    }
+
+    /// Changes the velocity by `delta_v` in the orbital frame of the dominant body at `days`, see
+    /// [`burn_vector`].
+    fn burn(&mut self, field: &GravityField, delta_v: DVec3, days: f64) {
+        let (center, center_velocity) = field
+            .dominant(self.position, days)
+            .map_or((DVec3::ZERO, DVec3::ZERO), |index| field.state(index, days));
+        self.velocity += burn_vector(
+            self.position - center,
+            self.velocity - center_velocity,
+            delta_v,
+        );
+    }
+
+    /// A fiftieth of the orbital time scale around the dominant body at `days`.
+    fn step_size(&self, field: &GravityField, days: f64) -> f64 {
+        field
+            .dominant(self.position, days)
+            .map(|index| {
+                let body = &field.attractors[index];
+                let r = field
+                    .state(index, days)
+                    .0
+                    .distance(self.position)
+                    .max(body.radius);
+                (r * r * r / body.gm).sqrt() / 50.0
+            })
+            .unwrap_or(MAX_STEP)
+            .min(MAX_STEP)
+    }
+
+    /// Rests the craft on the surface of any body it has sunk into at `days`, keeping only motion
+    /// away from the surface relative to the body.
+    fn land(&mut self, field: &GravityField, days: f64) {
+        self.landed_on = field.inside(self.position, days);
+        let Some(index) = self.landed_on else {
+            return;
+        };
+        let (center, center_velocity) = field.state(index, days);
+        let normal = (self.position - center).normalize();
+        let climb = (self.velocity - center_velocity).dot(normal).max(0.0);
+        self.position = center + normal * field.attractors[index].radius;
+        self.velocity = center_velocity + normal * climb;
+    }
+
+    /// Offset from body `index` and velocity relative to its motion, at the field's time.
+    pub fn relative_to(&self, field: &GravityField, index: usize) -> (DVec3, DVec3) {
+        let body = &field.attractors[index];
+        (self.position - body.position, self.velocity - body.velocity)
+    }
+
+    /// The dominant body at the field's time and the craft's orbit around it, `None` outside every
+    /// sphere of influence or on a radial path, which has no orbit.
+    pub fn orbit(&self, field: &GravityField) -> Option<(usize, OrbitalElements)> {
+        let index = field.dominant(self.position, field.days)?;
+        let orbit = field.orbit_around(index, self.position, self.velocity, field.days)?;
+        Some((index, orbit))
+    }
+}
+
+pub struct Spacecraft {
+    pub flight: Flight,
+    pub orientation: Quat,
+    /// In the craft's frame, radians per second.
+    angular_velocity: Vec3,
+    /// Main engine acceleration in AU/day².
+    pub thrust: f64,
+    pub stability_assist: bool,
+    pub exhaust: Emitter,
+    mesh: RockMesh,
+}
+
+impl Spacecraft {
+    /// Puts the craft on a circular prograde orbit `altitude` AU above body `index`, see
+    /// [`Flight::circular`].
+    pub fn in_orbit(
+        display: &Display<WindowSurface>,
+        field: &GravityField,
+        index: usize,
+        altitude: f64,
+    ) -> Result<Self> {
+        let flight = Flight::circular(field, index, altitude);
+        let prograde = flight.velocity - field.attractors[index].velocity;
+        let orientation =
+            Quat::from_rotation_arc(Vec3::NEG_Z, ecliptic_to_scene(prograde.normalize()));
+        Ok(Self {
+            flight,
+            orientation,
+            angular_velocity: Vec3::ZERO,
+            thrust: 1e-3,
+            stability_assist: true,
+            exhaust: Emitter::new(exhaust(SHIP_LENGTH), 0x5eed),
+            mesh: RockMesh::new(display, &ship_mesh())?,
+        })
+    }
+
+    pub fn scene_position(&self) -> Vec3 {
+        ecliptic_to_scene(self.flight.position) * AU
+    }
+
+    /// Unit vectors along the craft's nose and top in the scene frame. The nose is -Z.
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::NEG_Z
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::Y
+    }
+
+    /// Applies `controls` for `dt` real seconds, of which the last `dt_days` before the field's
+    /// time pass in simulation time.
+    pub fn update(&mut self, field: &GravityField, controls: &ShipControls, dt: f32, dt_days: f64) {
+        self.angular_velocity += controls.rotation * ANGULAR_ACCELERATION * dt;
+        if self.stability_assist && controls.rotation == Vec3::ZERO {
+            self.angular_velocity *= (-STABILITY_DAMPING * dt).exp();
+        }
+        let angle = self.angular_velocity.length() * dt;
+        if angle > 0.0 {
+            let local = Quat::from_axis_angle(self.angular_velocity.normalize(), angle);
+            self.orientation = (self.orientation * local).normalize();
+        }
+
+        let boost = if controls.boost { 10.0 } else { 1.0 };
+        // Positive forward input pushes along the nose, which is -Z in the craft's frame.
+        let local = controls.translation * Vec3::new(1.0, 1.0, -1.0);
+        let thrust_direction = scene_to_ecliptic(self.orientation * local);
+        let thrust = thrust_direction * self.thrust * boost;
+        if dt_days > 0.0 {
+            self.flight.advance(field, thrust, dt_days);
+        }
+
+        let nozzle = self.scene_position() - self.forward() * SHIP_LENGTH * 0.5;
+        self.exhaust.enabled = controls.translation.z > 0.0;
+        self.exhaust.set_direction(-self.forward());
+        self.exhaust.update(
+            dt,
+            Some(Placement {
+                position: nozzle,
+                radius: 0.0,
+            }),
+        );
+    }
+
+    /// Camera position, target and up vector for `mode`.
+    pub fn camera_pose(&self, mode: CameraMode) -> (Vec3, Vec3, Vec3) {
+        let position = self.scene_position();
+        let forward = self.forward();
+        let up = self.up();
+        match mode {
+            CameraMode::Cockpit => {
+                let eye = position + forward * SHIP_LENGTH * 0.5;
+                (eye, eye + forward, up)
+            }
+            CameraMode::Chase => {
+                let eye = position - forward * SHIP_LENGTH * 4.0 + up * SHIP_LENGTH;
+                (eye, position + forward * SHIP_LENGTH * 2.0, up)
+            }
+        }
+    }
+
+    /// Draws the hull, lit by the star at `light_position`.
+    pub fn draw(
+        &self,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+        light_position: Vec3,
+        light_color: [f32; 3],
+    ) -> Result<()> {
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::splat(SHIP_LENGTH),
+            self.orientation,
+            self.scene_position(),
+        );
+        let uniforms = uniform! {
+            model: model.to_cols_array_2d(),
+            view: view.to_cols_array_2d(),
+            perspective: perspective.to_cols_array_2d(),
+            tint: [0.6f32, 0.6, 0.65],
+            light_position: light_position.to_array(),
+            light_color: light_color,
+        };
+        let params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        frame.draw(
+            &self.mesh.vertex_buffer,
+            &self.mesh.index_buffer,
+            program,
+            &uniforms,
+            &params,
+        )?;
+        Ok(())
+    }
+}
+
+/// Arrowhead hull one unit long with its nose at -Z, with flat-shaded faces.
+fn ship_mesh() -> MeshData {
+    let nose = Vec3::new(0.0, 0.0, -0.5);
+    let tail = [
+        Vec3::new(-0.3, -0.05, 0.5),
+        Vec3::new(0.0, 0.12, 0.5),
+        Vec3::new(0.3, -0.05, 0.5),
+        Vec3::new(0.0, -0.1, 0.4),
+    ];
+    let mut triangles = Vec::new();
+    for i in 0..tail.len() {
+        triangles.push([nose, tail[i], tail[(i + 1) % tail.len()]]);
+    }
+    triangles.push([tail[0], tail[2], tail[1]]);
+    triangles.push([tail[0], tail[3], tail[2]]);
+
+    let mut mesh = MeshData::default();
+    for [a, b, c] in triangles {
+        let normal = (b - a).cross(c - a).normalize();
+        for vertex in [a, b, c] {
+            mesh.indices.push(mesh.positions.len() as u32);
+            mesh.positions.push(vertex.to_array());
+            mesh.normals.push(normal.to_array());
+            mesh.tex_coords.push([0.0; 2]);
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::BodyDefinition, kepler::GM_SUN, system::solar_system};
+
+    fn field_of(names: &[&str]) -> GravityField {
+        let bodies: Vec<_> = solar_system()
+            .into_iter()
+            .filter(|body| names.contains(&body.name.as_str()))
+            .collect();
+        GravityField::new(&bodies)
+    }
+
+    /// Flies `flight` for `days` one-day frames, moving the field along like the app does.
+    fn fly(flight: &mut Flight, field: &mut GravityField, thrust: DVec3, days: usize) {
+        for _ in 0..days {
+            field.set_time(field.days + 1.0);
+            flight.advance(field, thrust, 1.0);
+        }
+    }
+
+    #[test]
+    fn thrust_accelerates_uniformly_in_empty_space() {
+        let mut field = GravityField::new(&[]);
+        let velocity = DVec3::new(0.01, 0.0, 0.0);
+        let mut flight = Flight {
+            velocity,
+            ..Default::default()
+        };
+        let thrust = DVec3::new(0.0, 1e-3, -2e-4);
+        fly(&mut flight, &mut field, thrust, 10);
+        // Leapfrog is exact under a constant acceleration.
+        let expected = velocity * 10.0 + thrust * 50.0;
+        assert!(flight.position.abs_diff_eq(expected, 1e-12));
+        assert!(flight.velocity.abs_diff_eq(velocity + thrust * 10.0, 1e-12));
+    }
+
+    #[test]
+    fn coasting_conserves_angular_momentum_and_energy() {
+        let mut field = GravityField::new(&[BodyDefinition {
+            name: "Sun".to_owned(),
+            gm: GM_SUN,
+            ..Default::default()
+        }]);
+        let mut flight = Flight {
+            position: DVec3::new(1.0, 0.0, 0.0),
+            velocity: DVec3::new(0.0, 0.02, 0.001),
+            ..Default::default()
+        };
+        let momentum = |flight: &Flight| flight.position.cross(flight.velocity);
+        let energy = |flight: &Flight| {
+            flight.velocity.length_squared() / 2.0 - GM_SUN / flight.position.length()
+        };
+        let (start_momentum, start_energy) = (momentum(&flight), energy(&flight));
+        fly(&mut flight, &mut field, DVec3::ZERO, 400);
+        let momentum_error = momentum(&flight).distance(start_momentum) / start_momentum.length();
+        assert!(
+            momentum_error < 1e-10,
+            "momentum drifted by {momentum_error}"
+        );
+        let energy_error = (energy(&flight) - start_energy).abs() / start_energy.abs();
+        assert!(energy_error < 1e-5, "energy drifted by {energy_error}");
+    }
+
+    #[test]
+    fn orbit_around_a_moving_planet_stays_bound() {
+        let mut field = field_of(&["Sun", "Earth"]);
+        let earth = field.find("Earth").unwrap();
+        let radius = field.attractors[earth].radius;
+        let mut flight = Flight::circular(&field, earth, radius);
+        // About four revolutions while the earth covers a quarter of its own orbit.
+        fly(&mut flight, &mut field, DVec3::ZERO, 100);
+        let (index, orbit) = flight.orbit(&field).unwrap();
+        assert_eq!(index, earth);
+        assert!((orbit.semi_major_axis / (2.0 * radius) - 1.0).abs() < 0.05);
+        assert!(orbit.eccentricity < 0.05, "e = {}", orbit.eccentricity);
+    }
+
+    #[test]
+    fn orbit_is_relative_to_the_dominant_body() {
+        let mut field = GravityField::new(&solar_system());
+        field.set_time(1000.0);
+        let mars = field.find("Mars").unwrap();
+        let body = &field.attractors[mars];
+        let r = 3.0 * body.radius;
+        let speed = 1.1 * (body.gm / r).sqrt();
+        let flight = Flight {
+            position: body.position + DVec3::new(r, 0.0, 0.0),
+            velocity: body.velocity + DVec3::new(0.0, speed, 0.0),
+            ..Default::default()
+        };
+
+        let (offset, relative) = flight.relative_to(&field, mars);
+        assert!(offset.abs_diff_eq(DVec3::new(r, 0.0, 0.0), 1e-12));
+        assert!(relative.abs_diff_eq(DVec3::new(0.0, speed, 0.0), 1e-12));
+
+        // At periapsis with 1.1 times the circular speed, e = 1.1² - 1 and a = r / (1 - e).
+        let (index, orbit) = flight.orbit(&field).unwrap();
+        assert_eq!(index, mars);
+        let eccentricity = 1.1f64.powi(2) - 1.0;
+        assert!((orbit.eccentricity - eccentricity).abs() < 1e-9);
+        assert!((orbit.semi_major_axis - r / (1.0 - eccentricity)).abs() < 1e-9 * r);
+        assert!((orbit.periapsis() - r).abs() < 1e-9 * r);
+        assert!(orbit.inclination.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,119 @@
+//! Point-sprite stars from the bundled bright star catalog.
+//!
+//! Stars are placed on the celestial sphere from their J2000 right ascension and declination and
+//! rendered like the skybox: rotation-only view and pushed to the far plane. Size and brightness
+//! follow the visual magnitude, the color follows the B-V index.
+
+use std::fs;
+
+use anyhow::{Context, Result, anyhow};
+use glium::{Display, glutin::surface::WindowSurface};
+use log::info;
+
+use crate::{
+    celestial::{ecliptic_to_scene, equatorial_direction, equatorial_to_ecliptic},
+    color::{bv_to_temperature, temperature_to_linear_rgb},
+    shaders::ProgramDesc,
+};
+
+const CATALOG_PATH: &str = "assets/catalog/bright_stars.csv";
+
+pub const STARFIELD_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "stars.vert",
+    fragment: "stars.frag",
+    defines: &[],
+    uses_point_size: true,
+    outputs_srgb: false,
+};
+
+/// Point size in pixels of a magnitude 0 star.
+const MAGNITUDE_ZERO_SIZE: f32 = 5.0;
+
+/// One catalog entry.
+#[derive(Clone, Debug)]
+pub struct Star {
+    /// Hipparcos catalog number.
+    pub hip: u32,
+    pub ra_hours: f64,
+    pub dec_deg: f64,
+    pub vmag: f32,
+    pub b_v: f32,
+}
+
+impl Star {
+    /// Unit vector towards the star in the scene frame.
+    pub fn direction(&self) -> glam::Vec3 {
+        ecliptic_to_scene(equatorial_to_ecliptic(equatorial_direction(
+            self.ra_hours,
+            self.dec_deg,
+        )))
+    }
+}
+
+/// Parses the catalog CSV. Lines starting with `#` are comments and the first remaining line is
+/// the header.
+pub fn parse_catalog(csv: &str) -> Result<Vec<Star>> {
+    csv.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .skip(1)
+        .map(|(number, line)| parse_star(line).with_context(|| format!("line {}", number + 1)))
+        .collect()
+}
+
+fn parse_star(line: &str) -> Result<Star> {
+    let fields: Vec<_> = line.split(',').map(str::trim).collect();
+    let [hip, _name, ra_hours, dec_deg, vmag, b_v] = fields[..] else {
+        return Err(anyhow!("expected 6 fields, got {}", fields.len()));
+    };
+    Ok(Star {
+        hip: hip.parse()?,
+        ra_hours: ra_hours.parse()?,
+        dec_deg: dec_deg.parse()?,
+        vmag: vmag.parse()?,
+        b_v: b_v.parse()?,
+    })
+}
+
+#[derive(Copy, Clone)]
+pub struct StarVertex {
+    direction: [f32; 3],
+    color: [f32; 3],
+    size: f32,
+}
+
+implement_vertex!(StarVertex, direction, color, size);
+
+impl StarVertex {
+    fn new(star: &Star) -> Self {
+        // Apparent size grows with the square root of the flux, so the disc area tracks it.
+        // Stars that hit the minimum size are dimmed instead.
+        let size = MAGNITUDE_ZERO_SIZE * 10f32.powf(-0.2 * star.vmag);
+        let intensity = (size / 1.5).clamp(0.2, 1.0);
+        let color = temperature_to_linear_rgb(bv_to_temperature(star.b_v)).map(|c| c * intensity);
+        Self {
+            direction: star.direction().to_array(),
+            color,
+            size: size.max(1.5),
+        }
+    }
+}
+
+pub struct Starfield {
+    pub stars: Vec<Star>,
+    pub vertex_buffer: glium::VertexBuffer<StarVertex>,
+}
+
+impl Starfield {
+    pub fn init(display: &Display<WindowSurface>) -> Result<Self> {
+        let csv = fs::read_to_string(CATALOG_PATH)
+            .with_context(|| format!("cannot read star catalog {CATALOG_PATH}"))?;
+        let stars = parse_catalog(&csv)?;
+        let vertices: Vec<_> = stars.iter().map(StarVertex::new).collect();
+        info!("Loaded {} stars from {CATALOG_PATH}", stars.len());
+        Ok(Self {
+            vertex_buffer: glium::VertexBuffer::new(display, &vertices)?,
+            stars,
+        })
+    }
+}
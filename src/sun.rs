@@ -0,0 +1,311 @@
+//! The central star.
+//!
+//! Emissive bodies are drawn by [`Planets`](crate::planet::Planets) with [`STAR_PROGRAM`], which
+//! adds limb darkening and animated granulation to the sphere. [`SunEffects`] adds what lies
+//! outside the sphere: a corona billboard around it and a screen-space lens flare. The flare fades
+//! with the fraction of the sun that is visible, measured with occlusion queries whose results are
+//! read a frame later so the CPU never waits for the GPU.
+
+use anyhow::Result;
+use glam::{Mat4, Vec3, Vec4Swizzles};
+use glium::{
+    Display, DrawParameters, Program, Surface, VertexBuffer,
+    draw_parameters::SamplesPassedQuery,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+};
+
+use crate::{body::BodyDefinition, shaders::ProgramDesc};
+
+pub const STAR_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "planet.vert",
+    fragment: "star.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const CORONA_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "corona.vert",
+    fragment: "corona.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+pub const LENS_FLARE_PROGRAM: ProgramDesc = ProgramDesc {
+    vertex: "lens_flare.vert",
+    fragment: "lens_flare.frag",
+    defines: &[],
+    uses_point_size: false,
+    outputs_srgb: false,
+};
+
+/// HDR brightness of a star's surface, well above the bloom threshold.
+pub const STAR_INTENSITY: f32 = 4.0;
+/// Distance from the center of the star to the edge of the corona quad in star radii.
+const CORONA_EXTENT: f32 = 6.0;
+/// How fast the flare follows changes in visibility, per second.
+const VISIBILITY_RATE: f32 = 12.0;
+
+/// Ghosts along the flare axis: position (0 = sun, 1 = mirrored), size in screen heights, tint.
+const FLARE_ELEMENTS: [(f32, f32, [f32; 3]); 7] = [
+    (0.0, 0.25, [0.5, 0.45, 0.4]),
+    (0.3, 0.05, [0.3, 0.5, 0.8]),
+    (0.5, 0.08, [0.4, 0.8, 0.4]),
+    (0.7, 0.04, [0.9, 0.5, 0.3]),
+    (1.0, 0.12, [0.3, 0.4, 0.9]),
+    (1.3, 0.06, [0.8, 0.3, 0.6]),
+    (1.6, 0.2, [0.2, 0.3, 0.4]),
+];
+
+pub struct SunSettings {
+    pub corona: bool,
+    pub corona_intensity: f32,
+    pub lens_flare: bool,
+    pub flare_intensity: f32,
+}
+
+impl Default for SunSettings {
+    fn default() -> Self {
+        Self {
+            corona: true,
+            corona_intensity: 1.5,
+            lens_flare: true,
+            flare_intensity: 0.3,
+        }
+    }
+}
+
+/// Corner of a quad spanning -1..1, expanded in the vertex shader.
+#[derive(Copy, Clone)]
+pub struct QuadVertex {
+    pub corner: [f32; 2],
+}
+
+implement_vertex!(QuadVertex, corner);
+
+#[derive(Copy, Clone)]
+pub struct FlareVertex {
+    corner: [f32; 2],
+    along: f32,
+    size: f32,
+    tint: [f32; 3],
+}
+
+implement_vertex!(FlareVertex, corner, along, size, tint);
+
+/// Two triangles covering the quad.
+pub const QUAD_CORNERS: [[f32; 2]; 6] = [
+    [-1.0, -1.0],
+    [1.0, -1.0],
+    [1.0, 1.0],
+    [-1.0, -1.0],
+    [1.0, 1.0],
+    [-1.0, 1.0],
+];
+
+/// Samples drawn for the occlusion test quad with and without depth testing.
+struct OcclusionQueries {
+    visible: SamplesPassedQuery,
+    total: SamplesPassedQuery,
+}
+
+pub struct SunEffects {
+    quad: VertexBuffer<QuadVertex>,
+    flare: VertexBuffer<FlareVertex>,
+    queries: Option<OcclusionQueries>,
+    /// Visible fraction from the last finished query.
+    visible_fraction: f32,
+    /// `visible_fraction` smoothed over time, used to fade the flare.
+    visibility: f32,
+}
+
+impl SunEffects {
+    /// Expects the programs registered as `"corona"` and `"lens_flare"`.
+    pub fn new(display: &Display<WindowSurface>) -> Result<Self> {
+        let quad = QUAD_CORNERS.map(|corner| QuadVertex { corner });
+        let flare: Vec<FlareVertex> = FLARE_ELEMENTS
+            .iter()
+            .flat_map(|&(along, size, tint)| {
+                QUAD_CORNERS.map(|corner| FlareVertex {
+                    corner,
+                    along,
+                    size,
+                    tint,
+                })
+            })
+            .collect();
+        Ok(Self {
+            quad: VertexBuffer::new(display, &quad)?,
+            flare: VertexBuffer::new(display, &flare)?,
+            queries: None,
+            visible_fraction: 0.0,
+            visibility: 0.0,
+        })
+    }
+
+    /// Collects finished occlusion queries and eases the flare towards the visible fraction.
+    pub fn update(&mut self, delta_time: f32) {
+        if self
+            .queries
+            .as_ref()
+            .is_some_and(|queries| queries.visible.is_ready() && queries.total.is_ready())
+        {
+            let queries = self.queries.take().expect("queries checked above");
+            let total = queries.total.get();
+            self.visible_fraction = if total == 0 {
+                0.0
+            } else {
+                (queries.visible.get() as f32 / total as f32).min(1.0)
+            };
+        }
+        let blend = (delta_time * VISIBILITY_RATE).min(1.0);
+        self.visibility += (self.visible_fraction - self.visibility) * blend;
+    }
+
+    /// Draws the corona and lens flare of `sun` and starts a new occlusion query if the last one
+    /// has been read. Call after everything that can hide the sun has written depth.
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "camera state is passed like the other renderers"
+    )]
+    pub fn draw(
+        &mut self,
+        display: &Display<WindowSurface>,
+        target: &mut impl Surface,
+        corona_program: &Program,
+        flare_program: &Program,
+        sun: &BodyDefinition,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+        time: f32,
+        settings: &SunSettings,
+    ) -> Result<()> {
+        let star_color = sun.emission_color();
+        let additive = glium::Blend {
+            color: glium::BlendingFunction::Addition {
+                source: glium::LinearBlendingFactor::One,
+                destination: glium::LinearBlendingFactor::One,
+            },
+            ..Default::default()
+        };
+        let triangles = NoIndices(PrimitiveType::TrianglesList);
+
+        if settings.corona {
+            let uniforms = uniform! {
+                center: sun.position.to_array(),
+                size: sun.radius * CORONA_EXTENT,
+                radius: sun.radius,
+                extent: CORONA_EXTENT,
+                view: view.to_cols_array_2d(),
+                perspective: perspective.to_cols_array_2d(),
+                star_color: star_color,
+                intensity: settings.corona_intensity,
+                time: time,
+            };
+            let params = DrawParameters {
+                depth: glium::Depth {
+                    test: glium::DepthTest::IfLess,
+                    write: false,
+                    ..Default::default()
+                },
+                blend: additive,
+                ..Default::default()
+            };
+            target.draw(&self.quad, triangles, corona_program, &uniforms, &params)?;
+        }
+
+        if self.queries.is_none() {
+            self.queries = Some(self.query_occlusion(
+                display,
+                target,
+                corona_program,
+                sun,
+                view,
+                perspective,
+                camera_position,
+            )?);
+        }
+
+        let clip = perspective * view * sun.position.extend(1.0);
+        if !settings.lens_flare || clip.w <= 0.0 || self.visibility <= 0.001 {
+            return Ok(());
+        }
+        let sun_position = clip.xy() / clip.w;
+        // Fade the flare as the sun leaves the screen.
+        let edge = sun_position.abs().max_element();
+        let edge_fade = 1.0 - ((edge - 0.8) / 0.4).clamp(0.0, 1.0);
+        let (width, height) = target.get_dimensions();
+        let uniforms = uniform! {
+            sun_position: sun_position.to_array(),
+            aspect_ratio: width as f32 / height.max(1) as f32,
+            star_color: star_color,
+            intensity: settings.flare_intensity * self.visibility * edge_fade,
+        };
+        let params = DrawParameters {
+            blend: additive,
+            ..Default::default()
+        };
+        target.draw(&self.flare, triangles, flare_program, &uniforms, &params)?;
+        Ok(())
+    }
+
+    /// Draws a small quad just in front of the sun's surface twice without touching the color
+    /// buffer: once depth tested and once not. The ratio of samples is the visible fraction.
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "camera state is passed like the other renderers"
+    )]
+    fn query_occlusion(
+        &self,
+        display: &Display<WindowSurface>,
+        target: &mut impl Surface,
+        program: &Program,
+        sun: &BodyDefinition,
+        view: Mat4,
+        perspective: Mat4,
+        camera_position: Vec3,
+    ) -> Result<OcclusionQueries> {
+        let queries = OcclusionQueries {
+            visible: SamplesPassedQuery::new(display)?,
+            total: SamplesPassedQuery::new(display)?,
+        };
+        let toward_camera = (camera_position - sun.position).normalize_or_zero();
+        let uniforms = uniform! {
+            center: (sun.position + toward_camera * sun.radius * 1.01).to_array(),
+            size: sun.radius * 0.5,
+            radius: sun.radius,
+            extent: CORONA_EXTENT,
+            view: view.to_cols_array_2d(),
+            perspective: perspective.to_cols_array_2d(),
+            star_color: [0.0f32; 3],
+            intensity: 0.0f32,
+            time: 0.0f32,
+        };
+        for (query, test) in [
+            (&queries.visible, glium::DepthTest::IfLess),
+            (&queries.total, glium::DepthTest::Overwrite),
+        ] {
+            let params = DrawParameters {
+                depth: glium::Depth {
+                    test,
+                    write: false,
+                    ..Default::default()
+                },
+                color_mask: (false, false, false, false),
+                samples_passed_query: Some(query.into()),
+                ..Default::default()
+            };
+            target.draw(
+                &self.quad,
+                NoIndices(PrimitiveType::TrianglesList),
+                program,
+                &uniforms,
+                &params,
+            )?;
+        }
+        Ok(queries)
+    }
+}
@@ -0,0 +1,306 @@
+//! The built-in solar system.
+//!
+//! Distances and radii are in scene units and not to scale: one astronomical unit is [`AU`] units
+//! and radii are exaggerated so the planets are visible from a distance. Masses are not to scale
+//! either. Only the sun has its real mass; every other body gets the mass that makes its sphere of
+//! influence [`SOI_RADII`] of its radii wide, so there is room to orbit it between its surface and
+//! its neighbours. The real masses cannot work at these radii: the earth's Hill sphere, for one,
+//! is about as wide as its drawn radius. Texture paths follow the
+//! naming of the freely available Solar System Scope 2k texture pack placed in
+//! `assets/textures/`.
+//!
+//! The planets move on their real mean orbits with elements at the J2000 epoch, which
+//! [`planet_orbits`] also hands to transfer planning, and the moon circles the earth at a scene
+//! distance. Comets move on their real orbits as well.
+
+use glam::Vec3;
+
+use crate::{
+    atmosphere::Atmosphere,
+    body::{BodyDefinition, BodyOrbit, BodyTextures, Rings},
+    celestial::ecliptic_to_scene,
+    comet::CometDefinition,
+    kepler::{GM_SUN, OrbitalElements},
+};
+
+/// Scene units per astronomical unit.
+pub const AU: f32 = 150.0;
+
+const TEXTURE_DIR: &str = "assets/textures/";
+
+/// Radius of each sphere of influence in body radii.
+const SOI_RADII: f64 = 8.0;
+
+/// Gravitational parameter that gives a body of `radius` at `distance` from a parent with
+/// gravitational parameter `parent_gm` a sphere of influence of [`SOI_RADII`] radii, from
+/// `r_soi = d (m / M)^(2/5)`. Lengths in scene units.
+fn gm_for_soi(radius: f32, distance: f32, parent_gm: f64) -> f64 {
+    parent_gm * (SOI_RADII * radius as f64 / distance as f64).powf(2.5)
+}
+
+fn texture(file: &str) -> Option<String> {
+    Some(format!("{TEXTURE_DIR}{file}"))
+}
+
+/// Where a body on `elements` around a parent at `parent_position` is at J2000, in scene units.
+fn position_at_epoch(elements: &OrbitalElements, parent_gm: f64, parent_position: Vec3) -> Vec3 {
+    parent_position + ecliptic_to_scene(elements.state_vector(parent_gm).0) * AU
+}
+
+fn planet(orbit: &PlanetOrbit, radius: f32, axial_tilt_deg: f32, albedo: &str) -> BodyDefinition {
+    let distance = orbit.elements.semi_major_axis as f32 * AU;
+    BodyDefinition {
+        name: orbit.name.to_owned(),
+        radius,
+        position: position_at_epoch(&orbit.elements, GM_SUN, Vec3::ZERO),
+        orbit: Some(BodyOrbit {
+            parent: "Sun".to_owned(),
+            elements: orbit.elements,
+        }),
+        gm: gm_for_soi(radius, distance, GM_SUN),
+        axial_tilt_deg,
+        textures: BodyTextures {
+            albedo: texture(albedo),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+pub fn solar_system() -> Vec<BodyDefinition> {
+    let orbits = planet_orbits();
+    let earth = BodyDefinition {
+        textures: BodyTextures {
+            albedo: texture("2k_earth_daymap.jpg"),
+            normal: texture("2k_earth_normal_map.png"),
+            specular: texture("2k_earth_specular_map.png"),
+            night: texture("2k_earth_nightmap.jpg"),
+        },
+        atmosphere: Some(Atmosphere::earth()),
+        ..planet(&orbits[2], 1.5, 23.44, "2k_earth_daymap.jpg")
+    };
+    // A circular orbit 6 units out, tilted like the real one.
+    let moon_orbit = OrbitalElements {
+        semi_major_axis: 6.0 / AU as f64,
+        eccentricity: 0.0,
+        inclination: 5.145f64.to_radians(),
+        longitude_of_ascending_node: 0.0,
+        argument_of_periapsis: 0.0,
+        mean_anomaly: 0.0,
+    };
+    let moon = BodyDefinition {
+        name: "Moon".to_owned(),
+        radius: 0.4,
+        position: position_at_epoch(&moon_orbit, earth.gm, earth.position),
+        orbit: Some(BodyOrbit {
+            parent: "Earth".to_owned(),
+            elements: moon_orbit,
+        }),
+        gm: gm_for_soi(0.4, 6.0, earth.gm),
+        axial_tilt_deg: 6.68,
+        textures: BodyTextures {
+            albedo: texture("2k_moon.jpg"),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    vec![
+        BodyDefinition {
+            name: "Sun".to_owned(),
+            radius: 12.0,
+            position: Vec3::ZERO,
+            gm: GM_SUN,
+            axial_tilt_deg: 7.25,
+            emissive: true,
+            temperature: Some(5772.0),
+            ..Default::default()
+        },
+        planet(&orbits[0], 0.6, 0.03, "2k_mercury.jpg"),
+        BodyDefinition {
+            atmosphere: Some(Atmosphere::venus()),
+            ..planet(&orbits[1], 1.4, 177.4, "2k_venus_surface.jpg")
+        },
+        earth,
+        moon,
+        BodyDefinition {
+            atmosphere: Some(Atmosphere::mars()),
+            ..planet(&orbits[3], 0.8, 25.19, "2k_mars.jpg")
+        },
+        planet(&orbits[4], 6.0, 3.13, "2k_jupiter.jpg"),
+        BodyDefinition {
+            rings: Some(Rings {
+                inner_radius: 1.24,
+                outer_radius: 2.27,
+                texture: texture("2k_saturn_ring_alpha.png"),
+                color: [1.0, 1.0, 1.0],
+                opacity: 1.0,
+            }),
+            ..planet(&orbits[5], 5.0, 26.73, "2k_saturn.jpg")
+        },
+        BodyDefinition {
+            rings: Some(Rings {
+                inner_radius: 1.64,
+                outer_radius: 2.0,
+                texture: None,
+                color: [0.25, 0.25, 0.25],
+                opacity: 0.3,
+            }),
+            ..planet(&orbits[6], 2.5, 97.77, "2k_uranus.jpg")
+        },
+        planet(&orbits[7], 2.4, 28.32, "2k_neptune.jpg"),
+    ]
+}
+
+/// Elements at J2000 of a comet, with angles in degrees.
+fn comet(
+    name: &str,
+    semi_major_axis: f64,
+    eccentricity: f64,
+    inclination_deg: f64,
+    node_deg: f64,
+    periapsis_deg: f64,
+    mean_anomaly_deg: f64,
+) -> CometDefinition {
+    CometDefinition {
+        name: name.to_owned(),
+        elements: OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            inclination: inclination_deg.to_radians(),
+            longitude_of_ascending_node: node_deg.to_radians(),
+            argument_of_periapsis: periapsis_deg.to_radians(),
+            mean_anomaly: mean_anomaly_deg.to_radians(),
+        },
+        coma_radius: 0.6,
+        ion_color: [0.25, 0.45, 1.0],
+        dust_color: [1.0, 0.85, 0.6],
+        brightness: 1.0,
+    }
+}
+
+pub fn comets() -> Vec<CometDefinition> {
+    vec![
+        comet("1P/Halley", 17.834, 0.96714, 162.26, 58.42, 111.33, 66.4),
+        comet("2P/Encke", 2.215, 0.8471, 11.77, 334.57, 186.52, 284.6),
+        comet(
+            "C/1995 O1 Hale-Bopp",
+            186.5,
+            0.9951,
+            89.43,
+            282.47,
+            130.59,
+            0.39,
+        ),
+    ]
+}
+
+/// Mean heliocentric orbit of a planet, which it moves on in the scene and in transfer planning.
+#[derive(Clone, Debug)]
+pub struct PlanetOrbit {
+    pub name: &'static str,
+    pub elements: OrbitalElements,
+}
+
+/// Keplerian elements of the planets at J2000 (Standish, "Keplerian Elements for Approximate
+/// Positions of the Major Planets"). Earth's are those of the Earth-Moon barycenter.
+pub fn planet_orbits() -> Vec<PlanetOrbit> {
+    vec![
+        planet_orbit(
+            "Mercury",
+            0.387_099_27,
+            0.205_635_93,
+            7.004_979,
+            252.250_324,
+            77.457_796,
+            48.330_766,
+        ),
+        planet_orbit(
+            "Venus",
+            0.723_335_66,
+            0.006_776_72,
+            3.394_676,
+            181.979_100,
+            131.602_467,
+            76.679_843,
+        ),
+        planet_orbit(
+            "Earth",
+            1.000_002_61,
+            0.016_711_23,
+            -0.000_015,
+            100.464_572,
+            102.937_682,
+            0.0,
+        ),
+        planet_orbit(
+            "Mars",
+            1.523_710_34,
+            0.093_394_10,
+            1.849_691,
+            -4.553_432,
+            -23.943_630,
+            49.559_539,
+        ),
+        planet_orbit(
+            "Jupiter",
+            5.202_887,
+            0.048_386_24,
+            1.304_397,
+            34.396_441,
+            14.728_480,
+            100.473_909,
+        ),
+        planet_orbit(
+            "Saturn",
+            9.536_675_94,
+            0.053_861_79,
+            2.485_992,
+            49.954_244,
+            92.598_878,
+            113.662_424,
+        ),
+        planet_orbit(
+            "Uranus",
+            19.189_164_64,
+            0.047_257_44,
+            0.772_638,
+            313.238_105,
+            170.954_276,
+            74.016_925,
+        ),
+        planet_orbit(
+            "Neptune",
+            30.069_922_76,
+            0.008_590_48,
+            1.770_043,
+            -55.120_030,
+            44.964_762,
+            131.784_226,
+        ),
+    ]
+}
+
+/// Elements from mean longitude, longitude of perihelion and longitude of the ascending node,
+/// with angles in degrees.
+fn planet_orbit(
+    name: &'static str,
+    semi_major_axis: f64,
+    eccentricity: f64,
+    inclination_deg: f64,
+    mean_longitude_deg: f64,
+    perihelion_longitude_deg: f64,
+    node_deg: f64,
+) -> PlanetOrbit {
+    PlanetOrbit {
+        name,
+        elements: OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            inclination: inclination_deg.to_radians(),
+            longitude_of_ascending_node: node_deg.to_radians(),
+            argument_of_periapsis: (perihelion_longitude_deg - node_deg).to_radians(),
+            mean_anomaly: (mean_longitude_deg - perihelion_longitude_deg).to_radians(),
+        },
+    }
+}
@@ -0,0 +1,544 @@
+//! Predicted trajectories drawn as lines through the scene.
+//!
+//! A craft's future path is found with patched conics: it follows a Kepler orbit around the body
+//! whose sphere of influence it is in, and switches to the parent or a child body's orbit where
+//! it crosses a sphere's edge. Planned maneuver nodes change the velocity along the way. Each conic
+//! is relative to its body, which moves on along its own orbit meanwhile, and the state is handed
+//! over between bodies in heliocentric terms at the moment of the crossing.
+//!
+//! The segments around a body are drawn around where it is when the craft enters its sphere of
+//! influence, so a path around a planet shows the shape of the orbit instead of being smeared
+//! along the planet's motion. The segment after an escape or encounter then starts from where the
+//! bodies have moved to, and event markers sit on the segment they end.
+//!
+//! Paths are collected in heliocentric ecliptic AU each frame and drawn with the overlay program
+//! as real geometry, so bodies in front of them hide them.
+
+use anyhow::{Result, anyhow};
+use glam::{DVec3, Mat4};
+use glium::{
+    Display, DrawParameters, Program, Surface, VertexBuffer,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+};
+
+use crate::{
+    celestial::ecliptic_to_scene,
+    gravity::GravityField,
+    kepler::OrbitalElements,
+    maneuver::{ManeuverNode, burn_vector},
+    overlays::LineVertex,
+    system::AU,
+};
+
+/// Segments of a drawn conic.
+const CONIC_SEGMENTS: usize = 512;
+/// Limits on the patched conic search.
+const MAX_PATCHES: usize = 12;
+const MAX_STEPS: usize = 20_000;
+/// Days ahead to predict.
+const HORIZON: f64 = 3650.0;
+/// Steps per radian of the local orbital time scale, which sets how smooth the path is.
+const STEPS_PER_RADIAN: f64 = 16.0;
+/// Bisections to locate a sphere of influence crossing.
+const CROSSING_ITERATIONS: usize = 40;
+
+/// Points of the osculating conic of a body at `position` moving at `velocity` around a central
+/// body at `center` with gravitational parameter `mu`, clipped at `max_radius` from the center.
+/// A radial state has no conic and gives no points.
+pub fn conic_path(
+    position: DVec3,
+    velocity: DVec3,
+    center: DVec3,
+    mu: f64,
+    max_radius: f64,
+) -> Vec<DVec3> {
+    let Some(elements) = OrbitalElements::from_state_vector(position - center, velocity, mu) else {
+        return Vec::new();
+    };
+    elements
+        .conic_points(CONIC_SEGMENTS, max_radius)
+        .into_iter()
+        .map(|point| center + point)
+        .collect()
+}
+
+/// Points around an orbit relative to its central body, clipped at `max_radius`.
+pub fn orbit_path(elements: &OrbitalElements, max_radius: f64) -> Vec<DVec3> {
+    elements.conic_points(CONIC_SEGMENTS, max_radius)
+}
+
+/// Part of a patched trajectory spent around one body.
+pub struct ConicSegment {
+    /// Index of the central body in the gravity field.
+    pub body: usize,
+    /// Heliocentric ecliptic AU.
+    pub points: Vec<DVec3>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    /// Entering a body's sphere of influence, passing its center at `periapsis` AU.
+    Encounter {
+        periapsis: f64,
+    },
+    /// Leaving a body's sphere of influence.
+    Escape,
+    /// Hitting a body's surface, which ends the prediction.
+    Impact,
+    Maneuver,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TrajectoryEvent {
+    pub kind: EventKind,
+    /// Index of the body the event concerns, or the central body for maneuvers.
+    pub body: usize,
+    /// Days from now.
+    pub time: f64,
+    /// Where the event is drawn, on the segment it ends.
+    pub position: DVec3,
+}
+
+#[derive(Default)]
+pub struct PatchedTrajectory {
+    pub segments: Vec<ConicSegment>,
+    pub events: Vec<TrajectoryEvent>,
+}
+
+/// Predicts the path of a craft at `position` moving at `velocity` through the spheres of
+/// influence of `field`, from the field's time on, burning at each of `nodes`. The prediction ends
+/// on impact, on a radial path, after one revolution of a closed orbit once every node is done, or
+/// at the limits above.
+pub fn patched_conics(
+    field: &GravityField,
+    position: DVec3,
+    velocity: DVec3,
+    nodes: &[ManeuverNode],
+) -> PatchedTrajectory {
+    let mut trajectory = PatchedTrajectory::default();
+    let now = field.days;
+    let Some(mut center) = field.dominant(position, now) else {
+        return trajectory;
+    };
+    let mut nodes = nodes.to_vec();
+    nodes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    let mut nodes = nodes.into_iter().peekable();
+    let (mut position, mut velocity) = (position, velocity);
+    let mut time = 0.0;
+    let mut steps = 0;
+    // Body the last segment went around and where it was drawn.
+    let mut anchored: Option<(usize, DVec3)> = None;
+
+    'patches: while trajectory.segments.len() < MAX_PATCHES {
+        let body = &field.attractors[center];
+        // A radial path has no conic to follow, so the prediction stops there.
+        let Some(elements) = field.orbit_around(center, position, velocity, now + time) else {
+            break;
+        };
+        let epoch = time;
+        let period = elements.period(body.gm);
+        let anchor = match anchored {
+            Some((body, anchor)) if body == center => anchor,
+            _ => field.state(center, now + time).0,
+        };
+        anchored = Some((center, anchor));
+        // State relative to the body `t` days from now, and the heliocentric one.
+        let relative_at = |t: f64| elements.advanced(t - epoch, body.gm).state_vector(body.gm);
+        let state_at = |t: f64| {
+            let (r, v) = relative_at(t);
+            let (center_position, center_velocity) = field.state(center, now + t);
+            (center_position + r, center_velocity + v)
+        };
+        let children: Vec<_> = (0..field.attractors.len())
+            .filter(|&index| field.attractors[index].parent == Some(center))
+            .collect();
+        // Child `child` relative to the body `t` days from now.
+        let child_at = |child: usize, t: f64| {
+            let (position, velocity) = field.state(child, now + t);
+            let (center_position, center_velocity) = field.state(center, now + t);
+            (position - center_position, velocity - center_velocity)
+        };
+        // What ends the current conic at `point` relative to the body at `t`, if anything.
+        let crossing = |t: f64, point: DVec3| {
+            let r = point.length();
+            if r < body.radius {
+                return Some((EventKind::Impact, center));
+            }
+            if r > body.sphere_of_influence {
+                return Some((EventKind::Escape, center));
+            }
+            children
+                .iter()
+                .find(|&&child| {
+                    point.distance(child_at(child, t).0)
+                        < field.attractors[child].sphere_of_influence
+                })
+                .map(|&child| (EventKind::Encounter { periapsis: 0.0 }, child))
+        };
+
+        let mut points = vec![anchor + relative_at(time).0];
+        loop {
+            steps += 1;
+            if steps > MAX_STEPS || time >= HORIZON {
+                trajectory.segments.push(ConicSegment {
+                    body: center,
+                    points,
+                });
+                break 'patches;
+            }
+            // Steps short against the orbit, and against the time it could take to reach the
+            // nearest sphere of influence so that small ones are not stepped over.
+            let (relative, relative_velocity) = relative_at(time);
+            let r = relative.length();
+            let timescale = (r * r * r / body.gm).sqrt();
+            let speed = relative_velocity.length().max(f64::MIN_POSITIVE);
+            let reach = children
+                .iter()
+                .map(|&child| {
+                    let (child_position, child_velocity) = child_at(child, time);
+                    let gap = relative.distance(child_position)
+                        - field.attractors[child].sphere_of_influence;
+                    let closing = relative_velocity.distance(child_velocity);
+                    gap / closing.max(f64::MIN_POSITIVE)
+                })
+                .fold((body.sphere_of_influence - r) / speed, f64::min);
+            let mut dt = (timescale / STEPS_PER_RADIAN)
+                .min((0.5 * reach).max(timescale / 1000.0))
+                .min(HORIZON - time);
+            if let Some(node) = nodes.peek() {
+                dt = dt.min((node.time - time).max(0.0));
+            }
+
+            if let Some((kind, other)) = crossing(time + dt, relative_at(time + dt).0) {
+                let (mut before, mut after) = (time, time + dt);
+                for _ in 0..CROSSING_ITERATIONS {
+                    let middle = 0.5 * (before + after);
+                    if crossing(middle, relative_at(middle).0).is_some() {
+                        after = middle;
+                    } else {
+                        before = middle;
+                    }
+                }
+                time = after;
+                (position, velocity) = state_at(time);
+                let marker = anchor + relative_at(time).0;
+                points.push(marker);
+                trajectory.segments.push(ConicSegment {
+                    body: center,
+                    points,
+                });
+                let kind = match kind {
+                    EventKind::Encounter { .. } => EventKind::Encounter {
+                        // Heading straight for the center passes it at no distance.
+                        periapsis: field
+                            .orbit_around(other, position, velocity, now + time)
+                            .map_or(0.0, |orbit| orbit.periapsis()),
+                    },
+                    kind => kind,
+                };
+                trajectory.events.push(TrajectoryEvent {
+                    kind,
+                    body: other,
+                    time,
+                    position: marker,
+                });
+                center = match kind {
+                    EventKind::Encounter { .. } => other,
+                    EventKind::Escape => match body.parent {
+                        Some(parent) => parent,
+                        None => break 'patches,
+                    },
+                    _ => break 'patches,
+                };
+                continue 'patches;
+            }
+
+            time += dt;
+            let (relative, relative_velocity) = relative_at(time);
+            points.push(anchor + relative);
+            if let Some(node) = nodes.next_if(|node| node.time <= time) {
+                (position, velocity) = state_at(time);
+                velocity += burn_vector(relative, relative_velocity, node.delta_v);
+                trajectory.segments.push(ConicSegment {
+                    body: center,
+                    points,
+                });
+                trajectory.events.push(TrajectoryEvent {
+                    kind: EventKind::Maneuver,
+                    body: center,
+                    time,
+                    position: anchor + relative,
+                });
+                continue 'patches;
+            }
+            if nodes.peek().is_none() && period.is_some_and(|period| time - epoch >= period) {
+                trajectory.segments.push(ConicSegment {
+                    body: center,
+                    points,
+                });
+                break 'patches;
+            }
+        }
+    }
+    trajectory
+}
+
+pub struct TrajectoryRenderer {
+    vertices: Vec<LineVertex>,
+    vertex_buffer: VertexBuffer<LineVertex>,
+}
+
+impl TrajectoryRenderer {
+    pub fn new(display: &Display<WindowSurface>) -> Result<Self> {
+        Ok(Self {
+            vertices: Vec::new(),
+            vertex_buffer: VertexBuffer::empty_dynamic(display, 1024)?,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Adds a path through `points`, in heliocentric ecliptic AU. `color` is sRGB with alpha.
+    pub fn add_path(&mut self, points: &[DVec3], color: [f32; 4]) {
+        for pair in points.windows(2) {
+            for &point in pair {
+                self.vertices.push(LineVertex {
+                    position: (ecliptic_to_scene(point) * AU).to_array(),
+                    color,
+                });
+            }
+        }
+    }
+
+    /// Draws the paths added since the last [`TrajectoryRenderer::clear`]. The vertex buffer
+    /// grows to the largest set of paths seen so far.
+    pub fn draw(
+        &mut self,
+        display: &Display<WindowSurface>,
+        frame: &mut impl Surface,
+        program: &Program,
+        view: Mat4,
+        perspective: Mat4,
+    ) -> Result<()> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+        if self.vertices.len() > self.vertex_buffer.len() {
+            let capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = VertexBuffer::empty_dynamic(display, capacity)?;
+        }
+        let vertices = self
+            .vertex_buffer
+            .slice(0..self.vertices.len())
+            .ok_or_else(|| anyhow!("trajectory buffer is too small"))?;
+        vertices.write(&self.vertices);
+
+        let uniforms = uniform! {
+            view: view.to_cols_array_2d(),
+            perspective: perspective.to_cols_array_2d(),
+            on_sky: false,
+        };
+        let params = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        frame.draw(
+            vertices,
+            NoIndices(PrimitiveType::LinesList),
+            program,
+            &uniforms,
+            &params,
+        )?;
+        Ok(())
+    }
+}
+
+/// Object whose predicted orbit is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrbitTarget {
+    None,
+    Spacecraft,
+    /// Index into the comets.
+    Comet(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, TAU};
+
+    use super::*;
+    use crate::{
+        body::{BodyDefinition, BodyOrbit},
+        kepler::GM_SUN,
+        maneuver::{hohmann_delta_v, hohmann_transfer},
+    };
+
+    fn sun() -> BodyDefinition {
+        BodyDefinition {
+            name: "Sun".to_owned(),
+            radius: 12.0,
+            gm: GM_SUN,
+            ..Default::default()
+        }
+    }
+
+    /// Planet on a circular ecliptic orbit of `distance` AU, at `longitude` radians at J2000, with
+    /// the mass that makes its sphere of influence `sphere_of_influence` AU wide.
+    fn planet(
+        name: &str,
+        distance: f64,
+        longitude: f64,
+        sphere_of_influence: f64,
+    ) -> BodyDefinition {
+        BodyDefinition {
+            name: name.to_owned(),
+            radius: 0.3,
+            gm: GM_SUN * (sphere_of_influence / distance).powf(2.5),
+            orbit: Some(BodyOrbit {
+                parent: "Sun".to_owned(),
+                elements: OrbitalElements {
+                    semi_major_axis: distance,
+                    eccentricity: 0.0,
+                    inclination: 0.0,
+                    longitude_of_ascending_node: 0.0,
+                    argument_of_periapsis: 0.0,
+                    mean_anomaly: longitude,
+                },
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn hohmann_duration(r1: f64, r2: f64) -> f64 {
+        PI * ((r1 + r2).powi(3) / (8.0 * GM_SUN)).sqrt()
+    }
+
+    fn mean_motion(distance: f64) -> f64 {
+        (GM_SUN / distance.powi(3)).sqrt()
+    }
+
+    fn kinds(trajectory: &PatchedTrajectory) -> Vec<(EventKind, usize)> {
+        trajectory
+            .events
+            .iter()
+            .map(|event| match event.kind {
+                EventKind::Encounter { .. } => {
+                    (EventKind::Encounter { periapsis: 0.0 }, event.body)
+                }
+                kind => (kind, event.body),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hohmann_node_leads_to_an_encounter() {
+        let bodies = [sun(), planet("Mars", 1.524, 2.0, 0.05)];
+        let field = GravityField::new(&bodies);
+        let mars = field.find("Mars").unwrap();
+        let position = DVec3::X;
+        let velocity = DVec3::Y * GM_SUN.sqrt();
+
+        let transfer = hohmann_transfer(&field, position, velocity, mars).unwrap();
+        assert!((transfer.duration - hohmann_duration(1.0, 1.524)).abs() < 1e-9);
+        let trajectory = patched_conics(&field, position, velocity, &[transfer.departure]);
+        let sun = field.find("Sun").unwrap();
+        assert_eq!(
+            kinds(&trajectory)[..2],
+            [
+                (EventKind::Maneuver, sun),
+                (EventKind::Encounter { periapsis: 0.0 }, mars)
+            ]
+        );
+        let encounter = &trajectory.events[1];
+        // The sphere of influence is entered a few weeks before the transfer's apoapsis.
+        let arrival = transfer.departure.time + transfer.duration;
+        assert!(encounter.time < arrival && encounter.time > arrival - 40.0);
+        let EventKind::Encounter { periapsis } = encounter.kind else {
+            unreachable!()
+        };
+        assert!(periapsis < 0.05, "passes {periapsis} AU from the center");
+        assert_eq!(trajectory.segments[2].body, mars);
+    }
+
+    #[test]
+    fn craft_escapes_earth_crosses_to_mars_and_encounters_it() {
+        let earth_soi = 0.01;
+        let field = GravityField::new(&[sun(), planet("Earth", 1.0, 0.0, earth_soi)]);
+        let earth = &field.attractors[1];
+        // A hyperbola around the earth whose outgoing asymptote points prograde, at about the
+        // hyperbolic excess speed of a transfer to Mars.
+        let excess = hohmann_delta_v(GM_SUN, 1.0, 1.524).0;
+        let periapsis = 0.4 * earth_soi;
+        let eccentricity = 1.0 + periapsis * excess * excess / earth.gm;
+        let (sin, cos) = (PI / 2.0 - (-1.0 / eccentricity).acos()).sin_cos();
+        let direction = DVec3::new(cos, sin, 0.0);
+        let speed = (excess * excess + 2.0 * earth.gm / periapsis).sqrt();
+        let position = earth.position + direction * periapsis;
+        let velocity = earth.velocity + DVec3::Z.cross(direction) * speed;
+
+        // Out of the earth's sphere the craft follows a heliocentric conic, and Mars waits at its
+        // aphelion.
+        let trajectory = patched_conics(&field, position, velocity, &[]);
+        let escape = trajectory.events[0];
+        assert_eq!(escape.kind, EventKind::Escape);
+        let (relative, relative_velocity) = field
+            .orbit_around(1, position, velocity, 0.0)
+            .unwrap()
+            .advanced(escape.time, earth.gm)
+            .state_vector(earth.gm);
+        let (earth_position, earth_velocity) = field.state(1, escape.time);
+        let leg = OrbitalElements::from_state_vector(
+            earth_position + relative,
+            earth_velocity + relative_velocity,
+            GM_SUN,
+        )
+        .unwrap();
+        let mars_distance = leg.apoapsis().unwrap();
+        let aphelion_time =
+            escape.time + (PI - leg.mean_anomaly).rem_euclid(TAU) / leg.mean_motion(GM_SUN);
+        let aphelion = -leg.perifocal_basis().0;
+        let mars_longitude =
+            aphelion.y.atan2(aphelion.x) - mean_motion(mars_distance) * aphelion_time;
+
+        let bodies = [
+            sun(),
+            planet("Earth", 1.0, 0.0, earth_soi),
+            planet("Mars", mars_distance, mars_longitude, 0.05),
+        ];
+        let field = GravityField::new(&bodies);
+        let (sun, earth, mars) = (0, 1, 2);
+        let trajectory = patched_conics(&field, position, velocity, &[]);
+        assert_eq!(
+            kinds(&trajectory)[..2],
+            [
+                (EventKind::Escape, earth),
+                (EventKind::Encounter { periapsis: 0.0 }, mars)
+            ]
+        );
+        let bodies: Vec<_> = trajectory
+            .segments
+            .iter()
+            .map(|segment| segment.body)
+            .collect();
+        assert_eq!(bodies[..3], [earth, sun, mars]);
+        let encounter = trajectory.events[1].time;
+        assert!(encounter < aphelion_time && encounter > aphelion_time - 40.0);
+
+        // The heliocentric leg starts where the craft leaves the earth's sphere, and ends where
+        // it enters Mars'.
+        let leg = &trajectory.segments[1].points;
+        let (earth_position, _) = field.state(earth, escape.time);
+        assert!((leg[0].distance(earth_position) - earth_soi).abs() < 1e-6);
+        let (mars_position, _) = field.state(mars, encounter);
+        let end = *leg.last().unwrap();
+        assert!((end.distance(mars_position) - 0.05).abs() < 1e-6);
+    }
+}